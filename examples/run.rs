@@ -0,0 +1,68 @@
+//! Runs a raw 6502 binary until it halts, then prints the final state once.
+//!
+//! ```
+//! cargo run --example run -- program.bin [load address] [max cycles] [dump start-end]
+//! ```
+//!
+//! Addresses are hexadecimal, the load address defaults to 0600 and execution
+//! starts there. The program stops on BRK, an undecodable opcode, a jump or branch
+//! to itself, or after the cycle limit (1000000 by default).
+
+use std::process;
+
+use mos_6502::emulator_from_program;
+
+const DEFAULT_LOAD_ADDRESS: u16 = 0x0600;
+const DEFAULT_MAX_CYCLES: u64 = 1_000_000;
+
+fn parse_hex(text: &str) -> u16 {
+    u16::from_str_radix(text.trim_start_matches("0x"), 16).unwrap_or_else(|_| {
+        eprintln!("Invalid hex address: {text}");
+        process::exit(1);
+    })
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(path) = args.first() else {
+        eprintln!("Usage: run <program.bin> [load address] [max cycles] [dump start-end]");
+        process::exit(1);
+    };
+    let load_address = args
+        .get(1)
+        .map_or(DEFAULT_LOAD_ADDRESS, |arg| parse_hex(arg));
+    let max_cycles = args.get(2).map_or(DEFAULT_MAX_CYCLES, |arg| {
+        arg.parse().unwrap_or_else(|_| {
+            eprintln!("Invalid cycle limit: {arg}");
+            process::exit(1);
+        })
+    });
+    let dump = args.get(3).map(|arg| match arg.split_once('-') {
+        Some((start, end)) => (parse_hex(start), parse_hex(end)),
+        None => (parse_hex(arg), parse_hex(arg)),
+    });
+
+    let program = std::fs::read(path).unwrap_or_else(|err| {
+        eprintln!("Failed to read {path}: {err}");
+        process::exit(1);
+    });
+
+    let mut cpu =
+        emulator_from_program(&program, load_address, load_address).unwrap_or_else(|err| {
+            eprintln!("Failed to load {path}: {err}");
+            process::exit(1);
+        });
+    cpu.s = 0xFF;
+
+    let reason = cpu.run(max_cycles);
+
+    println!("Stopped: {reason:?} after {} cycles", cpu.cycles());
+    print!("{cpu:?}");
+    if let Some((start, end)) = dump {
+        let bytes: Vec<u8> = (start..=end).map(|addr| cpu.read_memory(addr)).collect();
+        for (row, bytes) in bytes.chunks(16).enumerate() {
+            let bytes: Vec<String> = bytes.iter().map(|byte| format!("{byte:02X}")).collect();
+            println!("{:04X}: {}", start as usize + row * 16, bytes.join(" "));
+        }
+    }
+}
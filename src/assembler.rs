@@ -0,0 +1,547 @@
+#[cfg(feature = "no_std")]
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+#[cfg(feature = "no_std")]
+use hashbrown::HashMap;
+#[cfg(not(feature = "no_std"))]
+use std::collections::HashMap;
+
+use crate::{
+    error::{AsmError, SourcePos},
+    instruction::{AddressingType, Instruction},
+    memory_bus::MemoryBus,
+    opcode_decoders::{
+        ArgumentType, INSTRUCTIONS_ADDRESSING, INSTRUCTIONS_ADDRESSING_MODE, INSTRUCTIONS_MNEMONIC,
+    },
+    util::bytes_from_dword,
+};
+
+// Operand syntax as written in the source, independent of the opcode tables
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum SyntaxMode {
+    Implied,
+    Accumulator,
+    Relative,
+    Indirect,
+    Addressing(AddressingType),
+}
+
+enum Operand {
+    Implied,
+    Accumulator,
+    Immediate(u16),
+    Address(u16, bool), // Value, written as zero page
+    XIndexed(u16, bool),
+    YIndexed(u16, bool),
+    XIndexedIndirect(u16),
+    IndirectYIndexed(u16),
+    Indirect(u16),
+}
+
+pub(crate) fn syntax_mode(instr: Instruction) -> SyntaxMode {
+    match instr {
+        Instruction::Bcc
+        | Instruction::Bcs
+        | Instruction::Beq
+        | Instruction::Bne
+        | Instruction::Bmi
+        | Instruction::Bpl
+        | Instruction::Bvc
+        | Instruction::Bvs => SyntaxMode::Relative,
+        Instruction::Jmp | Instruction::Jsr => SyntaxMode::Addressing(AddressingType::Absolute),
+        Instruction::JmpIndirect => SyntaxMode::Indirect,
+        Instruction::AslAccumulator
+        | Instruction::LsrAccumulator
+        | Instruction::RolAccumulator
+        | Instruction::RorAccumulator => SyntaxMode::Accumulator,
+        _ => match INSTRUCTIONS_ADDRESSING_MODE.get(&instr) {
+            Some(addressing_type) => SyntaxMode::Addressing(*addressing_type),
+            None => SyntaxMode::Implied,
+        },
+    }
+}
+
+pub(crate) fn mnemonic(instr: Instruction) -> &'static str {
+    INSTRUCTIONS_MNEMONIC[&instr]
+}
+
+lazy_static! {
+    static ref INSTRUCTIONS_BY_MNEMONIC: HashMap<(&'static str, SyntaxMode), Instruction> = {
+        let mut m = HashMap::new();
+        for opcode in 0..=u8::MAX {
+            if let Ok(instr) = Instruction::try_from(opcode) {
+                m.insert((mnemonic(instr), syntax_mode(instr)), instr);
+            }
+        }
+
+        m
+    };
+}
+
+// Label addresses seen by one line; `fallback` stands in for labels that are
+// not defined yet while instruction sizes are being worked out
+struct Labels<'a> {
+    addresses: &'a HashMap<String, u16>,
+    fallback: Option<u16>,
+}
+
+fn is_label(text: &str) -> bool {
+    let mut chars = text.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_value(text: &str, labels: &Labels) -> Result<(u16, bool), AsmError> {
+    let text = text.trim();
+    let invalid = || AsmError::InvalidOperand(text.to_string(), SourcePos::default());
+
+    // Labels always use absolute addressing so a line has the same size in both passes
+    if is_label(text) {
+        let name = text.to_uppercase();
+        return match labels.addresses.get(&name).copied().or(labels.fallback) {
+            Some(address) => Ok((address, false)),
+            None => Err(AsmError::UnknownLabel(name, SourcePos::default())),
+        };
+    }
+
+    let (value, zero_page) = if let Some(hex) = text.strip_prefix('$') {
+        let value = u32::from_str_radix(hex, 16).map_err(|_| invalid())?;
+        (value, hex.len() <= 2)
+    } else if let Some(bin) = text.strip_prefix('%') {
+        let value = u32::from_str_radix(bin, 2).map_err(|_| invalid())?;
+        (value, bin.len() <= 8)
+    } else {
+        let value = text.parse::<u32>().map_err(|_| invalid())?;
+        (value, value <= 0xFF)
+    };
+
+    let value = u16::try_from(value)
+        .map_err(|_| AsmError::OperandOutOfRange(text.to_string(), SourcePos::default()))?;
+
+    Ok((value, zero_page))
+}
+
+fn parse_operand(text: &str, labels: &Labels) -> Result<Operand, AsmError> {
+    let text = text.trim();
+    let upper = text.to_uppercase();
+
+    if text.is_empty() {
+        return Ok(Operand::Implied);
+    }
+    if upper == "A" {
+        return Ok(Operand::Accumulator);
+    }
+    if let Some(value) = text.strip_prefix('#') {
+        return Ok(Operand::Immediate(parse_value(value, labels)?.0));
+    }
+    if let Some(inner) = upper.strip_prefix('(') {
+        let inner = inner.replace(' ', "");
+        if let Some(ptr) = inner.strip_suffix(",X)") {
+            return Ok(Operand::XIndexedIndirect(parse_value(ptr, labels)?.0));
+        }
+        if let Some(ptr) = inner.strip_suffix("),Y") {
+            return Ok(Operand::IndirectYIndexed(parse_value(ptr, labels)?.0));
+        }
+        if let Some(ptr) = inner.strip_suffix(')') {
+            return Ok(Operand::Indirect(parse_value(ptr, labels)?.0));
+        }
+        return Err(AsmError::InvalidOperand(
+            text.to_string(),
+            SourcePos::default(),
+        ));
+    }
+
+    let compact = upper.replace(' ', "");
+    if let Some(base) = compact.strip_suffix(",X") {
+        let (value, zero_page) = parse_value(base, labels)?;
+        return Ok(Operand::XIndexed(value, zero_page));
+    }
+    if let Some(base) = compact.strip_suffix(",Y") {
+        let (value, zero_page) = parse_value(base, labels)?;
+        return Ok(Operand::YIndexed(value, zero_page));
+    }
+
+    let (value, zero_page) = parse_value(text, labels)?;
+    Ok(Operand::Address(value, zero_page))
+}
+
+fn zero_page_byte(value: u16, text: &str) -> Result<u16, AsmError> {
+    if value > 0xFF {
+        return Err(AsmError::OperandOutOfRange(
+            text.to_string(),
+            SourcePos::default(),
+        ));
+    }
+
+    Ok(value)
+}
+
+// Candidate encodings for an operand, preferred first
+fn candidates(operand: &Operand, text: &str) -> Result<Vec<(SyntaxMode, u16)>, AsmError> {
+    let zero_page_or_absolute = |zero_page_mode, absolute_mode, value: u16, zero_page: bool| {
+        let mut modes = Vec::new();
+        if zero_page && value <= 0xFF {
+            modes.push((SyntaxMode::Addressing(zero_page_mode), value));
+        }
+        modes.push((SyntaxMode::Addressing(absolute_mode), value));
+        modes
+    };
+
+    Ok(match *operand {
+        Operand::Implied => vec![(SyntaxMode::Implied, 0), (SyntaxMode::Accumulator, 0)],
+        Operand::Accumulator => vec![(SyntaxMode::Accumulator, 0)],
+        Operand::Immediate(value) => vec![(
+            SyntaxMode::Addressing(AddressingType::Immediate),
+            zero_page_byte(value, text)?,
+        )],
+        Operand::Address(value, zero_page) => {
+            let mut modes = vec![(SyntaxMode::Relative, value)];
+            modes.extend(zero_page_or_absolute(
+                AddressingType::ZeroPage,
+                AddressingType::Absolute,
+                value,
+                zero_page,
+            ));
+            modes
+        }
+        Operand::XIndexed(value, zero_page) => zero_page_or_absolute(
+            AddressingType::XIndexedZero,
+            AddressingType::XIndexedAbsolute,
+            value,
+            zero_page,
+        ),
+        Operand::YIndexed(value, zero_page) => zero_page_or_absolute(
+            AddressingType::YIndexedZero,
+            AddressingType::YIndexedAbsolute,
+            value,
+            zero_page,
+        ),
+        Operand::XIndexedIndirect(value) => vec![(
+            SyntaxMode::Addressing(AddressingType::XIndexedZeroIndirect),
+            zero_page_byte(value, text)?,
+        )],
+        Operand::IndirectYIndexed(value) => vec![(
+            SyntaxMode::Addressing(AddressingType::ZeroIndirectIndexed),
+            zero_page_byte(value, text)?,
+        )],
+        Operand::Indirect(value) => vec![(SyntaxMode::Indirect, value)],
+    })
+}
+
+// Errors carry their column within `line`, 0-based; `assemble` adds the line number
+fn assemble_line(line: &str, address: u16, labels: &Labels) -> Result<Vec<u8>, AsmError> {
+    let line = line.trim();
+    let (mnemonic, operand_text) = match line.split_once(char::is_whitespace) {
+        Some((mnemonic, operand)) => (mnemonic.to_uppercase(), operand.trim()),
+        None => (line.to_uppercase(), ""),
+    };
+    let at_operand = |mut error: AsmError| {
+        error.pos_mut().column = offset_in(line, operand_text);
+        error
+    };
+
+    let known_mnemonic = INSTRUCTIONS_BY_MNEMONIC.keys().any(|(m, _)| *m == mnemonic);
+    if !known_mnemonic {
+        return Err(AsmError::UnknownMnemonic(mnemonic, SourcePos::default()));
+    }
+    let operand = parse_operand(operand_text, labels).map_err(at_operand)?;
+
+    for (mode, value) in candidates(&operand, operand_text).map_err(at_operand)? {
+        let Some(instr) = INSTRUCTIONS_BY_MNEMONIC.get(&(mnemonic.as_str(), mode)) else {
+            continue;
+        };
+        let opcode: u8 = (*instr).into();
+
+        if mode == SyntaxMode::Relative {
+            let offset = value as i32 - (address as i32 + 2);
+            let offset = i8::try_from(offset).map_err(|_| {
+                at_operand(AsmError::OperandOutOfRange(
+                    operand_text.to_string(),
+                    SourcePos::default(),
+                ))
+            })?;
+
+            return Ok(vec![opcode, offset as u8]);
+        }
+
+        return Ok(match INSTRUCTIONS_ADDRESSING.get(instr) {
+            Some(ArgumentType::Byte) => vec![opcode, value as u8],
+            Some(ArgumentType::Addr) => {
+                let (low_byte, high_byte) = bytes_from_dword(value);
+                vec![opcode, low_byte, high_byte]
+            }
+            _ => vec![opcode],
+        });
+    }
+
+    Err(at_operand(AsmError::InvalidAddressingMode {
+        mnemonic,
+        operand: operand_text.to_string(),
+        pos: SourcePos::default(),
+    }))
+}
+
+// Byte offset of `inner` in `outer`, which it must be a slice of
+fn offset_in(outer: &str, inner: &str) -> usize {
+    inner.as_ptr() as usize - outer.as_ptr() as usize
+}
+
+// Assembles the instruction part of source line `number` (0-based), turning the
+// column of any error into a 1-based position in `line`
+fn assemble_source_line(
+    line: &str,
+    number: usize,
+    instruction: &str,
+    address: u16,
+    labels: &Labels,
+) -> Result<Vec<u8>, AsmError> {
+    assemble_line(instruction, address, labels).map_err(|mut error| {
+        let instruction = instruction.trim();
+        let pos = error.pos_mut();
+        pos.line = number + 1;
+        pos.column += offset_in(line, instruction) + 1;
+        error
+    })
+}
+
+// Splits a source line into an optional label definition and the instruction,
+// dropping any `;` comment
+fn split_line(line: &str) -> (Option<String>, &str) {
+    let line = line.split(';').next().unwrap_or_default().trim();
+
+    match line.split_once(':') {
+        Some((label, rest)) if is_label(label.trim()) => {
+            (Some(label.trim().to_uppercase()), rest.trim())
+        }
+        _ => (None, line),
+    }
+}
+
+/// Assembles `source`, one instruction per line, for a program placed at `origin`.
+/// Branch operands are absolute target addresses or labels. A line may start with
+/// a `label:` definition, and everything after a `;` is a comment. Labels are
+/// case-insensitive and always encoded as absolute addresses.
+pub fn assemble(source: &str, origin: u16) -> Result<Vec<u8>, AsmError> {
+    // First pass: collect label addresses, sizing lines that use labels defined later
+    let mut addresses = HashMap::new();
+    let mut address = origin;
+    for (number, line) in source.lines().enumerate() {
+        let (label, instruction) = split_line(line);
+        if let Some(label) = label {
+            if addresses.insert(label.clone(), address).is_some() {
+                let column = line.len() - line.trim_start().len() + 1;
+                let pos = SourcePos {
+                    line: number + 1,
+                    column,
+                };
+                return Err(AsmError::DuplicateLabel(label, pos));
+            }
+        }
+        if !instruction.is_empty() {
+            let labels = Labels {
+                addresses: &addresses,
+                fallback: Some(address),
+            };
+            let size = assemble_source_line(line, number, instruction, address, &labels)?.len();
+            address = address.wrapping_add(size as u16);
+        }
+    }
+
+    let labels = Labels {
+        addresses: &addresses,
+        fallback: None,
+    };
+    let mut program = Vec::new();
+    for (number, line) in source.lines().enumerate() {
+        let (_, instruction) = split_line(line);
+        if !instruction.is_empty() {
+            let address = origin.wrapping_add(program.len() as u16);
+            program.extend(assemble_source_line(
+                line,
+                number,
+                instruction,
+                address,
+                &labels,
+            )?);
+        }
+    }
+
+    Ok(program)
+}
+
+/// Assembles `source` and writes it to `mem` starting at `origin`.
+/// Returns the address following the last written byte. Fails on unmapped
+/// addresses and on ROM under `RomWritePolicy::Fault`.
+pub fn load_asm(mem: &mut MemoryBus, source: &str, origin: u16) -> Result<u16, AsmError> {
+    let program = assemble(source, origin)?;
+
+    for (offset, byte) in program.iter().enumerate() {
+        let address = origin.wrapping_add(offset as u16) as usize;
+        mem.try_write_byte(address, *byte)
+            .map_err(|error| AsmError::Write(error, SourcePos::default()))?;
+    }
+
+    Ok(origin.wrapping_add(program.len() as u16))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cpu::Cpu,
+        error::MemoryBusError,
+        memory_bus::{RomWritePolicy, MEM_SPACE_END},
+    };
+
+    #[test]
+    fn mnemonic_table_covers_opcodes() {
+        for opcode in 0..=u8::MAX {
+            if let Ok(instr) = Instruction::try_from(opcode) {
+                assert!(INSTRUCTIONS_MNEMONIC.contains_key(&instr), "{opcode:#04X}");
+                assert!(
+                    INSTRUCTIONS_BY_MNEMONIC.contains_key(&(mnemonic(instr), syntax_mode(instr)))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn assemble_addressing_modes() {
+        let program = assemble(
+            "LDA #$01
+            lda $44
+            LDA $44,X
+            LDX $44,Y
+            LDA $0044
+            LDA $1234,X
+            LDA $44,Y
+            LDA ($20,X)
+            LDA ($20),Y
+            ASL A
+            ASL
+            JMP ($1234)
+            JSR $1234
+            TAX",
+            0x0600,
+        )
+        .unwrap();
+
+        assert_eq!(
+            program,
+            vec![
+                0xA9, 0x01, 0xA5, 0x44, 0xB5, 0x44, 0xB6, 0x44, 0xAD, 0x44, 0x00, 0xBD, 0x34, 0x12,
+                0xB9, 0x44, 0x00, 0xA1, 0x20, 0xB1, 0x20, 0x0A, 0x0A, 0x6C, 0x34, 0x12, 0x20, 0x34,
+                0x12, 0xAA,
+            ]
+        );
+    }
+
+    #[test]
+    fn assemble_branches() {
+        let program = assemble("NOP\nBNE $0600\nBEQ $0610", 0x0600).unwrap();
+        assert_eq!(program, vec![0xEA, 0xD0, 0xFD, 0xF0, 0x0B]);
+
+        assert!(matches!(
+            assemble("BNE $0700", 0x0600),
+            Err(AsmError::OperandOutOfRange(_, _))
+        ));
+    }
+
+    #[test]
+    fn assemble_errors() {
+        let error = assemble("NOP\n  LDQ #$01", 0).unwrap_err();
+        assert!(matches!(&error, AsmError::UnknownMnemonic(m, _) if m == "LDQ"));
+        assert_eq!(error.pos(), SourcePos { line: 2, column: 3 });
+
+        // Zero page pointer that doesn't fit in a byte
+        let error = assemble("LDA ($1234),Y", 0).unwrap_err();
+        assert!(matches!(error, AsmError::OperandOutOfRange(_, _)));
+        assert_eq!(error.pos(), SourcePos { line: 1, column: 5 });
+
+        assert!(matches!(
+            assemble("LDA #$100", 0),
+            Err(AsmError::OperandOutOfRange(_, _))
+        ));
+        assert!(matches!(
+            assemble("LDA $10000", 0),
+            Err(AsmError::OperandOutOfRange(_, _))
+        ));
+        assert!(matches!(
+            assemble("STA #$01", 0),
+            Err(AsmError::InvalidAddressingMode { mnemonic, .. }) if mnemonic == "STA"
+        ));
+        assert!(matches!(
+            assemble("LDA #$1G", 0),
+            Err(AsmError::InvalidOperand(_, _))
+        ));
+        assert!(matches!(
+            assemble("LDA ($10", 0),
+            Err(AsmError::InvalidOperand(_, _))
+        ));
+    }
+
+    #[test]
+    fn load_asm_and_run() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, MEM_SPACE_END + 1);
+
+        let end = load_asm(&mut memory, "LDA #$01\nADC #$02\nTAX", 0x0600).unwrap();
+        assert_eq!(end, 0x0605);
+
+        let mut cpu = Cpu::new(memory);
+        cpu.set_pc(0x0600);
+        while cpu.pc < end {
+            cpu.step();
+        }
+
+        assert_eq!(cpu.x, 0x03);
+        assert_eq!(cpu.a, 0x03);
+    }
+
+    #[test]
+    fn load_asm_write_errors() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x0800);
+        memory.add_rom(0xF000, vec![0; 0x1000]).unwrap();
+        memory.set_rom_write_policy(RomWritePolicy::Fault);
+
+        assert!(matches!(
+            load_asm(&mut memory, "NOP", 0x2000),
+            Err(AsmError::Write(
+                MemoryBusError::OffsetOutOfBounds(0x2000),
+                _
+            ))
+        ));
+        assert!(matches!(
+            load_asm(&mut memory, "NOP", 0xF000),
+            Err(AsmError::Write(MemoryBusError::RomWrite(0xF000), _))
+        ));
+    }
+
+    #[test]
+    fn assemble_labels() {
+        let program = assemble(
+            "start: LDA $10 ; comment\n\
+             ; a comment line\n\
+             BEQ skip\n\
+             JMP start\n\
+             skip: NOP",
+            0x0010,
+        )
+        .unwrap();
+        assert_eq!(
+            program,
+            vec![0xA5, 0x10, 0xF0, 0x03, 0x4C, 0x10, 0x00, 0xEA]
+        );
+
+        let error = assemble("NOP\n JMP nowhere", 0).unwrap_err();
+        assert!(matches!(&error, AsmError::UnknownLabel(l, _) if l == "NOWHERE"));
+        assert_eq!(error.pos(), SourcePos { line: 2, column: 6 });
+        let error = assemble("a1: NOP\na1: NOP", 0).unwrap_err();
+        assert!(matches!(error, AsmError::DuplicateLabel(_, _)));
+        assert_eq!(error.pos(), SourcePos { line: 2, column: 1 });
+    }
+}
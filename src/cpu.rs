@@ -1,24 +1,228 @@
-use std::fmt;
+#[cfg(feature = "no_std")]
+use alloc::{boxed::Box, collections::VecDeque, format, string::String, vec, vec::Vec};
+use core::{cell::Cell, fmt};
+#[cfg(not(feature = "no_std"))]
+use std::{collections::VecDeque, io::Write};
+
+#[cfg(not(feature = "no_std"))]
+use crate::disassembler::Disassembler;
 
 use crate::{
-    error::DecodeError,
+    assembler, emulator_from_program,
+    error::{AsmError, DecodeError, MemoryBusError, StateError, StepError},
     flags_register::{FlagPosition, FlagsRegister},
-    instruction::{AddressingType, Instruction},
-    memory_bus::{MemoryBus, MEM_SPACE_END, STACK_BOTTOM},
-    opcode_decoders::{ArgumentType, INSTRUCTIONS_ADDRESSING},
+    instruction::{base_cycles, opcode_length, AddressingType, Instruction},
+    memory_bus::{Bus, MemoryBus, STACK_BOTTOM},
+    opcode_decoders::{ArgumentType, INSTRUCTIONS_ADDRESSING, INSTRUCTIONS_ADDRESSING_MODE},
+    util::{bytes_from_dword, dword_from_bytes},
 };
 
-pub struct Cpu {
-    pub address_space: MemoryBus, // TODO: replace with memory bus implementation
-    pub a: u8,                    // Accumulator register
-    pub x: u8,                    // X index register
-    pub y: u8,                    // Y index register
-    pub pc: u16,                  // Program counter
-    pub s: u8,                    // Stack pointer
-    pub p: FlagsRegister,         // Flags register
+pub const NMI_VECTOR: u16 = 0xFFFA;
+pub const RESET_VECTOR: u16 = 0xFFFC;
+pub const IRQ_VECTOR: u16 = 0xFFFE;
+
+const INTERRUPT_CYCLES: u64 = 7;
+
+const STATE_MAGIC: &[u8; 4] = b"6502";
+const STATE_VERSION: u8 = 1;
+// Header, registers, PC, cycle count, IRQ line and pending flags, then memory
+const STATE_LEN: usize = 5 + 5 + 2 + 8 + 2 + 0x10000;
+
+/// Which 6502 core the CPU emulates where their behavior differs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Variant {
+    #[default]
+    Nmos, // Original MOS 6502
+    Cmos, // WDC 65C02
+}
+
+/// Why `Cpu::run` returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    Brk,                // PC points at a BRK, which wasn't executed
+    IllegalOpcode(u8),  // PC points at an opcode that can't be decoded
+    InfiniteLoop(u16),  // An instruction jumped or branched to itself
+    CycleLimit,         // The cycle budget was used up
+    Breakpoint(u16),    // PC hit a breakpoint or a conditional breakpoint matched
+    StackOverflow,      // A push wrapped S from 0x00 to 0xFF
+    StackUnderflow,     // A pull wrapped S from 0xFF to 0x00
+    Trap { value: u8 }, // The trap set with `Cpu::set_trap` fired
+    RomWrite(u16),      // An instruction wrote to ROM under `RomWritePolicy::Fault`
+}
+
+/// What fires the trap set with `Cpu::set_trap`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrapMode {
+    Write,   // An instruction stores to the address, reporting the stored byte
+    Execute, // PC reaches the address, reporting A
+}
+
+// Undo record of one step: the state before it and the bytes its writes replaced
+struct HistoryEntry {
+    state: CpuState,
+    irq_pending: bool,
+    writes: Vec<(u16, u8)>,
+}
+
+/// Predicate checked before every instruction of `Cpu::run`
+pub type BreakCondition<B> = Box<dyn Fn(&Cpu<B>) -> bool>;
+
+/// Callback `Cpu::step` runs once the cycle counter reaches its deadline
+pub type ScheduledEvent<B> = Box<dyn FnMut(&mut Cpu<B>)>;
+
+pub struct Cpu<B: Bus = MemoryBus> {
+    address_space: B,
+    pub a: u8,            // Accumulator register
+    pub x: u8,            // X index register
+    pub y: u8,            // Y index register
+    pub pc: u16,          // Program counter
+    pub s: u8,            // Stack pointer
+    pub p: FlagsRegister, // Flags register
+    variant: Variant,
+    stack_base: usize, // Start of the stack page
+    cycles: u64,       // Cycles consumed since creation
+    instructions: u64, // Instructions executed since creation
+    flag_log: Option<Vec<FlagChange>>,
+    #[cfg(not(feature = "no_std"))]
+    trace_writer: Option<Box<dyn Write>>,
+    #[cfg(not(feature = "no_std"))]
+    trace_filter: Option<TraceFilter>,
+    on_cycle: Option<Box<dyn FnMut()>>,
+    breakpoints: Vec<u16>,
+    detect_stack_errors: bool,
+    stack_error: Option<StopReason>, // Wrap seen since the last check
+    rom_write: Option<u16>,          // First write the bus rejected in the last step
+    conditional_breakpoints: Vec<BreakCondition<B>>,
+    irq_line: bool,    // Level of the IRQ input, true when asserted
+    irq_pending: bool, // IRQ seen by the last poll, serviced before the next instruction
+    last_instruction: Option<(Instruction, Argument)>,
+    opcode_coverage: [u64; 256],    // Executions per opcode byte
+    code_range: Option<(u16, u16)>, // Inclusive, watched for self-modifying stores
+    on_code_write: Option<Box<dyn FnMut(u16)>>,
+    trap: Option<(u16, TrapMode)>,
+    trap_value: Option<u8>, // Byte stored to a write trap since the last check
+    history: VecDeque<HistoryEntry>, // Oldest first
+    history_depth: usize,   // Steps kept for `step_back`, 0 disables recording
+    strict_bcd: bool,       // Reject invalid decimal operands in `try_step`
+    trap_unimplemented: bool,
+    unimplemented: Option<Instruction>, // Reached execute's fallback arm this step
+    scheduled: Vec<(u64, ScheduledEvent<B>)>, // Sorted by deadline
+}
+
+/// Single status flag transition caused by an executed instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlagChange {
+    pub pc: u16, // Address of the instruction
+    pub instruction: Instruction,
+    pub flag: FlagPosition,
+    pub old: bool,
+    pub new: bool,
+}
+
+/// Instruction about to be traced, with the registers as they were before execution
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub instruction: Instruction,
+    pub argument: Argument,
+    pub registers: RegisterState,
+    pub cycles: u64,
+    pub address: Option<u16>, // Effective address, None for immediate and implied operands
+}
+
+/// Predicate choosing which instructions `Cpu::trace_to` writes
+pub type TraceFilter = Box<dyn Fn(&TraceEntry) -> bool>;
+
+/// Addresses stored in the interrupt and reset vectors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Vectors {
+    pub reset: u16,
+    pub irq: u16, // Also used by BRK
+    pub nmi: u16,
+}
+
+/// Snapshot of the register file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterState {
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub pc: u16,
+    pub s: u8,
+    pub p: u8,
+}
+
+impl RegisterState {
+    /// Describes every register and status flag differing from `other`,
+    /// formatted as `"<name>: <self> != <other>"`.
+    pub fn diff(&self, other: &RegisterState) -> Vec<String> {
+        let mut differences = Vec::new();
+
+        let registers = [
+            ("A", self.a, other.a),
+            ("X", self.x, other.x),
+            ("Y", self.y, other.y),
+            ("S", self.s, other.s),
+        ];
+        for (name, own, theirs) in registers {
+            if own != theirs {
+                differences.push(format!("{name}: {own:#04X} != {theirs:#04X}"));
+            }
+        }
+        if self.pc != other.pc {
+            differences.push(format!("PC: {:#06X} != {:#06X}", self.pc, other.pc));
+        }
+
+        let own_flags = FlagsRegister::new(self.p);
+        let their_flags = FlagsRegister::new(other.p);
+        for flag in FlagPosition::ALL {
+            let own = own_flags.read_flag(flag);
+            let theirs = their_flags.read_flag(flag);
+            if own != theirs {
+                differences.push(format!("flag {flag:?}: {own} != {theirs}"));
+            }
+        }
+
+        differences
+    }
+}
+
+/// Snapshot of the register file and cycle counter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuState {
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub pc: u16,
+    pub s: u8,
+    pub p: u8,
+    pub cycles: u64,
+}
+
+impl CpuState {
+    pub fn registers(&self) -> RegisterState {
+        RegisterState {
+            a: self.a,
+            x: self.x,
+            y: self.y,
+            pc: self.pc,
+            s: self.s,
+            p: self.p,
+        }
+    }
+
+    /// Like `RegisterState::diff`, followed by the cycle count if it differs.
+    pub fn diff(&self, other: &CpuState) -> Vec<String> {
+        let mut differences = self.registers().diff(&other.registers());
+
+        if self.cycles != other.cycles {
+            differences.push(format!("cycles: {} != {}", self.cycles, other.cycles));
+        }
+
+        differences
+    }
 }
 
-impl fmt::Debug for Cpu {
+impl<B: Bus> fmt::Debug for Cpu<B> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(f, "Registers:").unwrap();
 
@@ -26,12 +230,17 @@ impl fmt::Debug for Cpu {
         writeln!(f, "X: {:#X}", self.x).unwrap();
         writeln!(f, "Y: {:#X}", self.y).unwrap();
         writeln!(f, "PC: {:#X}", self.pc).unwrap();
-        writeln!(f, "S: {:#X} P: {:#X}", self.s, Into::<u8>::into(&self.p))
+        writeln!(f, "S: {:#X} P: {}", self.s, self.p).unwrap();
+        writeln!(
+            f,
+            "Cycles: {} Instructions: {}",
+            self.cycles, self.instructions
+        )
     }
 }
 
-#[derive(Debug)]
-enum Argument {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Argument {
     Void,
     Byte(u8),
     Addr(u16),
@@ -39,13 +248,13 @@ enum Argument {
 
 enum ShiftOperand {
     A,
-    Value(u8),
+    Memory(u8, u16), // Value read and the address it came from
 }
 
 enum IncDecOperand {
     X,
     Y,
-    Value(u8),
+    Memory(u8, u16), // Value read and the address it came from
 }
 
 enum LdOperand {
@@ -54,13 +263,23 @@ enum LdOperand {
     Y,
 }
 
+impl Argument {
+    fn variant_name(&self) -> &'static str {
+        match self {
+            Argument::Void => "Void",
+            Argument::Byte(_) => "Byte",
+            Argument::Addr(_) => "Addr",
+        }
+    }
+}
+
 impl TryInto<u8> for Argument {
     type Error = DecodeError;
 
     fn try_into(self) -> Result<u8, Self::Error> {
         match self {
             Argument::Byte(byte) => Ok(byte),
-            _ => Err(DecodeError::ByteExpectedArgument),
+            other => Err(DecodeError::ByteExpectedArgument(other.variant_name())),
         }
     }
 }
@@ -71,19 +290,119 @@ impl TryInto<u16> for Argument {
     fn try_into(self) -> Result<u16, Self::Error> {
         match self {
             Argument::Addr(addr) => Ok(addr),
-            _ => Err(DecodeError::AddrExpectedArgument),
+            other => Err(DecodeError::AddrExpectedArgument(other.variant_name())),
         }
     }
 }
 
 #[derive(Debug)]
-struct DecodedInstruction {
+pub struct DecodedInstruction {
     pub int: Instruction,
     pub arg: Argument,
 }
 
-fn dword_from_nibbles(low_byte: u8, high_byte: u8) -> u16 {
-    u16::from(high_byte) << 8 | u16::from(low_byte)
+// Little-endian word at `address`, the high byte coming from the next address
+fn read_word<F: Fn(u16) -> u8>(read: F, address: u16) -> u16 {
+    dword_from_bytes(read(address), read(address.wrapping_add(1)))
+}
+
+fn is_valid_bcd(value: u8) -> bool {
+    value >> 4 <= 9 && value & 0x0f <= 9
+}
+
+fn is_decimal_arithmetic(instr: Instruction) -> bool {
+    matches!(
+        instr,
+        Instruction::AdcXIndexedZeroIndirect
+            | Instruction::AdcZeroPage
+            | Instruction::AdcImmediate
+            | Instruction::AdcAbsolute
+            | Instruction::AdcZeroIndirectIndexed
+            | Instruction::AdcXIndexedZero
+            | Instruction::AdcYIndexedAbsolute
+            | Instruction::AdcXIndexedAbsolute
+            | Instruction::SbcXIndexedZeroIndirect
+            | Instruction::SbcZeroPage
+            | Instruction::SbcImmediate
+            | Instruction::SbcAbsolute
+            | Instruction::SbcZeroIndirectIndexed
+            | Instruction::SbcXIndexedZero
+            | Instruction::SbcYIndexedAbsolute
+            | Instruction::SbcXIndexedAbsolute
+    )
+}
+
+// Instructions that store to their effective address
+fn writes_operand(instr: Instruction) -> bool {
+    match instr {
+        Instruction::StaXIndexedZeroIndirect
+        | Instruction::StaZeroPage
+        | Instruction::StaAbsolute
+        | Instruction::StaZeroIndirectIndexed
+        | Instruction::StaXIndexedZero
+        | Instruction::StaYIndexedAbsolute
+        | Instruction::StaXIndexedAbsolute
+        | Instruction::StxZeroPage
+        | Instruction::StxAbsolute
+        | Instruction::StxYIndexedZero
+        | Instruction::StyZeroPage
+        | Instruction::StyAbsolute
+        | Instruction::StyXIndexedZero
+        | Instruction::IncZeroPage
+        | Instruction::IncAbsolute
+        | Instruction::IncXIndexedZero
+        | Instruction::IncXIndexedAbsolute
+        | Instruction::DecZeroPage
+        | Instruction::DecAbsolute
+        | Instruction::DecXIndexedZero
+        | Instruction::DecXIndexedAbsolute
+        | Instruction::AslZeroPage
+        | Instruction::AslAbsolute
+        | Instruction::AslXIndexedZero
+        | Instruction::AslXIndexedAbsolute
+        | Instruction::LsrZeroPage
+        | Instruction::LsrAbsolute
+        | Instruction::LsrXIndexedZero
+        | Instruction::LsrXIndexedAbsolute
+        | Instruction::RolZeroPage
+        | Instruction::RolAbsolute
+        | Instruction::RolXIndexedZero
+        | Instruction::RolXIndexedAbsolute
+        | Instruction::RorZeroPage
+        | Instruction::RorAbsolute
+        | Instruction::RorXIndexedZero
+        | Instruction::RorXIndexedAbsolute => true,
+        #[cfg(feature = "illegal_opcodes")]
+        Instruction::SloZeroPage
+        | Instruction::SloXIndexedZero
+        | Instruction::SloAbsolute
+        | Instruction::SloXIndexedAbsolute
+        | Instruction::SloYIndexedAbsolute
+        | Instruction::SloXIndexedZeroIndirect
+        | Instruction::SloZeroIndirectIndexed
+        | Instruction::RlaZeroPage
+        | Instruction::RlaXIndexedZero
+        | Instruction::RlaAbsolute
+        | Instruction::RlaXIndexedAbsolute
+        | Instruction::RlaYIndexedAbsolute
+        | Instruction::RlaXIndexedZeroIndirect
+        | Instruction::RlaZeroIndirectIndexed
+        | Instruction::SreZeroPage
+        | Instruction::SreXIndexedZero
+        | Instruction::SreAbsolute
+        | Instruction::SreXIndexedAbsolute
+        | Instruction::SreYIndexedAbsolute
+        | Instruction::SreXIndexedZeroIndirect
+        | Instruction::SreZeroIndirectIndexed
+        | Instruction::RraZeroPage
+        | Instruction::RraXIndexedZero
+        | Instruction::RraAbsolute
+        | Instruction::RraXIndexedAbsolute
+        | Instruction::RraYIndexedAbsolute
+        | Instruction::RraXIndexedZeroIndirect
+        | Instruction::RraZeroIndirectIndexed => true,
+        _ => false,
+    }
 }
 
 fn bcd_to_u8(bcd: u8) -> u8 {
@@ -100,8 +419,10 @@ fn u8_to_bcd(value: u8) -> u8 {
 
 struct FetchOperandResult(u8, Option<u16>);
 
-impl Cpu {
-    pub fn new(mem_bus: MemoryBus) -> Cpu {
+struct BranchResult(bool, bool); // Taken, destination on another page
+
+impl<B: Bus> Cpu<B> {
+    pub fn new(mem_bus: B) -> Cpu<B> {
         Cpu {
             address_space: mem_bus,
             a: 0,
@@ -110,346 +431,1266 @@ impl Cpu {
             pc: 0,
             s: 0,
             p: FlagsRegister::default(),
+            variant: Variant::default(),
+            stack_base: STACK_BOTTOM,
+            cycles: 0,
+            instructions: 0,
+            flag_log: None,
+            #[cfg(not(feature = "no_std"))]
+            trace_writer: None,
+            #[cfg(not(feature = "no_std"))]
+            trace_filter: None,
+            on_cycle: None,
+            breakpoints: Vec::new(),
+            detect_stack_errors: false,
+            stack_error: None,
+            rom_write: None,
+            conditional_breakpoints: Vec::new(),
+            irq_line: false,
+            irq_pending: false,
+            last_instruction: None,
+            opcode_coverage: [0; 256],
+            code_range: None,
+            on_code_write: None,
+            trap: None,
+            trap_value: None,
+            history: VecDeque::new(),
+            history_depth: 0,
+            strict_bcd: false,
+            trap_unimplemented: false,
+            unimplemented: None,
+            scheduled: Vec::new(),
+        }
+    }
+
+    pub fn state(&self) -> CpuState {
+        CpuState {
+            a: self.a,
+            x: self.x,
+            y: self.y,
+            pc: self.pc,
+            s: self.s,
+            p: Into::<u8>::into(&self.p),
+            cycles: self.cycles,
+        }
+    }
+
+    pub fn registers(&self) -> RegisterState {
+        self.state().registers()
+    }
+
+    /// Compact binary save state: the `6502` magic and a format version byte,
+    /// then A, X, Y, S, P, PC and the cycle count (little-endian), the IRQ line
+    /// and pending flags, and all 64K of memory read with `Bus::peek`.
+    /// Unmapped addresses are stored as 0.
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut state = Vec::with_capacity(STATE_LEN);
+        state.extend_from_slice(STATE_MAGIC);
+        state.push(STATE_VERSION);
+        state.extend_from_slice(&[self.a, self.x, self.y, self.s, Into::<u8>::into(&self.p)]);
+        state.extend_from_slice(&self.pc.to_le_bytes());
+        state.extend_from_slice(&self.cycles.to_le_bytes());
+        state.extend_from_slice(&[self.irq_line as u8, self.irq_pending as u8]);
+        for address in 0..=u16::MAX {
+            if self.address_space.is_mapped(address) {
+                state.push(self.address_space.peek(address));
+            } else {
+                state.push(0);
+            }
+        }
+
+        state
+    }
+
+    /// Restores a blob from `save_state`. The blob is checked before anything
+    /// changes. Memory is written back through the bus only where the saved byte
+    /// differs from what the address holds now, which leaves intact ROM alone.
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), StateError> {
+        if state.len() < STATE_MAGIC.len() + 1 || &state[..STATE_MAGIC.len()] != STATE_MAGIC {
+            return Err(StateError::BadHeader);
+        }
+        let version = state[STATE_MAGIC.len()];
+        if version != STATE_VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+        if state.len() != STATE_LEN {
+            return Err(StateError::Truncated(state.len(), STATE_LEN));
+        }
+
+        let registers = &state[5..22];
+        self.a = registers[0];
+        self.x = registers[1];
+        self.y = registers[2];
+        self.s = registers[3];
+        self.p.set_byte(registers[4]);
+        self.pc = u16::from_le_bytes([registers[5], registers[6]]);
+        self.cycles = u64::from_le_bytes(registers[7..15].try_into().unwrap());
+        self.irq_line = registers[15] != 0;
+        self.irq_pending = registers[16] != 0;
+
+        for (address, &value) in (0..=u16::MAX).zip(&state[22..]) {
+            if self.address_space.is_mapped(address) && self.address_space.peek(address) != value {
+                self.address_space.write(address, value);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Total cycles consumed by executed instructions
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Instructions executed since creation, not counting interrupt entries
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    /// Sets the cycle counter back to 0, returning the count it had.
+    /// A framed emulator can call `run_cycles(cycles_per_frame)` and then this
+    /// once per frame; the returned total minus the frame length is how far the
+    /// last instruction overshot, to be taken off the next frame's budget.
+    pub fn reset_cycle_counter(&mut self) -> u64 {
+        core::mem::take(&mut self.cycles)
+    }
+
+    /// Enables or disables recording of every status flag change.
+    /// Disabling drops any entries not yet taken.
+    pub fn log_flag_changes(&mut self, enabled: bool) {
+        self.flag_log = if enabled { Some(Vec::new()) } else { None };
+    }
+
+    /// Returns flag changes recorded since the last call, oldest first.
+    pub fn take_flag_log(&mut self) -> Vec<FlagChange> {
+        self.flag_log
+            .as_mut()
+            .map(core::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Writes `value` to `addr` through the memory bus, as a DMA transfer would.
+    pub fn write_memory(&mut self, addr: u16, value: u8) {
+        self.address_space.write(addr, value);
+    }
+
+    /// Reads the byte at `addr` through the memory bus.
+    pub fn read_memory(&mut self, addr: u16) -> u8 {
+        self.address_space.read(addr)
+    }
+
+    /// Pushes `value` onto the page one stack.
+    pub fn push_byte(&mut self, value: u8) {
+        self.push(value);
+    }
+
+    /// Pulls a byte from the page one stack.
+    pub fn pop_byte(&mut self) -> u8 {
+        self.pop()
+    }
+
+    /// Pushes `value` high byte first, as JSR and interrupts do.
+    pub fn push_word(&mut self, value: u16) {
+        self.push_dword(value);
+    }
+
+    /// Pulls a word pushed with `push_word`, e.g. a return address.
+    pub fn pop_word(&mut self) -> u16 {
+        self.pop_dword()
+    }
+
+    /// Pushes A, X, Y and P, in that order, like a kernel's context switch routine.
+    pub fn save_context(&mut self) {
+        self.push(self.a);
+        self.push(self.x);
+        self.push(self.y);
+        self.push(Into::<u8>::into(&self.p));
+    }
+
+    /// Pulls P, Y, X and A back, undoing `save_context`.
+    pub fn restore_context(&mut self) {
+        let status = self.pop();
+        self.p.set_byte(status);
+        self.y = self.pop();
+        self.x = self.pop();
+        self.a = self.pop();
+    }
+
+    /// Return sites of the subroutine calls in progress, innermost first.
+    /// Heuristic: the stack page from S + 1 up is scanned for words that point
+    /// just past a JSR opcode, and each is reported plus one, where RTS resumes.
+    /// Data pushed by the program can look like a return address and a stack
+    /// manipulated by hand can hide real ones. Memory is read with `Bus::peek`.
+    pub fn call_stack(&self) -> Vec<u16> {
+        let stack_address = |offset: u16| (self.stack_base as u16).wrapping_add(offset);
+        let mut calls = Vec::new();
+
+        let mut offset = self.s as u16 + 1;
+        while offset < 0xFF {
+            let pushed = self.peek_dword(stack_address(offset));
+            if self.address_space.peek(pushed.wrapping_sub(2)) == Instruction::Jsr as u8 {
+                calls.push(pushed.wrapping_add(1));
+                offset += 2;
+            } else {
+                offset += 1;
+            }
+        }
+
+        calls
+    }
+
+    /// Address stored in the reset vector, read with `Bus::peek`.
+    pub fn reset_vector(&self) -> u16 {
+        self.peek_dword(RESET_VECTOR)
+    }
+
+    /// Address stored in the IRQ/BRK vector, read with `Bus::peek`.
+    pub fn irq_vector(&self) -> u16 {
+        self.peek_dword(IRQ_VECTOR)
+    }
+
+    /// Address stored in the NMI vector, read with `Bus::peek`.
+    pub fn nmi_vector(&self) -> u16 {
+        self.peek_dword(NMI_VECTOR)
+    }
+
+    /// Writes all three vectors to their canonical addresses through the bus.
+    pub fn set_vectors(&mut self, vectors: Vectors) {
+        self.write_word(RESET_VECTOR, vectors.reset);
+        self.write_word(IRQ_VECTOR, vectors.irq);
+        self.write_word(NMI_VECTOR, vectors.nmi);
+    }
+
+    fn write_word(&mut self, address: u16, value: u16) {
+        let (low_byte, high_byte) = bytes_from_dword(value);
+        self.address_space.write(address, low_byte);
+        self.address_space.write(address.wrapping_add(1), high_byte);
+    }
+
+    /// Assembles `source` (see `assembler::assemble`) and writes it through the bus
+    /// starting at `origin`, then points the reset vector and PC at `origin`.
+    pub fn load_asm(&mut self, source: &str, origin: u16) -> Result<(), AsmError> {
+        let program = assembler::assemble(source, origin)?;
+
+        for (offset, byte) in program.iter().enumerate() {
+            self.address_space
+                .write(origin.wrapping_add(offset as u16), *byte);
         }
+        self.write_word(RESET_VECTOR, origin);
+        self.pc = origin;
+
+        Ok(())
+    }
+
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    pub fn set_variant(&mut self, variant: Variant) {
+        self.variant = variant;
+    }
+
+    pub fn stack_base(&self) -> usize {
+        self.stack_base
     }
 
+    /// Moves the stack off page one, for derivative designs. Defaults to `STACK_BOTTOM`.
+    pub fn set_stack_base(&mut self, base: usize) {
+        self.stack_base = base;
+    }
+
+    /// Moves execution to `val`, the next `step` runs the instruction there
     pub fn set_pc(&mut self, val: u16) {
         self.pc = val;
     }
 
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
     pub fn reset(&mut self) {
+        self.soft_reset();
+        self.pc = self.read_u16(RESET_VECTOR);
+        //self.pc = 0xE2B3;
+    }
+
+    /// Same register and flag reset as `reset`, but PC is left where it is and
+    /// the reset vector is never read, so it doesn't have to be mapped.
+    pub fn soft_reset(&mut self) {
         self.a = 0;
         self.x = 0;
         self.y = 0;
         self.s = 0;
-        self.p = FlagsRegister::default();
-        self.pc = self.fetch_dword(0xFFFC);
-        //self.pc = 0xE2B3;
+        // IRQs disabled, bit 5 set. Only the 65C02 also clears D, NMOS keeps it
+        let decimal = self.p.read_flag(FlagPosition::DecimalMode);
+        self.p.set_byte(0x24);
+        if self.variant == Variant::Nmos {
+            self.p.write_flag(FlagPosition::DecimalMode, decimal);
+        }
+        self.irq_pending = false;
     }
 
-    pub fn step(&mut self) {
-        let opcode = self.fetch(self.pc);
-        let instruction = self.decode(opcode);
+    /// Sets the level of the IRQ input. While asserted, `step` services an
+    /// interrupt whenever the line was polled with IRQs enabled.
+    pub fn set_irq_line(&mut self, asserted: bool) {
+        self.irq_line = asserted;
+    }
 
-        self.execute(instruction);
+    /// Services a maskable interrupt request unless IRQs are disabled.
+    /// Returns whether the interrupt was taken.
+    pub fn irq(&mut self) -> bool {
+        if self.p.read_flag(FlagPosition::IrqDisable) {
+            return false;
+        }
+
+        self.interrupt(self.pc, IRQ_VECTOR, false);
+        self.cycles += INTERRUPT_CYCLES;
+
+        true
     }
 
-    fn fetch(&self, address: u16) -> u8 {
-        const SPACE_END: u16 = MEM_SPACE_END as u16;
-        match address {
-            0..=SPACE_END => self.address_space.read_byte(address as usize),
-            _ => panic!("PC address out of bounds"),
+    /// Services a non-maskable interrupt.
+    pub fn nmi(&mut self) {
+        self.interrupt(self.pc, NMI_VECTOR, false);
+        self.cycles += INTERRUPT_CYCLES;
+    }
+
+    pub fn step(&mut self) {
+        let start = self.cycles;
+        self.rom_write = None;
+        self.unimplemented = None;
+        self.begin_history_entry();
+
+        if self.irq_pending {
+            self.irq_pending = false;
+            self.irq();
+        } else {
+            self.step_instruction();
+        }
+
+        if let Some(on_cycle) = self.on_cycle.as_mut() {
+            for _ in start..self.cycles {
+                on_cycle();
+            }
         }
+
+        self.run_scheduled();
     }
 
-    fn fetch_dword(&self, address: u16) -> u16 {
-        let low_byte = self.fetch(address);
-        let high_byte = self.fetch(address + 1);
+    /// Runs `event` after the first step that brings `cycles()` to `deadline`
+    /// or past it, e.g. to raise a timer IRQ. Events with the same deadline run
+    /// in the order they were scheduled, and an event may schedule its next run.
+    pub fn schedule(&mut self, deadline: u64, event: ScheduledEvent<B>) {
+        let index = self
+            .scheduled
+            .partition_point(|(scheduled, _)| *scheduled <= deadline);
+        self.scheduled.insert(index, (deadline, event));
+    }
 
-        dword_from_nibbles(low_byte, high_byte)
+    /// Drops every pending scheduled event
+    pub fn clear_schedule(&mut self) {
+        self.scheduled.clear();
     }
 
-    fn decode(&self, value: u8) -> DecodedInstruction {
-        let opcode = Instruction::try_from(value)
-            .unwrap_or_else(|_| panic!("Failed to decode opcode {value:#X}"));
-        let argument_kind = INSTRUCTIONS_ADDRESSING
-            .get(&opcode)
-            .unwrap_or_else(|| panic!("Unimplemented opcode {opcode:?}"));
+    fn run_scheduled(&mut self) {
+        while self
+            .scheduled
+            .first()
+            .is_some_and(|(deadline, _)| *deadline <= self.cycles)
+        {
+            let (_, mut event) = self.scheduled.remove(0);
+            event(self);
+        }
+    }
 
-        let arg: Argument = match *argument_kind {
-            ArgumentType::Addr => {
-                let low_byte = self.fetch(self.pc + 1);
-                let high_byte = self.fetch(self.pc + 2);
+    /// Calls `callback` once per consumed cycle. The calls for an instruction or
+    /// interrupt sequence all happen after it has completed, before `step` returns,
+    /// so the callback never observes a half-executed instruction.
+    pub fn set_cycle_callback(&mut self, callback: Box<dyn FnMut()>) {
+        self.on_cycle = Some(callback);
+    }
 
-                Argument::Addr(dword_from_nibbles(low_byte, high_byte))
-                // TODO: Make args vec of Instruction ?
-            }
-            ArgumentType::Byte => Argument::Byte(self.fetch(self.pc + 1)),
-            ArgumentType::Void => Argument::Void,
-        };
+    pub fn clear_cycle_callback(&mut self) {
+        self.on_cycle = None;
+    }
 
-        DecodedInstruction { int: opcode, arg }
+    /// Marks `start..=end` as code. Stores into it are reported to the callback
+    /// set with `set_code_write_callback`, to catch self-modifying code.
+    pub fn set_code_range(&mut self, start: u16, end: u16) {
+        self.code_range = Some((start, end));
     }
 
-    fn fetch_operand(
-        &self,
-        instr: DecodedInstruction,
-        addressing_type: AddressingType,
-    ) -> FetchOperandResult {
-        match addressing_type {
-            AddressingType::XIndexedZeroIndirect => {
-                let arg0: u8 = TryInto::<u8>::try_into(instr.arg)
-                    .expect("x indexed zero indirect operand fetch error: expected byte");
+    pub fn clear_code_range(&mut self) {
+        self.code_range = None;
+    }
 
-                let x_indexed_ptr = u8::wrapping_add(self.x, arg0) as u16;
+    /// Calls `callback` with the address of every store into the code range.
+    pub fn set_code_write_callback(&mut self, callback: Box<dyn FnMut(u16)>) {
+        self.on_code_write = Some(callback);
+    }
 
-                let address = self.fetch_dword(x_indexed_ptr);
+    fn step_instruction(&mut self) {
+        let instruction = self.decode_at(self.pc);
+        let int = instruction.int;
 
-                FetchOperandResult(self.fetch(address), Some(address))
-            }
-            AddressingType::ZeroPage => {
-                let arg0: u8 = TryInto::try_into(instr.arg)
-                    .expect("zero page operand fetch error: expected zero page addr byte");
+        #[cfg(not(feature = "no_std"))]
+        if self.trace_writer.is_some() {
+            self.write_trace(&instruction);
+        }
 
-                FetchOperandResult(self.fetch(arg0 as u16), Some(arg0 as u16))
-            }
-            AddressingType::Immediate => FetchOperandResult(
-                TryInto::try_into(instr.arg)
-                    .expect("immediate operand fetch error: expected immediate byte"),
-                None,
-            ),
-            AddressingType::Absolute => {
-                let address: u16 = TryInto::try_into(instr.arg)
-                    .expect("absolute operand fetch error: expected address");
+        self.last_instruction = Some((instruction.int, instruction.arg));
+        self.opcode_coverage[instruction.int as usize] += 1;
+        self.instructions += 1;
+        let irq_disabled = self.p.read_flag(FlagPosition::IrqDisable);
+        self.execute(instruction);
 
-                FetchOperandResult(self.fetch(address), Some(address))
-            }
-            AddressingType::ZeroIndirectIndexed => {
-                let arg0: u8 = TryInto::try_into(instr.arg)
-                    .expect("Zero indirect indexed operand fetch error: expected byte");
+        // The IRQ line is polled before the last cycle, where CLI, SEI and PLP
+        // change I, so they only affect the poll of the following instruction.
+        // RTI restores I early enough for its own poll.
+        let irq_disabled = match int {
+            Instruction::Rti => self.p.read_flag(FlagPosition::IrqDisable),
+            _ => irq_disabled,
+        };
+        self.irq_pending = self.irq_line && !irq_disabled;
+    }
 
-                let low_byte = self.fetch(arg0 as u16);
-                let high_byte = self.fetch(arg0 as u16 + 1);
-                let address = dword_from_nibbles(low_byte, high_byte).wrapping_add(self.y as u16);
+    /// In strict decimal mode `try_step` refuses to run ADC or SBC with the
+    /// Decimal flag set when the accumulator or operand has a nibble above 9,
+    /// returning `StepError::InvalidBcd` instead. `step` is never affected.
+    pub fn set_strict_bcd(&mut self, strict: bool) {
+        self.strict_bcd = strict;
+    }
 
-                FetchOperandResult(self.fetch(address), Some(address))
-            }
-            AddressingType::XIndexedZero => {
-                let arg0: u8 = TryInto::try_into(instr.arg)
-                    .expect("X indexed zero page operand fetch error: expected byte");
+    /// With the trap on, an instruction that decodes but has no execute arm, such
+    /// as BIT #imm on NMOS, is left unexecuted instead of the emulator panicking.
+    /// PC stays on it, so `run_cycles` and `run` stop there instead of spinning.
+    /// `try_step` already refuses opcodes `is_implemented` rejects and reports
+    /// any other missing arm as `StepError::Unimplemented`. Off by default.
+    pub fn set_trap_unimplemented(&mut self, trap: bool) {
+        self.trap_unimplemented = trap;
+    }
 
-                let x_indexed_ptr = u8::wrapping_add(self.x, arg0) as u16;
+    /// Keeps an undo record of the last `depth` steps for `step_back`.
+    /// A depth of 0, the default, turns recording off and drops the history.
+    pub fn set_history_depth(&mut self, depth: usize) {
+        self.history_depth = depth;
+        while self.history.len() > depth {
+            self.history.pop_front();
+        }
+    }
 
-                FetchOperandResult(self.fetch(x_indexed_ptr), Some(x_indexed_ptr))
-            }
-            AddressingType::YIndexedZero => {
-                let arg0: u8 = TryInto::try_into(instr.arg)
-                    .expect("Y indexed zero page operand fetch error: expected byte");
+    /// Undoes the most recent recorded step, restoring registers and the bytes
+    /// it overwrote. Returns false when there is no history left.
+    pub fn step_back(&mut self) -> bool {
+        let Some(entry) = self.history.pop_back() else {
+            return false;
+        };
 
-                let y_indexed_ptr = u8::wrapping_add(self.y, arg0) as u16;
+        for (address, value) in entry.writes.into_iter().rev() {
+            self.address_space.write(address, value);
+        }
 
-                FetchOperandResult(self.fetch(y_indexed_ptr), Some(y_indexed_ptr))
-            }
-            AddressingType::XIndexedAbsolute => {
-                let address: u16 = TryInto::try_into(instr.arg)
-                    .expect("X indexed absolute operand fetch error: expected address");
+        let state = entry.state;
+        self.a = state.a;
+        self.x = state.x;
+        self.y = state.y;
+        self.pc = state.pc;
+        self.s = state.s;
+        self.p.set_byte(state.p);
+        self.cycles = state.cycles;
+        self.irq_pending = entry.irq_pending;
+
+        true
+    }
 
-                let address_x_indexed = address.wrapping_add(self.x as u16);
+    fn begin_history_entry(&mut self) {
+        if self.history_depth == 0 {
+            return;
+        }
+        if self.history.len() == self.history_depth {
+            self.history.pop_front();
+        }
+        self.history.push_back(HistoryEntry {
+            state: self.state(),
+            irq_pending: self.irq_pending,
+            writes: Vec::new(),
+        });
+    }
 
-                FetchOperandResult(self.fetch(address_x_indexed), Some(address_x_indexed))
-            }
-            AddressingType::YIndexedAbsolute => {
-                let address: u16 = TryInto::try_into(instr.arg)
-                    .expect("Y indexed absolute operand fetch error: expected address");
+    /// Instruction executed by the most recent `step`, with its operand.
+    /// Interrupt sequences don't replace it.
+    pub fn last_instruction(&self) -> Option<(Instruction, Argument)> {
+        self.last_instruction
+    }
+
+    /// Number of times each opcode byte has been executed, indexed by opcode.
+    pub fn opcode_coverage(&self) -> &[u64; 256] {
+        &self.opcode_coverage
+    }
 
-                let address_y_indexed = address.wrapping_add(self.y as u16);
+    pub fn clear_opcode_coverage(&mut self) {
+        self.opcode_coverage = [0; 256];
+    }
 
-                FetchOperandResult(self.fetch(address_y_indexed), Some(address_y_indexed))
+    /// Executes whole instructions until at least `budget` cycles have elapsed.
+    /// Returns the cycles actually consumed, which may overshoot by one instruction.
+    /// Stops early at an instruction caught by `set_trap_unimplemented`.
+    pub fn run_cycles(&mut self, budget: u64) -> u64 {
+        let start = self.cycles;
+        while self.cycles - start < budget {
+            self.step();
+            if self.unimplemented.is_some() {
+                break;
             }
         }
+
+        self.cycles - start
     }
 
-    fn execute(&mut self, instr: DecodedInstruction) {
-        println!("Executing opcode {:#X}", instr.int as u8);
-        match instr.int {
-            Instruction::AdcXIndexedZeroIndirect => {
-                let FetchOperandResult(operand, _) =
-                    self.fetch_operand(instr, AddressingType::XIndexedZeroIndirect);
-                self.adc(operand);
-                self.pc += 2;
+    /// Runs until the program halts, a breakpoint is reached or `max_cycles`
+    /// cycles have elapsed.
+    /// BRK and undecodable opcodes stop the run before they execute.
+    /// Breakpoints are not checked for the first instruction, so a run can resume
+    /// from the place where the previous one stopped.
+    pub fn run(&mut self, max_cycles: u64) -> StopReason {
+        let start = self.cycles;
+        let mut first = true;
+
+        loop {
+            if !first && self.at_breakpoint() {
+                return StopReason::Breakpoint(self.pc);
             }
-            Instruction::AdcZeroPage => {
-                let FetchOperandResult(arg0, _) =
-                    self.fetch_operand(instr, AddressingType::ZeroPage);
-                self.adc(arg0);
-                self.pc += 2;
+            first = false;
+
+            let opcode = self.peek_opcode();
+            if opcode == Instruction::Brk.into() {
+                return StopReason::Brk;
+            }
+            if !self.is_implemented(opcode) {
+                return StopReason::IllegalOpcode(opcode);
+            }
+            if self.cycles - start >= max_cycles {
+                return StopReason::CycleLimit;
             }
-            Instruction::AdcImmediate => {
-                let FetchOperandResult(arg0, _) =
-                    self.fetch_operand(instr, AddressingType::Immediate);
 
-                self.adc(arg0);
-                self.pc += 2;
+            let pc = self.pc;
+            self.step();
+            if let Some(reason) = self.stack_error.take() {
+                return reason;
             }
-            Instruction::AdcAbsolute => {
-                let FetchOperandResult(arg0, _) =
-                    self.fetch_operand(instr, AddressingType::Absolute);
-                self.adc(arg0);
-                self.pc += 3;
+            if let Some(address) = self.rom_write.take() {
+                return StopReason::RomWrite(address);
             }
-            Instruction::AdcZeroIndirectIndexed => {
-                let FetchOperandResult(arg0, _) =
-                    self.fetch_operand(instr, AddressingType::ZeroIndirectIndexed);
-                self.adc(arg0);
-                self.pc += 2;
+            if self.unimplemented.take().is_some() {
+                return StopReason::IllegalOpcode(opcode);
             }
-            Instruction::AdcXIndexedZero => {
-                let FetchOperandResult(arg0, _) =
-                    self.fetch_operand(instr, AddressingType::XIndexedZero);
-                self.adc(arg0);
-                self.pc += 2;
+            if let Some(value) = self.trap_value.take() {
+                return StopReason::Trap { value };
             }
-            Instruction::AdcYIndexedAbsolute => {
-                let FetchOperandResult(arg0, _) =
-                    self.fetch_operand(instr, AddressingType::YIndexedAbsolute);
-                self.adc(arg0);
-                self.pc += 3;
+            if self.trap == Some((self.pc, TrapMode::Execute)) {
+                return StopReason::Trap { value: self.a };
+            }
+            if self.pc == pc {
+                return StopReason::InfiniteLoop(pc);
+            }
+        }
+    }
+
+    /// Makes `run` stop with `StackOverflow`/`StackUnderflow` after an instruction
+    /// that wrapped S. Off by default, as S legitimately wraps on hardware.
+    pub fn detect_stack_errors(&mut self, enabled: bool) {
+        self.detect_stack_errors = enabled;
+        self.stack_error = None;
+    }
+
+    /// Makes `run` stop with `StopReason::Trap` when `address` is written or
+    /// reached, the usual way test ROMs report pass or fail.
+    pub fn set_trap(&mut self, address: u16, mode: TrapMode) {
+        self.trap = Some((address, mode));
+        self.trap_value = None;
+    }
+
+    pub fn clear_trap(&mut self) {
+        self.trap = None;
+        self.trap_value = None;
+    }
+
+    /// Stops `run` before the instruction at `address`.
+    pub fn add_breakpoint(&mut self, address: u16) {
+        if !self.breakpoints.contains(&address) {
+            self.breakpoints.push(address);
+        }
+    }
+
+    pub fn remove_breakpoint(&mut self, address: u16) {
+        self.breakpoints.retain(|breakpoint| *breakpoint != address);
+    }
+
+    /// Stops `run` before any instruction for which `condition` returns true.
+    pub fn add_conditional_breakpoint(&mut self, condition: BreakCondition<B>) {
+        self.conditional_breakpoints.push(condition);
+    }
+
+    /// Removes all address and conditional breakpoints.
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+        self.conditional_breakpoints.clear();
+    }
+
+    fn at_breakpoint(&self) -> bool {
+        self.breakpoints.contains(&self.pc)
+            || self
+                .conditional_breakpoints
+                .iter()
+                .any(|condition| condition(self))
+    }
+
+    /// Like `step`, but reports an unmapped instruction byte, indirect pointer or
+    /// effective address as `StepError::MemoryFault`, a write the bus would fault
+    /// on (ROM under `RomWritePolicy::Fault`) as `StepError::RomWrite` and an
+    /// opcode this build can't decode as `StepError::UnknownOpcode` instead of
+    /// panicking, and invalid decimal operands as `StepError::InvalidBcd` in
+    /// strict BCD mode. Nothing is executed when one of these is returned, so
+    /// random programs never panic here. With `set_trap_unimplemented` on, an
+    /// instruction without an execute arm is reported as
+    /// `StepError::Unimplemented` after the step has been counted.
+    pub fn try_step(&mut self) -> Result<(), StepError> {
+        let fault = Cell::new(None);
+        let peek = |address: u16| {
+            if self.address_space.is_mapped(address) {
+                self.address_space.peek(address)
+            } else {
+                fault.set(fault.get().or(Some(address)));
+                0
+            }
+        };
+
+        // A pending IRQ is serviced instead of the instruction at PC
+        if self.irq_pending {
+            for address in self.implicit_accesses(Instruction::Brk) {
+                peek(address);
+            }
+            if let Some(address) = fault.get() {
+                return Err(StepError::MemoryFault(address));
+            }
+            self.check_writable(&self.stack_writes(Instruction::Brk))?;
+            self.step();
+            return Ok(());
+        }
+
+        let opcode = peek(self.pc);
+        if let Some(address) = fault.get() {
+            return Err(StepError::MemoryFault(address));
+        }
+        let Some(length) = opcode_length(opcode).filter(|_| self.is_implemented(opcode)) else {
+            return Err(StepError::UnknownOpcode(opcode));
+        };
+        for offset in 1..length {
+            peek(self.pc.wrapping_add(offset as u16));
+        }
+        if let Some(address) = fault.get() {
+            return Err(StepError::MemoryFault(address));
+        }
+
+        let instruction = self.decode_with(self.pc, opcode, peek);
+        let address = self.effective_address_with(&instruction, peek);
+        let operand = match address {
+            Some(address) => peek(address),
+            None => TryInto::<u8>::try_into(instruction.arg).unwrap_or(0),
+        };
+        for address in self.implicit_accesses(instruction.int) {
+            peek(address);
+        }
+        if let Instruction::JmpIndirect = instruction.int {
+            let pointer: u16 = TryInto::try_into(instruction.arg).unwrap_or(0);
+            peek(pointer);
+            peek(pointer.wrapping_add(1));
+            peek(pointer & 0xFF00 | pointer.wrapping_add(1) & 0x00FF);
+        }
+        if let Some(address) = fault.get() {
+            return Err(StepError::MemoryFault(address));
+        }
+
+        if self.strict_bcd
+            && self.p.read_flag(FlagPosition::DecimalMode)
+            && is_decimal_arithmetic(instruction.int)
+        {
+            if let Some(value) = [self.a, operand].into_iter().find(|&v| !is_valid_bcd(v)) {
+                return Err(StepError::InvalidBcd(value));
+            }
+        }
+
+        let mut writes = self.stack_writes(instruction.int);
+        if writes_operand(instruction.int) {
+            writes.extend(address);
+        }
+        self.check_writable(&writes)?;
+
+        self.step();
+
+        match self.unimplemented.take() {
+            Some(instr) => Err(StepError::Unimplemented(instr)),
+            None => Ok(()),
+        }
+    }
+
+    // Stack slots and vectors `instr` accesses besides its operand. BRK stands
+    // in for hardware interrupts, which touch the same addresses.
+    fn implicit_accesses(&self, instr: Instruction) -> Vec<u16> {
+        let stack =
+            |offset: i8| (self.stack_base + self.s.wrapping_add_signed(offset) as usize) as u16;
+
+        let mut accesses = self.stack_writes(instr);
+        accesses.extend(match instr {
+            Instruction::Brk => vec![IRQ_VECTOR, IRQ_VECTOR + 1],
+            Instruction::Pla | Instruction::Plp => vec![stack(1)],
+            Instruction::Rts => vec![stack(1), stack(2)],
+            Instruction::Rti => vec![stack(1), stack(2), stack(3)],
+            _ => Vec::new(),
+        });
+
+        accesses
+    }
+
+    // Stack slots `instr` pushes to, BRK again standing in for interrupts
+    fn stack_writes(&self, instr: Instruction) -> Vec<u16> {
+        let stack =
+            |offset: i8| (self.stack_base + self.s.wrapping_add_signed(offset) as usize) as u16;
+
+        match instr {
+            Instruction::Brk => vec![stack(0), stack(-1), stack(-2)],
+            Instruction::Jsr => vec![stack(0), stack(-1)],
+            Instruction::Pha | Instruction::Php => vec![stack(0)],
+            _ => Vec::new(),
+        }
+    }
+
+    fn check_writable(&self, addresses: &[u16]) -> Result<(), StepError> {
+        match addresses
+            .iter()
+            .find(|&&address| !self.address_space.is_writable(address))
+        {
+            Some(&address) => Err(StepError::RomWrite(address)),
+            None => Ok(()),
+        }
+    }
+
+    #[cfg(not(feature = "no_std"))]
+    /// Writes a monitor-style line for every executed instruction to `writer`,
+    /// with registers and cycle count as they were before execution.
+    /// Write errors are ignored, tracing never interrupts execution.
+    pub fn trace_to(&mut self, writer: Box<dyn Write>) {
+        self.trace_writer = Some(writer);
+    }
+
+    #[cfg(not(feature = "no_std"))]
+    pub fn stop_trace(&mut self) {
+        self.trace_writer = None;
+    }
+
+    #[cfg(not(feature = "no_std"))]
+    /// Only writes trace lines for instructions `filter` accepts, e.g. branches
+    /// or a PC range. The filter stays in place across `stop_trace`.
+    pub fn set_trace_filter(&mut self, filter: TraceFilter) {
+        self.trace_filter = Some(filter);
+    }
+
+    #[cfg(not(feature = "no_std"))]
+    pub fn clear_trace_filter(&mut self) {
+        self.trace_filter = None;
+    }
+
+    #[cfg(not(feature = "no_std"))]
+    fn write_trace(&mut self, instr: &DecodedInstruction) {
+        let entry = TraceEntry {
+            instruction: instr.int,
+            argument: instr.arg,
+            registers: self.registers(),
+            cycles: self.cycles,
+            address: self.effective_address(instr),
+        };
+        if !self
+            .trace_filter
+            .as_ref()
+            .is_none_or(|filter| filter(&entry))
+        {
+            return;
+        }
+
+        let opcode: u8 = entry.instruction.into();
+        let bytes = match entry.argument {
+            Argument::Void => format!("{opcode:02X}"),
+            Argument::Byte(byte) => format!("{opcode:02X} {byte:02X}"),
+            Argument::Addr(addr) => {
+                let (low_byte, high_byte) = bytes_from_dword(addr);
+                format!("{opcode:02X} {low_byte:02X} {high_byte:02X}")
+            }
+        };
+        let registers = entry.registers;
+        // Decoded from the same bytes, so the disassembler always has a text for it
+        let (text, _) = Disassembler::new()
+            .disassemble(&self.address_space, registers.pc)
+            .unwrap_or_default();
+        let line = format!(
+            "{:04X}  {:<8}  {:<24} A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} CYC:{}",
+            registers.pc,
+            bytes,
+            text,
+            registers.a,
+            registers.x,
+            registers.y,
+            registers.p,
+            registers.s,
+            entry.cycles
+        );
+
+        if let Some(writer) = self.trace_writer.as_mut() {
+            let _ = writeln!(writer, "{line}");
+        }
+    }
+
+    // Every u16 is a valid address; whether anything answers is up to the bus,
+    // and `try_step` reports unmapped accesses as `StepError::MemoryFault`
+    fn fetch(&self, address: u16) -> u8 {
+        self.address_space.read(address)
+    }
+
+    /// Reads the little-endian word at `addr` through the bus, with the same side
+    /// effects as the CPU's own reads. The high byte comes from `addr + 1`, wrapping
+    /// at the top of memory.
+    pub fn read_u16(&self, addr: u16) -> u16 {
+        read_word(|address| self.fetch(address), addr)
+    }
+
+    fn peek_dword(&self, address: u16) -> u16 {
+        read_word(|address| self.address_space.peek(address), address)
+    }
+
+    // NMOS parts don't carry into the pointer's high byte, so JMP ($xxFF) reads
+    // its high byte from $xx00. The 65C02 fixed this.
+    fn jmp_indirect_target(&self, pointer: u16) -> u16 {
+        match self.variant {
+            Variant::Nmos => {
+                let high_address = pointer & 0xFF00 | pointer.wrapping_add(1) & 0x00FF;
+                dword_from_bytes(self.fetch(pointer), self.fetch(high_address))
+            }
+            Variant::Cmos => self.read_u16(pointer),
+        }
+    }
+
+    /// Whether this build can decode and execute `opcode` on the selected variant,
+    /// so a loader or fuzzer can avoid the panic in `step`. Includes undocumented
+    /// opcodes when the `illegal_opcodes` feature is enabled. 65C02 additions
+    /// such as BIT #imm ($89) only count for `Variant::Cmos`.
+    pub fn is_implemented(&self, opcode: u8) -> bool {
+        let cmos_only =
+            opcode == Instruction::BitImmediate.into() && !cfg!(feature = "illegal_opcodes");
+
+        opcode_length(opcode).is_some() && (self.variant == Variant::Cmos || !cmos_only)
+    }
+
+    /// Executes `opcode` with the given operand bytes as if it were fetched at PC,
+    /// without reading the instruction from memory. Unused operand bytes are ignored.
+    /// PC still advances past the instruction, and operands in memory are accessed as usual.
+    /// Panics on opcodes this build doesn't implement.
+    pub fn execute_opcode(&mut self, opcode: u8, operand_lo: u8, operand_hi: u8) {
+        let operand_address = self.pc.wrapping_add(1);
+        let instruction = self.decode_with(self.pc, opcode, |address| {
+            if address == operand_address {
+                operand_lo
+            } else {
+                operand_hi
+            }
+        });
+
+        self.begin_history_entry();
+        self.last_instruction = Some((instruction.int, instruction.arg));
+        self.opcode_coverage[instruction.int as usize] += 1;
+        self.instructions += 1;
+        self.execute(instruction);
+    }
+
+    /// Opcode at PC, read with `Bus::peek` so stepping afterwards sees the same bus.
+    pub fn peek_opcode(&self) -> u8 {
+        self.address_space.peek(self.pc)
+    }
+
+    /// Decodes the instruction at `addr` without executing it or moving PC.
+    /// Panics on opcodes this build doesn't implement.
+    pub fn decode_at(&self, addr: u16) -> DecodedInstruction {
+        self.decode_with(addr, self.fetch(addr), |address| self.fetch(address))
+    }
+
+    fn decode_with<F: Fn(u16) -> u8>(&self, addr: u16, value: u8, read: F) -> DecodedInstruction {
+        let opcode = Instruction::try_from(value)
+            .unwrap_or_else(|_| panic!("Failed to decode opcode {value:#X}"));
+        let argument_kind = INSTRUCTIONS_ADDRESSING
+            .get(&opcode)
+            .unwrap_or_else(|| panic!("Unimplemented opcode {opcode:?}"));
+
+        let arg: Argument = match *argument_kind {
+            ArgumentType::Addr => Argument::Addr(read_word(&read, addr.wrapping_add(1))),
+            ArgumentType::Byte => Argument::Byte(read(addr.wrapping_add(1))),
+            ArgumentType::Void => Argument::Void,
+        };
+
+        DecodedInstruction { int: opcode, arg }
+    }
+
+    fn operand_address<F: Fn(u16) -> u8>(
+        &self,
+        arg: Argument,
+        addressing_type: AddressingType,
+        read: F,
+    ) -> Option<u16> {
+        match addressing_type {
+            AddressingType::XIndexedZeroIndirect => {
+                let arg0: u8 = TryInto::<u8>::try_into(arg)
+                    .expect("x indexed zero indirect operand fetch error: expected byte");
+
+                let x_indexed_ptr = u8::wrapping_add(self.x, arg0);
+
+                // The pointer never leaves the zero page, $FF takes its high byte from $00
+                Some(dword_from_bytes(
+                    read(x_indexed_ptr as u16),
+                    read(x_indexed_ptr.wrapping_add(1) as u16),
+                ))
+            }
+            AddressingType::ZeroPage => {
+                let arg0: u8 = TryInto::try_into(arg)
+                    .expect("zero page operand fetch error: expected zero page addr byte");
+
+                Some(arg0 as u16)
+            }
+            AddressingType::Immediate => None,
+            AddressingType::Absolute => {
+                let address: u16 =
+                    TryInto::try_into(arg).expect("absolute operand fetch error: expected address");
+
+                Some(address)
+            }
+            AddressingType::ZeroIndirectIndexed => {
+                let arg0: u8 = TryInto::try_into(arg)
+                    .expect("Zero indirect indexed operand fetch error: expected byte");
+
+                let low_byte = read(arg0 as u16);
+                let high_byte = read(arg0.wrapping_add(1) as u16);
+
+                Some(dword_from_bytes(low_byte, high_byte).wrapping_add(self.y as u16))
+            }
+            AddressingType::XIndexedZero => {
+                let arg0: u8 = TryInto::try_into(arg)
+                    .expect("X indexed zero page operand fetch error: expected byte");
+
+                Some(u8::wrapping_add(self.x, arg0) as u16)
+            }
+            AddressingType::YIndexedZero => {
+                let arg0: u8 = TryInto::try_into(arg)
+                    .expect("Y indexed zero page operand fetch error: expected byte");
+
+                Some(u8::wrapping_add(self.y, arg0) as u16)
+            }
+            AddressingType::XIndexedAbsolute => {
+                let address: u16 = TryInto::try_into(arg)
+                    .expect("X indexed absolute operand fetch error: expected address");
+
+                Some(address.wrapping_add(self.x as u16))
+            }
+            AddressingType::YIndexedAbsolute => {
+                let address: u16 = TryInto::try_into(arg)
+                    .expect("Y indexed absolute operand fetch error: expected address");
+
+                Some(address.wrapping_add(self.y as u16))
+            }
+        }
+    }
+
+    // Operand of a read-modify-write instruction, which always addresses memory
+    fn fetch_rmw_operand(
+        &self,
+        instr: DecodedInstruction,
+        addressing_type: AddressingType,
+    ) -> ShiftOperand {
+        let (value, address) = self.fetch_rmw_target(instr, addressing_type);
+
+        ShiftOperand::Memory(value, address)
+    }
+
+    // Value and address of a read-modify-write instruction's memory operand
+    fn fetch_rmw_target(
+        &self,
+        instr: DecodedInstruction,
+        addressing_type: AddressingType,
+    ) -> (u8, u16) {
+        let FetchOperandResult(value, address) = self.fetch_operand(instr, addressing_type);
+
+        (
+            value,
+            address.expect("read-modify-write: expected operand address"),
+        )
+    }
+
+    fn fetch_operand(
+        &self,
+        instr: DecodedInstruction,
+        addressing_type: AddressingType,
+    ) -> FetchOperandResult {
+        match self.operand_address(instr.arg, addressing_type, |address| self.fetch(address)) {
+            Some(address) => FetchOperandResult(self.fetch(address), Some(address)),
+            None => FetchOperandResult(
+                TryInto::try_into(instr.arg)
+                    .expect("immediate operand fetch error: expected immediate byte"),
+                None,
+            ),
+        }
+    }
+
+    /// Address the executor would access for `instr` with the current register values,
+    /// or `None` for immediate, implied and accumulator operands.
+    /// Pointers are read with `Bus::peek`, so this never touches the bus state.
+    pub fn effective_address(&self, instr: &DecodedInstruction) -> Option<u16> {
+        self.effective_address_with(instr, |address| self.address_space.peek(address))
+    }
+
+    fn effective_address_with<F: Fn(u16) -> u8>(
+        &self,
+        instr: &DecodedInstruction,
+        read: F,
+    ) -> Option<u16> {
+        let addressing_type = *INSTRUCTIONS_ADDRESSING_MODE.get(&instr.int)?;
+
+        self.operand_address(instr.arg, addressing_type, read)
+    }
+
+    fn execute(&mut self, instr: DecodedInstruction) {
+        let pc = self.pc;
+        let int = instr.int;
+        let flags_before = Into::<u8>::into(&self.p);
+        let mut extra_cycles: u64 = 0;
+
+        match instr.int {
+            Instruction::AdcXIndexedZeroIndirect => {
+                let FetchOperandResult(operand, _) =
+                    self.fetch_operand(instr, AddressingType::XIndexedZeroIndirect);
+                self.adc(operand);
+                self.pc = self.pc.wrapping_add(2);
+            }
+            Instruction::AdcZeroPage => {
+                let FetchOperandResult(arg0, _) =
+                    self.fetch_operand(instr, AddressingType::ZeroPage);
+                self.adc(arg0);
+                self.pc = self.pc.wrapping_add(2);
+            }
+            Instruction::AdcImmediate => {
+                let FetchOperandResult(arg0, _) =
+                    self.fetch_operand(instr, AddressingType::Immediate);
+
+                self.adc(arg0);
+                self.pc = self.pc.wrapping_add(2);
+            }
+            Instruction::AdcAbsolute => {
+                let FetchOperandResult(arg0, _) =
+                    self.fetch_operand(instr, AddressingType::Absolute);
+                self.adc(arg0);
+                self.pc = self.pc.wrapping_add(3);
+            }
+            Instruction::AdcZeroIndirectIndexed => {
+                let FetchOperandResult(arg0, _) =
+                    self.fetch_operand(instr, AddressingType::ZeroIndirectIndexed);
+                self.adc(arg0);
+                self.pc = self.pc.wrapping_add(2);
+            }
+            Instruction::AdcXIndexedZero => {
+                let FetchOperandResult(arg0, _) =
+                    self.fetch_operand(instr, AddressingType::XIndexedZero);
+                self.adc(arg0);
+                self.pc = self.pc.wrapping_add(2);
+            }
+            Instruction::AdcYIndexedAbsolute => {
+                let FetchOperandResult(arg0, _) =
+                    self.fetch_operand(instr, AddressingType::YIndexedAbsolute);
+                self.adc(arg0);
+                self.pc = self.pc.wrapping_add(3);
             }
             Instruction::AdcXIndexedAbsolute => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::XIndexedAbsolute);
                 self.adc(arg0);
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             // AND
             Instruction::AndXIndexedZeroIndirect => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::XIndexedZeroIndirect);
                 self.and(arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::AndZeroPage => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::ZeroPage);
                 self.and(arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::AndImmediate => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Immediate);
                 self.and(arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::AndAbsolute => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Absolute);
                 self.and(arg0);
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             Instruction::AndZeroIndirectIndexed => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::ZeroIndirectIndexed);
                 self.and(arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::AndXIndexedZero => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::XIndexedZero);
                 self.and(arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::AndYIndexedAbsolute => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::YIndexedAbsolute);
                 self.and(arg0);
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             Instruction::AndXIndexedAbsolute => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::XIndexedAbsolute);
                 self.and(arg0);
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             // ASL
             Instruction::AslAbsolute => {
-                let FetchOperandResult(arg0, address) =
-                    self.fetch_operand(instr, AddressingType::Absolute);
-                self.asl(ShiftOperand::Value(arg0), address);
-                self.pc += 3;
+                let operand = self.fetch_rmw_operand(instr, AddressingType::Absolute);
+                self.asl(operand);
+                self.pc = self.pc.wrapping_add(3);
             }
             Instruction::AslZeroPage => {
-                let FetchOperandResult(arg0, address) =
-                    self.fetch_operand(instr, AddressingType::ZeroPage);
-                self.asl(ShiftOperand::Value(arg0), address);
-                self.pc += 2;
+                let operand = self.fetch_rmw_operand(instr, AddressingType::ZeroPage);
+                self.asl(operand);
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::AslAccumulator => {
-                self.asl(ShiftOperand::A, None);
-                self.pc += 1;
+                self.asl(ShiftOperand::A);
+                self.pc = self.pc.wrapping_add(1);
             }
             Instruction::AslXIndexedZero => {
-                let FetchOperandResult(arg0, address) =
-                    self.fetch_operand(instr, AddressingType::XIndexedZero);
-                self.asl(ShiftOperand::Value(arg0), address);
-                self.pc += 2;
+                let operand = self.fetch_rmw_operand(instr, AddressingType::XIndexedZero);
+                self.asl(operand);
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::AslXIndexedAbsolute => {
-                let FetchOperandResult(arg0, address) =
-                    self.fetch_operand(instr, AddressingType::XIndexedAbsolute);
-                self.asl(ShiftOperand::Value(arg0), address);
-                self.pc += 3;
+                let operand = self.fetch_rmw_operand(instr, AddressingType::XIndexedAbsolute);
+                self.asl(operand);
+                self.pc = self.pc.wrapping_add(3);
             }
             // Branch
             Instruction::Bcc => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Immediate);
 
-                self.pc += 2;
-                self.branch(arg0 as i8, FlagPosition::Carry, false);
+                self.pc = self.pc.wrapping_add(2);
+                let BranchResult(taken, page_crossed) =
+                    self.branch(arg0 as i8, FlagPosition::Carry, false);
+                extra_cycles += taken as u64 + page_crossed as u64;
             }
             Instruction::Bcs => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Immediate);
 
-                self.pc += 2;
-                self.branch(arg0 as i8, FlagPosition::Carry, true);
+                self.pc = self.pc.wrapping_add(2);
+                let BranchResult(taken, page_crossed) =
+                    self.branch(arg0 as i8, FlagPosition::Carry, true);
+                extra_cycles += taken as u64 + page_crossed as u64;
             }
             Instruction::Beq => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Immediate);
 
-                self.pc += 2;
-                self.branch(arg0 as i8, FlagPosition::Zero, true);
+                self.pc = self.pc.wrapping_add(2);
+                let BranchResult(taken, page_crossed) =
+                    self.branch(arg0 as i8, FlagPosition::Zero, true);
+                extra_cycles += taken as u64 + page_crossed as u64;
             }
             Instruction::Bne => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Immediate);
 
-                self.pc += 2;
-                self.branch(arg0 as i8, FlagPosition::Zero, false);
+                self.pc = self.pc.wrapping_add(2);
+                let BranchResult(taken, page_crossed) =
+                    self.branch(arg0 as i8, FlagPosition::Zero, false);
+                extra_cycles += taken as u64 + page_crossed as u64;
             }
             Instruction::Bmi => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Immediate);
 
-                self.pc += 2;
-                self.branch(arg0 as i8, FlagPosition::Negative, true);
+                self.pc = self.pc.wrapping_add(2);
+                let BranchResult(taken, page_crossed) =
+                    self.branch(arg0 as i8, FlagPosition::Negative, true);
+                extra_cycles += taken as u64 + page_crossed as u64;
             }
             Instruction::Bpl => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Immediate);
 
-                self.pc += 2;
-                self.branch(arg0 as i8, FlagPosition::Negative, false);
+                self.pc = self.pc.wrapping_add(2);
+                let BranchResult(taken, page_crossed) =
+                    self.branch(arg0 as i8, FlagPosition::Negative, false);
+                extra_cycles += taken as u64 + page_crossed as u64;
             }
             Instruction::Bvc => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Immediate);
 
-                self.pc += 2;
-                self.branch(arg0 as i8, FlagPosition::Overflow, false);
+                self.pc = self.pc.wrapping_add(2);
+                let BranchResult(taken, page_crossed) =
+                    self.branch(arg0 as i8, FlagPosition::Overflow, false);
+                extra_cycles += taken as u64 + page_crossed as u64;
             }
             Instruction::Bvs => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Immediate);
 
-                self.pc += 2;
-                self.branch(arg0 as i8, FlagPosition::Overflow, true);
+                self.pc = self.pc.wrapping_add(2);
+                let BranchResult(taken, page_crossed) =
+                    self.branch(arg0 as i8, FlagPosition::Overflow, true);
+                extra_cycles += taken as u64 + page_crossed as u64;
             }
             // BIT
             Instruction::BitZeroPage => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::ZeroPage);
 
-                self.bit(arg0);
-                self.pc += 2;
+                self.bit(arg0, false);
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::BitAbsolute => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Absolute);
 
-                self.bit(arg0);
-                self.pc += 3;
+                self.bit(arg0, false);
+                self.pc = self.pc.wrapping_add(3);
+            }
+            Instruction::BitImmediate if self.variant == Variant::Cmos => {
+                let FetchOperandResult(arg0, _) =
+                    self.fetch_operand(instr, AddressingType::Immediate);
+
+                self.bit(arg0, true);
+                self.pc = self.pc.wrapping_add(2);
+            }
+            // Undocumented two-byte NOP on NMOS
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::BitImmediate => {
+                self.pc = self.pc.wrapping_add(2);
             }
             // Software interrupt
             Instruction::Brk => {
@@ -458,252 +1699,243 @@ impl Cpu {
             // Flag reset
             Instruction::Clc => {
                 self.clear_flag(FlagPosition::Carry);
-                self.pc += 1;
+                self.pc = self.pc.wrapping_add(1);
             }
             Instruction::Cld => {
                 self.clear_flag(FlagPosition::DecimalMode);
-                self.pc += 1;
+                self.pc = self.pc.wrapping_add(1);
             }
             Instruction::Cli => {
                 self.clear_flag(FlagPosition::IrqDisable);
-                self.pc += 1;
+                self.pc = self.pc.wrapping_add(1);
             }
             Instruction::Clv => {
                 self.clear_flag(FlagPosition::Overflow);
-                self.pc += 1;
+                self.pc = self.pc.wrapping_add(1);
             }
             // CMP
             Instruction::CmpXIndexedZeroIndirect => {
                 let FetchOperandResult(operand, _) =
                     self.fetch_operand(instr, AddressingType::XIndexedZeroIndirect);
                 self.cmp(self.a, operand);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::CmpZeroPage => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::ZeroPage);
                 self.cmp(self.a, arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::CmpImmediate => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Immediate);
 
                 self.cmp(self.a, arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::CmpAbsolute => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Absolute);
                 self.cmp(self.a, arg0);
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             Instruction::CmpZeroIndirectIndexed => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::ZeroIndirectIndexed);
                 self.cmp(self.a, arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::CmpXIndexedZero => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::XIndexedZero);
                 self.cmp(self.a, arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::CmpYIndexedAbsolute => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::YIndexedAbsolute);
                 self.cmp(self.a, arg0);
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             Instruction::CmpXIndexedAbsolute => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::XIndexedAbsolute);
                 self.cmp(self.a, arg0);
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             // CPX
             Instruction::CpxZeroPage => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::ZeroPage);
                 self.cmp(self.x, arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::CpxImmediate => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Immediate);
 
                 self.cmp(self.x, arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::CpxAbsolute => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Absolute);
                 self.cmp(self.x, arg0);
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             // CPY
             Instruction::CpyZeroPage => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::ZeroPage);
                 self.cmp(self.y, arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::CpyImmediate => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Immediate);
 
                 self.cmp(self.y, arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::CpyAbsolute => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Absolute);
                 self.cmp(self.y, arg0);
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             // DEC
             Instruction::DecAbsolute => {
-                let FetchOperandResult(arg0, address) =
-                    self.fetch_operand(instr, AddressingType::Absolute);
-                self.inc_dec(false, IncDecOperand::Value(arg0), address);
-                self.pc += 3;
+                let (value, address) = self.fetch_rmw_target(instr, AddressingType::Absolute);
+                self.inc_dec(false, IncDecOperand::Memory(value, address));
+                self.pc = self.pc.wrapping_add(3);
             }
             Instruction::DecZeroPage => {
-                let FetchOperandResult(arg0, address) =
-                    self.fetch_operand(instr, AddressingType::ZeroPage);
-                self.inc_dec(false, IncDecOperand::Value(arg0), address);
-                self.pc += 2;
+                let (value, address) = self.fetch_rmw_target(instr, AddressingType::ZeroPage);
+                self.inc_dec(false, IncDecOperand::Memory(value, address));
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::DecXIndexedZero => {
-                let FetchOperandResult(arg0, address) =
-                    self.fetch_operand(instr, AddressingType::XIndexedZero);
-                self.inc_dec(false, IncDecOperand::Value(arg0), address);
-                self.pc += 2;
+                let (value, address) = self.fetch_rmw_target(instr, AddressingType::XIndexedZero);
+                self.inc_dec(false, IncDecOperand::Memory(value, address));
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::DecXIndexedAbsolute => {
-                let FetchOperandResult(arg0, address) =
-                    self.fetch_operand(instr, AddressingType::XIndexedAbsolute);
-                self.inc_dec(false, IncDecOperand::Value(arg0), address);
-                self.pc += 3;
+                let (value, address) =
+                    self.fetch_rmw_target(instr, AddressingType::XIndexedAbsolute);
+                self.inc_dec(false, IncDecOperand::Memory(value, address));
+                self.pc = self.pc.wrapping_add(3);
             }
             // DEX
             Instruction::Dex => {
-                self.inc_dec(false, IncDecOperand::X, None);
-                self.pc += 1;
+                self.inc_dec(false, IncDecOperand::X);
+                self.pc = self.pc.wrapping_add(1);
             }
             // DEY
             Instruction::Dey => {
-                self.inc_dec(false, IncDecOperand::Y, None);
-                self.pc += 1;
+                self.inc_dec(false, IncDecOperand::Y);
+                self.pc = self.pc.wrapping_add(1);
             }
             // EOR
             Instruction::EorXIndexedZeroIndirect => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::XIndexedZeroIndirect);
                 self.eor(arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::EorZeroPage => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::ZeroPage);
                 self.eor(arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::EorImmediate => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Immediate);
                 self.eor(arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::EorAbsolute => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Absolute);
                 self.eor(arg0);
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             Instruction::EorZeroIndirectIndexed => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::ZeroIndirectIndexed);
                 self.eor(arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::EorXIndexedZero => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::XIndexedZero);
                 self.eor(arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::EorYIndexedAbsolute => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::YIndexedAbsolute);
                 self.eor(arg0);
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             Instruction::EorXIndexedAbsolute => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::XIndexedAbsolute);
                 self.eor(arg0);
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             // INC
             Instruction::IncAbsolute => {
-                let FetchOperandResult(arg0, address) =
-                    self.fetch_operand(instr, AddressingType::Absolute);
-                self.inc_dec(true, IncDecOperand::Value(arg0), address);
-                self.pc += 3;
+                let (value, address) = self.fetch_rmw_target(instr, AddressingType::Absolute);
+                self.inc_dec(true, IncDecOperand::Memory(value, address));
+                self.pc = self.pc.wrapping_add(3);
             }
             Instruction::IncZeroPage => {
-                let FetchOperandResult(arg0, address) =
-                    self.fetch_operand(instr, AddressingType::ZeroPage);
-                self.inc_dec(true, IncDecOperand::Value(arg0), address);
-                self.pc += 2;
+                let (value, address) = self.fetch_rmw_target(instr, AddressingType::ZeroPage);
+                self.inc_dec(true, IncDecOperand::Memory(value, address));
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::IncXIndexedZero => {
-                let FetchOperandResult(arg0, address) =
-                    self.fetch_operand(instr, AddressingType::XIndexedZero);
-                self.inc_dec(true, IncDecOperand::Value(arg0), address);
-                self.pc += 2;
+                let (value, address) = self.fetch_rmw_target(instr, AddressingType::XIndexedZero);
+                self.inc_dec(true, IncDecOperand::Memory(value, address));
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::IncXIndexedAbsolute => {
-                let FetchOperandResult(arg0, address) =
-                    self.fetch_operand(instr, AddressingType::XIndexedAbsolute);
-                self.inc_dec(true, IncDecOperand::Value(arg0), address);
-                self.pc += 3;
+                let (value, address) =
+                    self.fetch_rmw_target(instr, AddressingType::XIndexedAbsolute);
+                self.inc_dec(true, IncDecOperand::Memory(value, address));
+                self.pc = self.pc.wrapping_add(3);
             }
             // INX
             Instruction::Inx => {
-                self.inc_dec(true, IncDecOperand::X, None);
-                self.pc += 1;
+                self.inc_dec(true, IncDecOperand::X);
+                self.pc = self.pc.wrapping_add(1);
             }
             // INY
             Instruction::Iny => {
-                self.inc_dec(true, IncDecOperand::Y, None);
-                self.pc += 1;
+                self.inc_dec(true, IncDecOperand::Y);
+                self.pc = self.pc.wrapping_add(1);
             }
             Instruction::Nop => {
-                self.pc += 1;
+                self.pc = self.pc.wrapping_add(1);
             }
             Instruction::Jmp => {
                 let addr: u16 =
                     TryInto::try_into(instr.arg).expect("JMP nnnn execute error: expected address");
-                println!("jump addr {addr:#X}");
 
                 self.pc = addr;
             }
             Instruction::JmpIndirect => {
                 let indirect_addr: u16 = TryInto::try_into(instr.arg)
                     .expect("JMP (nnnn) execute error: expected address");
-                println!("jump addr {indirect_addr:#X}");
 
-                let addr = self.fetch_dword(indirect_addr);
+                let addr = self.jmp_indirect_target(indirect_addr);
 
                 self.pc = addr;
             }
             Instruction::Jsr => {
                 let addr: u16 =
                     TryInto::try_into(instr.arg).expect("JSR execute error: expected address");
-                println!("jump addr {addr:#X}");
 
                 self.jsr(addr);
             }
@@ -712,270 +1944,258 @@ impl Cpu {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::XIndexedZeroIndirect);
                 self.ld(LdOperand::A, arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::LdaZeroPage => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::ZeroPage);
                 self.ld(LdOperand::A, arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::LdaImmediate => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Immediate);
                 self.ld(LdOperand::A, arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::LdaAbsolute => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Absolute);
                 self.ld(LdOperand::A, arg0);
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             Instruction::LdaZeroIndirectIndexed => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::ZeroIndirectIndexed);
                 self.ld(LdOperand::A, arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::LdaXIndexedZero => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::XIndexedZero);
                 self.ld(LdOperand::A, arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::LdaYIndexedAbsolute => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::YIndexedAbsolute);
                 self.ld(LdOperand::A, arg0);
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             Instruction::LdaXIndexedAbsolute => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::XIndexedAbsolute);
                 self.ld(LdOperand::A, arg0);
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             // LDX
             Instruction::LdxZeroPage => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::ZeroPage);
                 self.ld(LdOperand::X, arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::LdxImmediate => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Immediate);
                 self.ld(LdOperand::X, arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::LdxAbsolute => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Absolute);
                 self.ld(LdOperand::X, arg0);
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             Instruction::LdxYIndexedAbsolute => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::YIndexedAbsolute);
                 self.ld(LdOperand::X, arg0);
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             Instruction::LdxYIndexedZero => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::YIndexedZero);
                 self.ld(LdOperand::X, arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             // LDY
             Instruction::LdyZeroPage => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::ZeroPage);
                 self.ld(LdOperand::Y, arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::LdyImmediate => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Immediate);
                 self.ld(LdOperand::Y, arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::LdyAbsolute => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Absolute);
                 self.ld(LdOperand::Y, arg0);
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             Instruction::LdyXIndexedAbsolute => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::XIndexedAbsolute);
                 self.ld(LdOperand::Y, arg0);
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             Instruction::LdyXIndexedZero => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::XIndexedZero);
                 self.ld(LdOperand::Y, arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             // LSR
             Instruction::LsrAbsolute => {
-                let FetchOperandResult(arg0, address) =
-                    self.fetch_operand(instr, AddressingType::Absolute);
-                self.lsr(ShiftOperand::Value(arg0), address);
+                let operand = self.fetch_rmw_operand(instr, AddressingType::Absolute);
+                self.lsr(operand);
 
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             Instruction::LsrZeroPage => {
-                let FetchOperandResult(arg0, address) =
-                    self.fetch_operand(instr, AddressingType::ZeroPage);
-                self.lsr(ShiftOperand::Value(arg0), address);
-                self.pc += 2;
+                let operand = self.fetch_rmw_operand(instr, AddressingType::ZeroPage);
+                self.lsr(operand);
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::LsrAccumulator => {
-                self.lsr(ShiftOperand::A, None);
-                self.pc += 1;
+                self.lsr(ShiftOperand::A);
+                self.pc = self.pc.wrapping_add(1);
             }
             Instruction::LsrXIndexedAbsolute => {
-                let FetchOperandResult(arg0, address) =
-                    self.fetch_operand(instr, AddressingType::XIndexedAbsolute);
-                self.lsr(ShiftOperand::Value(arg0), address);
-                self.pc += 3;
+                let operand = self.fetch_rmw_operand(instr, AddressingType::XIndexedAbsolute);
+                self.lsr(operand);
+                self.pc = self.pc.wrapping_add(3);
             }
             Instruction::LsrXIndexedZero => {
-                let FetchOperandResult(arg0, address) =
-                    self.fetch_operand(instr, AddressingType::XIndexedZero);
-                self.lsr(ShiftOperand::Value(arg0), address);
-                self.pc += 2;
+                let operand = self.fetch_rmw_operand(instr, AddressingType::XIndexedZero);
+                self.lsr(operand);
+                self.pc = self.pc.wrapping_add(2);
             }
             // ORA
             Instruction::OraXIndexedZeroIndirect => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::XIndexedZeroIndirect);
                 self.ora(arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::OraZeroPage => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::ZeroPage);
                 self.ora(arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::OraImmediate => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Immediate);
                 self.ora(arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::OraAbsolute => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Absolute);
                 self.ora(arg0);
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             Instruction::OraZeroIndirectIndexed => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::ZeroIndirectIndexed);
                 self.ora(arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::OraXIndexedZero => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::XIndexedZero);
                 self.ora(arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::OraYIndexedAbsolute => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::YIndexedAbsolute);
                 self.ora(arg0);
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             Instruction::OraXIndexedAbsolute => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::XIndexedAbsolute);
                 self.ora(arg0);
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             // PHA
             Instruction::Pha => {
                 self.push(self.a);
-                self.pc += 1;
+                self.pc = self.pc.wrapping_add(1);
             }
             // PHP
             Instruction::Php => {
-                self.push(Into::<u8>::into(&self.p) | 0x1 << 5 | 0x1 << 4);
-                self.pc += 1;
+                self.php();
+                self.pc = self.pc.wrapping_add(1);
             }
             // PLA
             Instruction::Pla => {
                 self.pla();
-                self.pc += 1;
+                self.pc = self.pc.wrapping_add(1);
             }
             // PLP
             Instruction::Plp => {
                 self.plp();
-                self.pc += 1;
+                self.pc = self.pc.wrapping_add(1);
             }
             // ROL
             Instruction::RolAbsolute => {
-                let FetchOperandResult(arg0, address) =
-                    self.fetch_operand(instr, AddressingType::Absolute);
-                self.rol(ShiftOperand::Value(arg0), address);
+                let operand = self.fetch_rmw_operand(instr, AddressingType::Absolute);
+                self.rol(operand);
 
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             Instruction::RolZeroPage => {
-                let FetchOperandResult(arg0, address) =
-                    self.fetch_operand(instr, AddressingType::ZeroPage);
-                self.rol(ShiftOperand::Value(arg0), address);
-                self.pc += 2;
+                let operand = self.fetch_rmw_operand(instr, AddressingType::ZeroPage);
+                self.rol(operand);
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::RolAccumulator => {
-                self.rol(ShiftOperand::A, None);
-                self.pc += 1;
+                self.rol(ShiftOperand::A);
+                self.pc = self.pc.wrapping_add(1);
             }
             Instruction::RolXIndexedZero => {
-                let FetchOperandResult(arg0, address) =
-                    self.fetch_operand(instr, AddressingType::XIndexedZero);
-                self.rol(ShiftOperand::Value(arg0), address);
-                self.pc += 2;
+                let operand = self.fetch_rmw_operand(instr, AddressingType::XIndexedZero);
+                self.rol(operand);
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::RolXIndexedAbsolute => {
-                let FetchOperandResult(arg0, address) =
-                    self.fetch_operand(instr, AddressingType::XIndexedAbsolute);
-                self.rol(ShiftOperand::Value(arg0), address);
-                self.pc += 3;
+                let operand = self.fetch_rmw_operand(instr, AddressingType::XIndexedAbsolute);
+                self.rol(operand);
+                self.pc = self.pc.wrapping_add(3);
             }
             // ROR
             Instruction::RorAbsolute => {
-                let FetchOperandResult(arg0, address) =
-                    self.fetch_operand(instr, AddressingType::Absolute);
-                self.ror(ShiftOperand::Value(arg0), address);
+                let operand = self.fetch_rmw_operand(instr, AddressingType::Absolute);
+                self.ror(operand);
 
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             Instruction::RorZeroPage => {
-                let FetchOperandResult(arg0, address) =
-                    self.fetch_operand(instr, AddressingType::ZeroPage);
-                self.ror(ShiftOperand::Value(arg0), address);
-                self.pc += 2;
+                let operand = self.fetch_rmw_operand(instr, AddressingType::ZeroPage);
+                self.ror(operand);
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::RorAccumulator => {
-                self.ror(ShiftOperand::A, None);
-                self.pc += 1;
+                self.ror(ShiftOperand::A);
+                self.pc = self.pc.wrapping_add(1);
             }
             Instruction::RorXIndexedZero => {
-                let FetchOperandResult(arg0, address) =
-                    self.fetch_operand(instr, AddressingType::XIndexedZero);
-                self.ror(ShiftOperand::Value(arg0), address);
-                self.pc += 2;
+                let operand = self.fetch_rmw_operand(instr, AddressingType::XIndexedZero);
+                self.ror(operand);
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::RorXIndexedAbsolute => {
-                let FetchOperandResult(arg0, address) =
-                    self.fetch_operand(instr, AddressingType::XIndexedAbsolute);
-                self.ror(ShiftOperand::Value(arg0), address);
-                self.pc += 3;
+                let operand = self.fetch_rmw_operand(instr, AddressingType::XIndexedAbsolute);
+                self.ror(operand);
+                self.pc = self.pc.wrapping_add(3);
             }
             // RTI
             Instruction::Rti => {
@@ -990,179 +2210,455 @@ impl Cpu {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::XIndexedZeroIndirect);
                 self.sbc(arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::SbcZeroPage => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::ZeroPage);
                 self.sbc(arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::SbcImmediate => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Immediate);
                 self.sbc(arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::SbcAbsolute => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::Absolute);
                 self.sbc(arg0);
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             Instruction::SbcZeroIndirectIndexed => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::ZeroIndirectIndexed);
                 self.sbc(arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::SbcXIndexedZero => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::XIndexedZero);
                 self.sbc(arg0);
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::SbcYIndexedAbsolute => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::YIndexedAbsolute);
                 self.sbc(arg0);
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             Instruction::SbcXIndexedAbsolute => {
                 let FetchOperandResult(arg0, _) =
                     self.fetch_operand(instr, AddressingType::XIndexedAbsolute);
                 self.sbc(arg0);
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             // Set flags
             Instruction::Sec => {
                 self.sec();
-                self.pc += 1;
+                self.pc = self.pc.wrapping_add(1);
             }
             Instruction::Sed => {
                 self.sed();
-                self.pc += 1;
+                self.pc = self.pc.wrapping_add(1);
             }
             Instruction::Sei => {
                 self.sei();
-                self.pc += 1;
+                self.pc = self.pc.wrapping_add(1);
             }
             // STA
             Instruction::StaXIndexedZeroIndirect => {
                 let FetchOperandResult(_, address) =
                     self.fetch_operand(instr, AddressingType::XIndexedZeroIndirect);
                 self.st(LdOperand::A, address.expect("STA: expected address"));
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::StaZeroPage => {
                 let FetchOperandResult(_, address) =
                     self.fetch_operand(instr, AddressingType::ZeroPage);
                 self.st(LdOperand::A, address.expect("STA: expected address"));
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::StaAbsolute => {
                 let FetchOperandResult(_, address) =
                     self.fetch_operand(instr, AddressingType::Absolute);
                 self.st(LdOperand::A, address.expect("STA: expected address"));
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             Instruction::StaZeroIndirectIndexed => {
                 let FetchOperandResult(_, address) =
                     self.fetch_operand(instr, AddressingType::ZeroIndirectIndexed);
                 self.st(LdOperand::A, address.expect("STA: expected address"));
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::StaXIndexedZero => {
                 let FetchOperandResult(_, address) =
                     self.fetch_operand(instr, AddressingType::XIndexedZero);
                 self.st(LdOperand::A, address.expect("STA: expected address"));
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::StaYIndexedAbsolute => {
                 let FetchOperandResult(_, address) =
                     self.fetch_operand(instr, AddressingType::YIndexedAbsolute);
                 self.st(LdOperand::A, address.expect("STA: expected address"));
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             Instruction::StaXIndexedAbsolute => {
                 let FetchOperandResult(_, address) =
                     self.fetch_operand(instr, AddressingType::XIndexedAbsolute);
                 self.st(LdOperand::A, address.expect("STA: expected address"));
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             // STX
             Instruction::StxZeroPage => {
                 let FetchOperandResult(_, address) =
                     self.fetch_operand(instr, AddressingType::ZeroPage);
                 self.st(LdOperand::X, address.expect("STX: expected address"));
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::StxAbsolute => {
                 let FetchOperandResult(_, address) =
                     self.fetch_operand(instr, AddressingType::Absolute);
                 self.st(LdOperand::X, address.expect("STX: expected address"));
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             Instruction::StxYIndexedZero => {
                 let FetchOperandResult(_, address) =
                     self.fetch_operand(instr, AddressingType::YIndexedZero);
                 self.st(LdOperand::X, address.expect("STX: expected address"));
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             // STY
             Instruction::StyZeroPage => {
                 let FetchOperandResult(_, address) =
                     self.fetch_operand(instr, AddressingType::ZeroPage);
                 self.st(LdOperand::Y, address.expect("STY: expected address"));
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::StyAbsolute => {
                 let FetchOperandResult(_, address) =
                     self.fetch_operand(instr, AddressingType::Absolute);
                 self.st(LdOperand::Y, address.expect("STY: expected address"));
-                self.pc += 3;
+                self.pc = self.pc.wrapping_add(3);
             }
             Instruction::StyXIndexedZero => {
                 let FetchOperandResult(_, address) =
                     self.fetch_operand(instr, AddressingType::XIndexedZero);
                 self.st(LdOperand::Y, address.expect("STY: expected address"));
-                self.pc += 2;
+                self.pc = self.pc.wrapping_add(2);
             }
             // Transfer
             Instruction::Tax => {
                 self.tax();
-                self.pc += 1;
+                self.pc = self.pc.wrapping_add(1);
             }
             Instruction::Tay => {
                 self.tay();
-                self.pc += 1;
+                self.pc = self.pc.wrapping_add(1);
             }
             Instruction::Tsx => {
                 self.tsx();
-                self.pc += 1;
+                self.pc = self.pc.wrapping_add(1);
             }
             Instruction::Txa => {
                 self.txa();
-                self.pc += 1;
+                self.pc = self.pc.wrapping_add(1);
             }
             Instruction::Txs => {
                 self.txs();
-                self.pc += 1;
+                self.pc = self.pc.wrapping_add(1);
             }
             Instruction::Tya => {
                 self.tya();
-                self.pc += 1;
-            }
-            _ => panic!("Unknown instruction {:?}", instr.int),
+                self.pc = self.pc.wrapping_add(1);
+            }
+            // Undocumented multi-byte NOPs
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::Dop80 | Instruction::Dop82 | Instruction::DopC2 | Instruction::DopE2 => {
+                self.pc = self.pc.wrapping_add(2);
+            }
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::Dop04 | Instruction::Dop44 | Instruction::Dop64 => {
+                // Dummy read, the value is discarded
+                self.fetch_operand(instr, AddressingType::ZeroPage);
+                self.pc = self.pc.wrapping_add(2);
+            }
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::Dop14
+            | Instruction::Dop34
+            | Instruction::Dop54
+            | Instruction::Dop74
+            | Instruction::DopD4
+            | Instruction::DopF4 => {
+                // Dummy read, the value is discarded
+                self.fetch_operand(instr, AddressingType::XIndexedZero);
+                self.pc = self.pc.wrapping_add(2);
+            }
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::Top0C => {
+                // Dummy read, the value is discarded
+                self.fetch_operand(instr, AddressingType::Absolute);
+                self.pc = self.pc.wrapping_add(3);
+            }
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::Top1C
+            | Instruction::Top3C
+            | Instruction::Top5C
+            | Instruction::Top7C
+            | Instruction::TopDC
+            | Instruction::TopFC => {
+                // Dummy read, the value is discarded
+                self.fetch_operand(instr, AddressingType::XIndexedAbsolute);
+                self.pc = self.pc.wrapping_add(3);
+            }
+            // SLO: ASL then ORA
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::SloZeroPage => {
+                let operand = self.fetch_rmw_operand(instr, AddressingType::ZeroPage);
+                let result = self.asl(operand);
+                self.ora(result);
+                self.pc = self.pc.wrapping_add(2);
+            }
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::SloXIndexedZero => {
+                let operand = self.fetch_rmw_operand(instr, AddressingType::XIndexedZero);
+                let result = self.asl(operand);
+                self.ora(result);
+                self.pc = self.pc.wrapping_add(2);
+            }
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::SloAbsolute => {
+                let operand = self.fetch_rmw_operand(instr, AddressingType::Absolute);
+                let result = self.asl(operand);
+                self.ora(result);
+                self.pc = self.pc.wrapping_add(3);
+            }
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::SloXIndexedAbsolute => {
+                let operand = self.fetch_rmw_operand(instr, AddressingType::XIndexedAbsolute);
+                let result = self.asl(operand);
+                self.ora(result);
+                self.pc = self.pc.wrapping_add(3);
+            }
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::SloYIndexedAbsolute => {
+                let operand = self.fetch_rmw_operand(instr, AddressingType::YIndexedAbsolute);
+                let result = self.asl(operand);
+                self.ora(result);
+                self.pc = self.pc.wrapping_add(3);
+            }
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::SloXIndexedZeroIndirect => {
+                let operand = self.fetch_rmw_operand(instr, AddressingType::XIndexedZeroIndirect);
+                let result = self.asl(operand);
+                self.ora(result);
+                self.pc = self.pc.wrapping_add(2);
+            }
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::SloZeroIndirectIndexed => {
+                let operand = self.fetch_rmw_operand(instr, AddressingType::ZeroIndirectIndexed);
+                let result = self.asl(operand);
+                self.ora(result);
+                self.pc = self.pc.wrapping_add(2);
+            }
+            // RLA: ROL then AND
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::RlaZeroPage => {
+                let operand = self.fetch_rmw_operand(instr, AddressingType::ZeroPage);
+                let result = self.rol(operand);
+                self.and(result);
+                self.pc = self.pc.wrapping_add(2);
+            }
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::RlaXIndexedZero => {
+                let operand = self.fetch_rmw_operand(instr, AddressingType::XIndexedZero);
+                let result = self.rol(operand);
+                self.and(result);
+                self.pc = self.pc.wrapping_add(2);
+            }
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::RlaAbsolute => {
+                let operand = self.fetch_rmw_operand(instr, AddressingType::Absolute);
+                let result = self.rol(operand);
+                self.and(result);
+                self.pc = self.pc.wrapping_add(3);
+            }
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::RlaXIndexedAbsolute => {
+                let operand = self.fetch_rmw_operand(instr, AddressingType::XIndexedAbsolute);
+                let result = self.rol(operand);
+                self.and(result);
+                self.pc = self.pc.wrapping_add(3);
+            }
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::RlaYIndexedAbsolute => {
+                let operand = self.fetch_rmw_operand(instr, AddressingType::YIndexedAbsolute);
+                let result = self.rol(operand);
+                self.and(result);
+                self.pc = self.pc.wrapping_add(3);
+            }
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::RlaXIndexedZeroIndirect => {
+                let operand = self.fetch_rmw_operand(instr, AddressingType::XIndexedZeroIndirect);
+                let result = self.rol(operand);
+                self.and(result);
+                self.pc = self.pc.wrapping_add(2);
+            }
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::RlaZeroIndirectIndexed => {
+                let operand = self.fetch_rmw_operand(instr, AddressingType::ZeroIndirectIndexed);
+                let result = self.rol(operand);
+                self.and(result);
+                self.pc = self.pc.wrapping_add(2);
+            }
+            // SRE: LSR then EOR
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::SreZeroPage => {
+                let operand = self.fetch_rmw_operand(instr, AddressingType::ZeroPage);
+                let result = self.lsr(operand);
+                self.eor(result);
+                self.pc = self.pc.wrapping_add(2);
+            }
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::SreXIndexedZero => {
+                let operand = self.fetch_rmw_operand(instr, AddressingType::XIndexedZero);
+                let result = self.lsr(operand);
+                self.eor(result);
+                self.pc = self.pc.wrapping_add(2);
+            }
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::SreAbsolute => {
+                let operand = self.fetch_rmw_operand(instr, AddressingType::Absolute);
+                let result = self.lsr(operand);
+                self.eor(result);
+                self.pc = self.pc.wrapping_add(3);
+            }
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::SreXIndexedAbsolute => {
+                let operand = self.fetch_rmw_operand(instr, AddressingType::XIndexedAbsolute);
+                let result = self.lsr(operand);
+                self.eor(result);
+                self.pc = self.pc.wrapping_add(3);
+            }
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::SreYIndexedAbsolute => {
+                let operand = self.fetch_rmw_operand(instr, AddressingType::YIndexedAbsolute);
+                let result = self.lsr(operand);
+                self.eor(result);
+                self.pc = self.pc.wrapping_add(3);
+            }
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::SreXIndexedZeroIndirect => {
+                let operand = self.fetch_rmw_operand(instr, AddressingType::XIndexedZeroIndirect);
+                let result = self.lsr(operand);
+                self.eor(result);
+                self.pc = self.pc.wrapping_add(2);
+            }
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::SreZeroIndirectIndexed => {
+                let operand = self.fetch_rmw_operand(instr, AddressingType::ZeroIndirectIndexed);
+                let result = self.lsr(operand);
+                self.eor(result);
+                self.pc = self.pc.wrapping_add(2);
+            }
+            // RRA: ROR then ADC
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::RraZeroPage => {
+                let operand = self.fetch_rmw_operand(instr, AddressingType::ZeroPage);
+                let result = self.ror(operand);
+                self.adc(result);
+                self.pc = self.pc.wrapping_add(2);
+            }
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::RraXIndexedZero => {
+                let operand = self.fetch_rmw_operand(instr, AddressingType::XIndexedZero);
+                let result = self.ror(operand);
+                self.adc(result);
+                self.pc = self.pc.wrapping_add(2);
+            }
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::RraAbsolute => {
+                let operand = self.fetch_rmw_operand(instr, AddressingType::Absolute);
+                let result = self.ror(operand);
+                self.adc(result);
+                self.pc = self.pc.wrapping_add(3);
+            }
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::RraXIndexedAbsolute => {
+                let operand = self.fetch_rmw_operand(instr, AddressingType::XIndexedAbsolute);
+                let result = self.ror(operand);
+                self.adc(result);
+                self.pc = self.pc.wrapping_add(3);
+            }
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::RraYIndexedAbsolute => {
+                let operand = self.fetch_rmw_operand(instr, AddressingType::YIndexedAbsolute);
+                let result = self.ror(operand);
+                self.adc(result);
+                self.pc = self.pc.wrapping_add(3);
+            }
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::RraXIndexedZeroIndirect => {
+                let operand = self.fetch_rmw_operand(instr, AddressingType::XIndexedZeroIndirect);
+                let result = self.ror(operand);
+                self.adc(result);
+                self.pc = self.pc.wrapping_add(2);
+            }
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::RraZeroIndirectIndexed => {
+                let operand = self.fetch_rmw_operand(instr, AddressingType::ZeroIndirectIndexed);
+                let result = self.ror(operand);
+                self.adc(result);
+                self.pc = self.pc.wrapping_add(2);
+            }
+            #[allow(unreachable_patterns)]
+            _ => return self.unimplemented(int),
         }
-    }
 
-    fn adc(&mut self, operand: u8) {
-        let decimal = self.p.read_flag(FlagPosition::DecimalMode);
-        let carry = self.p.read_flag(FlagPosition::Carry);
+        self.cycles += base_cycles(int) as u64 + extra_cycles;
 
-        let result = if !decimal {
-            let a = self.a as u16;
+        if self.flag_log.is_some() {
+            self.record_flag_changes(pc, int, flags_before);
+        }
+    }
+
+    // Fallback for an instruction execute has no arm for
+    fn unimplemented(&mut self, instr: Instruction) {
+        if !self.trap_unimplemented {
+            panic!("Unknown instruction {instr:?}");
+        }
+        self.unimplemented = Some(instr);
+    }
+
+    fn record_flag_changes(&mut self, pc: u16, instruction: Instruction, flags_before: u8) {
+        let before = FlagsRegister::new(flags_before);
+
+        for flag in FlagPosition::ALL {
+            let old = before.read_flag(flag);
+            let new = self.p.read_flag(flag);
+
+            if old != new {
+                if let Some(log) = self.flag_log.as_mut() {
+                    log.push(FlagChange {
+                        pc,
+                        instruction,
+                        flag,
+                        old,
+                        new,
+                    });
+                }
+            }
+        }
+    }
+
+    fn adc(&mut self, operand: u8) {
+        let decimal = self.p.read_flag(FlagPosition::DecimalMode);
+        let carry = self.p.read_flag(FlagPosition::Carry);
+
+        // Values the Zero and Negative flags are taken from
+        let (result, zero_source, negative_source) = if !decimal {
+            let a = self.a as u16;
             let r = a.wrapping_add(operand as u16).wrapping_add(carry as u16);
 
             self.p.write_flag(FlagPosition::Carry, r & 0xFF00 != 0);
@@ -1171,9 +2667,14 @@ impl Cpu {
                 (a ^ r) & (operand as u16 ^ r) & 0x80 != 0,
             );
 
-            r
+            (r as u8, r as u8, r as u8)
         } else {
-            let mut r = bcd_to_u8(self.a) + bcd_to_u8(operand) + carry as u8;
+            if self.variant == Variant::Cmos {
+                self.cycles += 1; // 65C02 spends an extra cycle on decimal correction
+            }
+
+            // Invalid BCD digits can push the sum past 255
+            let mut r = bcd_to_u8(self.a) as u16 + bcd_to_u8(operand) as u16 + carry as u16;
 
             let carry_new = r > 99;
             if carry_new {
@@ -1182,14 +2683,30 @@ impl Cpu {
 
             self.p.write_flag(FlagPosition::Carry, carry_new);
 
-            u8_to_bcd(r as u8) as u16
+            let result = u8_to_bcd(r as u8);
+            match self.variant {
+                // NMOS takes Z from the binary sum and N from the sum
+                // before the high nibble is adjusted
+                Variant::Nmos => {
+                    let binary = self.a.wrapping_add(operand).wrapping_add(carry as u8);
+                    let low_nibble = (self.a & 0x0F) + (operand & 0x0F) + carry as u8;
+                    let intermediate = (self.a & 0xF0)
+                        .wrapping_add(operand & 0xF0)
+                        .wrapping_add(if low_nibble > 9 { 0x10 } else { 0 });
+
+                    (result, binary, intermediate)
+                }
+                Variant::Cmos => (result, result, result),
+            }
         };
 
-        self.a = result as u8;
+        self.a = result;
 
-        self.p.write_flag(FlagPosition::Zero, result & 0xFF == 0);
-        self.p
-            .write_flag(FlagPosition::Negative, (result & 0b1000_0000) >> 7 == 1);
+        self.p.write_flag(FlagPosition::Zero, zero_source == 0);
+        self.p.write_flag(
+            FlagPosition::Negative,
+            (negative_source & 0b1000_0000) >> 7 == 1,
+        );
     }
 
     fn and(&mut self, operand: u8) {
@@ -1202,10 +2719,10 @@ impl Cpu {
         self.a = result;
     }
 
-    fn asl(&mut self, operand: ShiftOperand, operand_address: Option<u16>) {
+    fn asl(&mut self, operand: ShiftOperand) -> u8 {
         let operand_value: u8 = match operand {
             ShiftOperand::A => self.a,
-            ShiftOperand::Value(v) => v,
+            ShiftOperand::Memory(value, _) => value,
         };
 
         let result = operand_value.wrapping_shl(1);
@@ -1216,27 +2733,41 @@ impl Cpu {
             .write_flag(FlagPosition::Negative, (result & 0b1000_0000) >> 7 == 1);
         self.p.write_flag(FlagPosition::Zero, result == 0);
 
+        self.rmw(operand, result);
+
+        result
+    }
+
+    // Write-back step of read-modify-write instructions
+    fn rmw(&mut self, operand: ShiftOperand, result: u8) {
         match operand {
             ShiftOperand::A => self.a = result,
-            ShiftOperand::Value(_) => self.address_space.write_byte(
-                operand_address.expect("ASL: expected address") as usize,
-                result,
-            ),
+            ShiftOperand::Memory(_, address) => self.store(address, result),
         }
     }
 
-    fn branch(&mut self, offset: i8, flag: FlagPosition, set: bool) {
+    fn branch(&mut self, offset: i8, flag: FlagPosition, set: bool) -> BranchResult {
         // PC is already on next command after branch here
 
         if self.p.read_flag(flag) == set {
-            self.pc = self.pc.wrapping_add(offset as i16 as u16);
+            let target = self.pc.wrapping_add(offset as i16 as u16);
+            let page_crossed = target & 0xFF00 != self.pc & 0xFF00;
+            self.pc = target;
+
+            BranchResult(true, page_crossed)
+        } else {
+            BranchResult(false, false)
         }
     }
 
-    fn bit(&mut self, operand: u8) {
+    // The immediate form has no memory operand to copy N and V from, so only Z changes
+    fn bit(&mut self, operand: u8, immediate: bool) {
         let result = self.a & operand;
 
         self.p.write_flag(FlagPosition::Zero, result == 0);
+        if immediate {
+            return;
+        }
         self.p
             .write_flag(FlagPosition::Overflow, (operand & 0b0100_0000) >> 6 == 1);
         self.p
@@ -1244,13 +2775,22 @@ impl Cpu {
     }
 
     fn brk(&mut self) {
-        self.push_dword(self.pc + 2);
-        self.push(Into::<u8>::into(&self.p) | 0x1 << 5 | 0x1 << 4);
+        self.interrupt(self.pc.wrapping_add(2), IRQ_VECTOR, true);
+    }
 
-        let irq_vec_high_byte = self.address_space.read_byte(0xFFFF);
-        let irq_vec_low_byte = self.address_space.read_byte(0xFFFE);
+    // Shared entry sequence of BRK, IRQ and NMI. Only BRK pushes the status with
+    // the Break bit set, which is how handlers tell it apart from a hardware IRQ.
+    fn interrupt(&mut self, return_address: u16, vector: u16, software: bool) {
+        self.push_dword(return_address);
 
-        self.pc = dword_from_nibbles(irq_vec_low_byte, irq_vec_high_byte);
+        let break_bit = if software {
+            1 << FlagPosition::Break as u8
+        } else {
+            0
+        };
+        self.push(Into::<u8>::into(&self.p) | 1 << FlagPosition::Unused as u8 | break_bit);
+
+        self.pc = self.read_u16(vector);
         self.p.write_flag(FlagPosition::IrqDisable, true);
     }
 
@@ -1273,11 +2813,11 @@ impl Cpu {
         self.p.write_flag(FlagPosition::Carry, register >= operand);
     }
 
-    fn inc_dec(&mut self, inc: bool, operand: IncDecOperand, operand_address: Option<u16>) {
+    fn inc_dec(&mut self, inc: bool, operand: IncDecOperand) {
         let operand_value: u8 = match operand {
             IncDecOperand::X => self.x,
             IncDecOperand::Y => self.y,
-            IncDecOperand::Value(v) => v,
+            IncDecOperand::Memory(value, _) => value,
         };
 
         let result = if inc {
@@ -1290,18 +2830,12 @@ impl Cpu {
         self.p
             .write_flag(FlagPosition::Negative, (result & 0b1000_0000) >> 7 == 1);
 
-        println!(
-            "Inc {} operand {} address {:?}",
-            inc, operand_value, operand_address
-        );
-
         match operand {
             IncDecOperand::X => self.x = result,
             IncDecOperand::Y => self.y = result,
-            IncDecOperand::Value(_) => self.address_space.write_byte(
-                operand_address.expect("INC/DEC: expected address") as usize,
-                result,
-            ),
+            IncDecOperand::Memory(value, address) => {
+                self.rmw(ShiftOperand::Memory(value, address), result)
+            }
         }
     }
 
@@ -1316,19 +2850,9 @@ impl Cpu {
     }
 
     fn jsr(&mut self, address: u16) {
-        self.pc += 2;
-
-        let high_byte = (self.pc & 0xFF00) >> 8;
-        let low_byte = self.pc & 0x00FF;
-
-        self.address_space
-            .write_byte(STACK_BOTTOM + self.s as usize, high_byte as u8);
-        self.s = self.s.wrapping_sub(1);
-
-        self.address_space
-            .write_byte(STACK_BOTTOM + self.s as usize, low_byte as u8);
-        self.s = self.s.wrapping_sub(1);
-
+        // The pushed return address is the last byte of the JSR, which wraps
+        // around the top of memory like PC does
+        self.push_dword(self.pc.wrapping_add(2));
         self.pc = address;
     }
 
@@ -1350,10 +2874,10 @@ impl Cpu {
             .write_flag(FlagPosition::Negative, (operand & 0b1000_0000) >> 7 == 1);
     }
 
-    fn lsr(&mut self, operand: ShiftOperand, operand_address: Option<u16>) {
+    fn lsr(&mut self, operand: ShiftOperand) -> u8 {
         let operand_value: u8 = match operand {
             ShiftOperand::A => self.a,
-            ShiftOperand::Value(v) => v,
+            ShiftOperand::Memory(value, _) => value,
         };
 
         let result = operand_value >> 1;
@@ -1363,13 +2887,9 @@ impl Cpu {
         self.p.write_flag(FlagPosition::Negative, false);
         self.p.write_flag(FlagPosition::Zero, result == 0);
 
-        match operand {
-            ShiftOperand::A => self.a = result,
-            ShiftOperand::Value(_) => self.address_space.write_byte(
-                operand_address.expect("LSR: expected address") as usize,
-                result,
-            ),
-        }
+        self.rmw(operand, result);
+
+        result
     }
 
     fn ora(&mut self, operand: u8) {
@@ -1382,38 +2902,52 @@ impl Cpu {
         self.a = result;
     }
 
-    fn push(&mut self, value: u8) {
-        self.address_space
-            .write_byte(STACK_BOTTOM + self.s as usize, value);
+    fn decrement_stack_pointer(&mut self) {
+        if self.s == 0x00 && self.detect_stack_errors {
+            self.stack_error = Some(StopReason::StackOverflow);
+        }
         self.s = self.s.wrapping_sub(1);
     }
 
+    fn increment_stack_pointer(&mut self) {
+        if self.s == 0xFF && self.detect_stack_errors {
+            self.stack_error = Some(StopReason::StackUnderflow);
+        }
+        self.s = self.s.wrapping_add(1);
+    }
+
+    fn stack_address(&self) -> u16 {
+        (self.stack_base + self.s as usize) as u16
+    }
+
+    fn push(&mut self, value: u8) {
+        self.write_bus(self.stack_address(), value);
+        self.decrement_stack_pointer();
+    }
+
     fn push_dword(&mut self, value: u16) {
-        let high_byte = (value & 0xFF00) >> 8;
-        let low_byte = value & 0x00FF;
+        let (low_byte, high_byte) = bytes_from_dword(value);
 
-        self.address_space
-            .write_byte(STACK_BOTTOM + self.s as usize, high_byte as u8);
-        self.s = self.s.wrapping_sub(1);
+        self.write_bus(self.stack_address(), high_byte);
+        self.decrement_stack_pointer();
 
-        self.address_space
-            .write_byte(STACK_BOTTOM + self.s as usize, low_byte as u8);
-        self.s = self.s.wrapping_sub(1);
+        self.write_bus(self.stack_address(), low_byte);
+        self.decrement_stack_pointer();
     }
 
     fn pop(&mut self) -> u8 {
-        self.s = self.s.wrapping_add(1);
-        self.address_space.read_byte(STACK_BOTTOM + self.s as usize)
+        self.increment_stack_pointer();
+        self.address_space.read(self.stack_address())
     }
 
     fn pop_dword(&mut self) -> u16 {
-        self.s = self.s.wrapping_add(1);
-        let low_byte = self.address_space.read_byte(STACK_BOTTOM + self.s as usize);
+        self.increment_stack_pointer();
+        let low_byte = self.address_space.read(self.stack_address());
 
-        self.s = self.s.wrapping_add(1);
-        let high_byte = self.address_space.read_byte(STACK_BOTTOM + self.s as usize);
+        self.increment_stack_pointer();
+        let high_byte = self.address_space.read(self.stack_address());
 
-        dword_from_nibbles(low_byte, high_byte)
+        dword_from_bytes(low_byte, high_byte)
     }
 
     fn pla(&mut self) {
@@ -1423,16 +2957,23 @@ impl Cpu {
             .write_flag(FlagPosition::Negative, (self.a & 0b1000_0000) >> 7 == 1);
     }
 
+    // The pushed copy always has Break and Unused set, the register keeps its own bits
+    fn php(&mut self) {
+        let status = Into::<u8>::into(&self.p)
+            | 1 << FlagPosition::Break as u8
+            | 1 << FlagPosition::Unused as u8;
+        self.push(status);
+    }
+
     fn plp(&mut self) {
-        self.p = FlagsRegister::new(self.pop());
-        self.p.write_flag(FlagPosition::Break, false);
-        self.p.write_flag(FlagPosition::Unused, true);
+        let status = self.pop();
+        self.p.set_byte(status);
     }
 
-    fn rol(&mut self, operand: ShiftOperand, operand_address: Option<u16>) {
+    fn rol(&mut self, operand: ShiftOperand) -> u8 {
         let operand_value: u8 = match operand {
             ShiftOperand::A => self.a,
-            ShiftOperand::Value(v) => v,
+            ShiftOperand::Memory(value, _) => value,
         };
 
         let carry = self.p.read_flag(FlagPosition::Carry) as u8;
@@ -1444,19 +2985,15 @@ impl Cpu {
             .write_flag(FlagPosition::Negative, (result & 0b1000_0000) >> 7 == 1);
         self.p.write_flag(FlagPosition::Zero, result == 0);
 
-        match operand {
-            ShiftOperand::A => self.a = result,
-            ShiftOperand::Value(_) => self.address_space.write_byte(
-                operand_address.expect("ROL: expected address") as usize,
-                result,
-            ),
-        }
+        self.rmw(operand, result);
+
+        result
     }
 
-    fn ror(&mut self, operand: ShiftOperand, operand_address: Option<u16>) {
+    fn ror(&mut self, operand: ShiftOperand) -> u8 {
         let operand_value: u8 = match operand {
             ShiftOperand::A => self.a,
-            ShiftOperand::Value(v) => v,
+            ShiftOperand::Memory(value, _) => value,
         };
 
         let carry = self.p.read_flag(FlagPosition::Carry) as u8;
@@ -1468,13 +3005,9 @@ impl Cpu {
             .write_flag(FlagPosition::Negative, (result & 0b1000_0000) >> 7 == 1);
         self.p.write_flag(FlagPosition::Zero, result == 0);
 
-        match operand {
-            ShiftOperand::A => self.a = result,
-            ShiftOperand::Value(_) => self.address_space.write_byte(
-                operand_address.expect("ROR: expected address") as usize,
-                result,
-            ),
-        }
+        self.rmw(operand, result);
+
+        result
     }
 
     fn rti(&mut self) {
@@ -1489,9 +3022,10 @@ impl Cpu {
     fn sbc(&mut self, operand: u8) {
         let decimal = self.p.read_flag(FlagPosition::DecimalMode);
         let borrow = !self.p.read_flag(FlagPosition::Carry);
-        let mut carry_out = false;
+        let carry_out;
 
-        let result = if !decimal {
+        // Value the Zero and Negative flags are taken from
+        let (result, flag_source) = if !decimal {
             let a = self.a as u16;
             let r = a.wrapping_sub(operand as u16).wrapping_sub(borrow as u16);
 
@@ -1501,8 +3035,12 @@ impl Cpu {
                 (a ^ r) & (!operand as u16 ^ r) & 0x80 != 0,
             );
 
-            r
+            (r as u8, r as u8)
         } else {
+            if self.variant == Variant::Cmos {
+                self.cycles += 1; // 65C02 spends an extra cycle on decimal correction
+            }
+
             let mut r = bcd_to_u8(self.a)
                 .wrapping_sub(bcd_to_u8(operand))
                 .wrapping_sub(borrow as u8) as i8;
@@ -1514,15 +3052,25 @@ impl Cpu {
 
             carry_out = carry;
 
-            u8_to_bcd(r as u8) as u16
+            let result = u8_to_bcd(r as u8);
+            match self.variant {
+                // NMOS sets N and Z from the binary difference
+                Variant::Nmos => (
+                    result,
+                    self.a.wrapping_sub(operand).wrapping_sub(borrow as u8),
+                ),
+                Variant::Cmos => (result, result),
+            }
         };
 
-        self.a = result as u8;
+        self.a = result;
 
         self.p.write_flag(FlagPosition::Carry, !carry_out);
-        self.p.write_flag(FlagPosition::Zero, result & 0xFF == 0);
-        self.p
-            .write_flag(FlagPosition::Negative, (result & 0b1000_0000) >> 7 == 1);
+        self.p.write_flag(FlagPosition::Zero, flag_source == 0);
+        self.p.write_flag(
+            FlagPosition::Negative,
+            (flag_source & 0b1000_0000) >> 7 == 1,
+        );
     }
 
     fn sec(&mut self) {
@@ -1537,11 +3085,45 @@ impl Cpu {
         self.p.write_flag(FlagPosition::IrqDisable, true);
     }
 
+    // Every write made by an instruction goes through here so `step_back` can undo it.
+    // Writes the bus would fault on are dropped and reported by `try_step` and `run`.
+    fn write_bus(&mut self, address: u16, value: u8) {
+        if !self.address_space.is_writable(address) {
+            self.rom_write = self.rom_write.or(Some(address));
+            return;
+        }
+        if self.history_depth > 0 {
+            if let Some(entry) = self.history.back_mut() {
+                entry
+                    .writes
+                    .push((address, self.address_space.peek(address)));
+            }
+        }
+        self.address_space.write(address, value);
+    }
+
+    // Data write by an instruction, as opposed to stack and interrupt traffic
+    fn store(&mut self, address: u16, value: u8) {
+        self.write_bus(address, value);
+
+        if self.trap == Some((address, TrapMode::Write)) {
+            self.trap_value = Some(value);
+        }
+
+        if let Some((start, end)) = self.code_range {
+            if (start..=end).contains(&address) {
+                if let Some(on_code_write) = self.on_code_write.as_mut() {
+                    on_code_write(address);
+                }
+            }
+        }
+    }
+
     fn st(&mut self, register: LdOperand, address: u16) {
         match register {
-            LdOperand::A => self.address_space.write_byte(address as usize, self.a),
-            LdOperand::X => self.address_space.write_byte(address as usize, self.x),
-            LdOperand::Y => self.address_space.write_byte(address as usize, self.y),
+            LdOperand::A => self.store(address, self.a),
+            LdOperand::X => self.store(address, self.x),
+            LdOperand::Y => self.store(address, self.y),
         }
     }
 
@@ -1585,15 +3167,66 @@ impl Cpu {
     }
 }
 
+impl Cpu<MemoryBus> {
+    /// Creates a CPU in the conventional post-reset state used by reference logs:
+    /// A = X = Y = 0, S = 0xFD, P = 0x24 and PC loaded from the reset vector,
+    /// or 0 when the vector isn't mapped.
+    pub fn new_deterministic(mem_bus: MemoryBus) -> Cpu {
+        let mut cpu = Cpu::new(mem_bus);
+        cpu.s = 0xFD;
+        cpu.p = FlagsRegister::new(0x24);
+
+        if cpu.address_space.is_mapped(RESET_VECTOR as usize)
+            && cpu.address_space.is_mapped(RESET_VECTOR as usize + 1)
+        {
+            cpu.pc = cpu.read_u16(RESET_VECTOR);
+        }
+
+        cpu
+    }
+
+    /// Builds a CPU on flat 64K RAM with `bytes` loaded at `origin`, the reset
+    /// vector pointing there and `reset` already done, ready to step. Fails if
+    /// the program runs past $FFFF.
+    ///
+    /// ```
+    /// use mos_6502::cpu::Cpu;
+    ///
+    /// // LDA #$02; CLC; ADC #$03
+    /// let mut cpu = Cpu::from_program(&[0xA9, 0x02, 0x18, 0x69, 0x03], 0x0600).unwrap();
+    /// for _ in 0..3 {
+    ///     cpu.step();
+    /// }
+    /// assert_eq!(cpu.a, 0x05);
+    /// assert_eq!(cpu.pc(), 0x0605);
+    /// ```
+    pub fn from_program(bytes: &[u8], origin: u16) -> Result<Cpu, MemoryBusError> {
+        let mut cpu = emulator_from_program(bytes, origin, origin)?;
+        cpu.write_word(RESET_VECTOR, origin);
+        cpu.reset();
+
+        Ok(cpu)
+    }
+}
+
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod test {
-    static mut MEMORY: [u8; 0x10000] = [0; 0x10000];
+    use std::{cell::RefCell, rc::Rc};
+
     use crate::{
         cpu::Cpu,
         flags_register::{FlagPosition, FlagsRegister},
-        memory_bus::MemoryBus,
+        memory_bus::{Bus, MemoryBus, MemoryRegion},
     };
 
+    // Writes `bytes` through the bus starting at `address`
+    fn load(memory: &mut impl Bus, address: u16, bytes: &[u8]) {
+        for (offset, byte) in bytes.iter().enumerate() {
+            memory.write(address.wrapping_add(offset as u16), *byte);
+        }
+    }
+
     #[test]
     fn adc() {
         let memory = MemoryBus::new();
@@ -1646,7 +3279,7 @@ mod test {
         cpu.adc(0x81);
         assert_eq!(cpu.a, 0x60); // 79 + 81 = 160, subtract 100, result is 60
         assert_eq!(cpu.p.read_flag(FlagPosition::Carry), true);
-        assert_eq!(cpu.p.read_flag(FlagPosition::Zero), false); // TODO: Not sure as in some implementations it's not set in decimal mode
+        assert_eq!(cpu.p.read_flag(FlagPosition::Zero), false); // Binary sum 0xFA on NMOS
         assert_eq!(cpu.p.read_flag(FlagPosition::Negative), false);
         assert_eq!(cpu.p.read_flag(FlagPosition::Overflow), false);
     }
@@ -1681,14 +3314,14 @@ mod test {
         let mut cpu = Cpu::new(memory);
 
         cpu.a = 0b1000_0000;
-        cpu.asl(crate::cpu::ShiftOperand::A, None);
+        cpu.asl(crate::cpu::ShiftOperand::A);
         assert_eq!(cpu.a, 0b0000_0000);
         assert_eq!(cpu.p.read_flag(FlagPosition::Carry), true);
         assert_eq!(cpu.p.read_flag(FlagPosition::Zero), true);
         assert_eq!(cpu.p.read_flag(FlagPosition::Negative), false);
 
         cpu.a = 0b0100_0000;
-        cpu.asl(crate::cpu::ShiftOperand::A, None);
+        cpu.asl(crate::cpu::ShiftOperand::A);
         assert_eq!(cpu.a, 0b1000_0000);
         assert_eq!(cpu.p.read_flag(FlagPosition::Carry), false);
         assert_eq!(cpu.p.read_flag(FlagPosition::Zero), false);
@@ -1701,19 +3334,19 @@ mod test {
         let mut cpu = Cpu::new(memory);
 
         cpu.a = 0b1010_1010;
-        cpu.bit(0b1100_1100);
+        cpu.bit(0b1100_1100, false);
         assert_eq!(cpu.p.read_flag(FlagPosition::Zero), false);
         assert_eq!(cpu.p.read_flag(FlagPosition::Overflow), true);
         assert_eq!(cpu.p.read_flag(FlagPosition::Negative), true);
 
         cpu.a = 0b1010_1010;
-        cpu.bit(0b0000_0000);
+        cpu.bit(0b0000_0000, false);
         assert_eq!(cpu.p.read_flag(FlagPosition::Zero), true);
         assert_eq!(cpu.p.read_flag(FlagPosition::Overflow), false);
         assert_eq!(cpu.p.read_flag(FlagPosition::Negative), false);
 
         cpu.a = 0b1010_1010;
-        cpu.bit(0b0100_1100);
+        cpu.bit(0b0100_1100, false);
         assert_eq!(cpu.p.read_flag(FlagPosition::Zero), false);
         assert_eq!(cpu.p.read_flag(FlagPosition::Overflow), true);
         assert_eq!(cpu.p.read_flag(FlagPosition::Negative), false);
@@ -1722,32 +3355,23 @@ mod test {
     #[test]
     fn brk() {
         let mut memory = MemoryBus::new();
-        memory.add_region(crate::memory_bus::MemoryRegion {
-            start: 0,
-            end: 0xFFFF,
-            read_handler: Box::new(|addr: usize| unsafe { MEMORY[addr] }),
-            write_handler: Box::new(|addr: usize, value: u8| unsafe { MEMORY[addr] = value }),
-        });
+        memory.add_ram(0, 0x10000);
         let mut cpu = Cpu::new(memory);
 
         cpu.s = 0xFF;
 
-        unsafe {
-            MEMORY[0xFFFE] = 0x25;
-            MEMORY[0xFFFF] = 0x45;
-        }
+        cpu.write_memory(0xFFFE, 0x25);
+        cpu.write_memory(0xFFFF, 0x45);
 
         cpu.brk();
         assert_eq!(cpu.pc, 0x4525);
         assert_eq!(cpu.p.read_flag(FlagPosition::Break), false);
-        assert_eq!(cpu.p.read_flag(FlagPosition::Unused), false);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Unused), true);
         assert_eq!(cpu.p.read_flag(FlagPosition::IrqDisable), true);
 
-        unsafe {
-            assert_eq!(MEMORY[0x1FF], 0x0);
-            assert_eq!(MEMORY[0x1FE], 0x2);
-            assert_eq!(MEMORY[0x1FD], 1 << 5 | 1 << 4);
-        }
+        assert_eq!(cpu.read_memory(0x1FF), 0x0);
+        assert_eq!(cpu.read_memory(0x1FE), 0x2);
+        assert_eq!(cpu.read_memory(0x1FD), 1 << 5 | 1 << 4);
     }
 
     #[test]
@@ -1931,51 +3555,31 @@ mod test {
     fn dec() {
         let mut memory = MemoryBus::new();
 
-        memory.add_region(crate::memory_bus::MemoryRegion {
-            start: 0,
-            end: 0,
-            read_handler: Box::new(|addr: usize| unsafe { MEMORY[addr] }),
-            write_handler: Box::new(|addr: usize, value: u8| unsafe { MEMORY[addr] = value }),
-        });
+        memory.add_ram(0, 0x1);
 
-        unsafe {
-            MEMORY[0] = 0x5;
-        }
+        memory.write_byte(0, 0x5);
 
         let mut cpu = Cpu::new(memory);
 
-        cpu.inc_dec(
-            false,
-            unsafe { crate::cpu::IncDecOperand::Value(MEMORY[0]) },
-            Some(0),
-        );
-        assert_eq!(unsafe { MEMORY[0] }, 0x4);
+        let value = cpu.read_memory(0);
+        cpu.inc_dec(false, crate::cpu::IncDecOperand::Memory(value, 0));
+        assert_eq!(cpu.read_memory(0), 0x4);
         assert_eq!(cpu.p.read_flag(FlagPosition::Negative), false);
         assert_eq!(cpu.p.read_flag(FlagPosition::Zero), false);
 
-        unsafe {
-            MEMORY[0] = 0x0;
-        }
+        cpu.write_memory(0, 0x0);
 
-        cpu.inc_dec(
-            false,
-            unsafe { crate::cpu::IncDecOperand::Value(MEMORY[0]) },
-            Some(0),
-        );
-        assert_eq!(unsafe { MEMORY[0] }, 0xFF);
+        let value = cpu.read_memory(0);
+        cpu.inc_dec(false, crate::cpu::IncDecOperand::Memory(value, 0));
+        assert_eq!(cpu.read_memory(0), 0xFF);
         assert_eq!(cpu.p.read_flag(FlagPosition::Negative), true);
         assert_eq!(cpu.p.read_flag(FlagPosition::Zero), false);
 
-        unsafe {
-            MEMORY[0] = 0x1;
-        }
+        cpu.write_memory(0, 0x1);
 
-        cpu.inc_dec(
-            false,
-            unsafe { crate::cpu::IncDecOperand::Value(MEMORY[0]) },
-            Some(0),
-        );
-        assert_eq!(unsafe { MEMORY[0] }, 0x0);
+        let value = cpu.read_memory(0);
+        cpu.inc_dec(false, crate::cpu::IncDecOperand::Memory(value, 0));
+        assert_eq!(cpu.read_memory(0), 0x0);
         assert_eq!(cpu.p.read_flag(FlagPosition::Negative), false);
         assert_eq!(cpu.p.read_flag(FlagPosition::Zero), true);
     }
@@ -1986,19 +3590,19 @@ mod test {
         let mut cpu = Cpu::new(memory);
 
         cpu.x = 0x05;
-        cpu.inc_dec(false, crate::cpu::IncDecOperand::X, None);
+        cpu.inc_dec(false, crate::cpu::IncDecOperand::X);
         assert_eq!(cpu.x, 0x04);
         assert_eq!(cpu.p.read_flag(FlagPosition::Negative), false);
         assert_eq!(cpu.p.read_flag(FlagPosition::Zero), false);
 
         cpu.x = 0x01;
-        cpu.inc_dec(false, crate::cpu::IncDecOperand::X, None);
+        cpu.inc_dec(false, crate::cpu::IncDecOperand::X);
         assert_eq!(cpu.x, 0x00);
         assert_eq!(cpu.p.read_flag(FlagPosition::Negative), false);
         assert_eq!(cpu.p.read_flag(FlagPosition::Zero), true);
 
         cpu.x = 0x00;
-        cpu.inc_dec(false, crate::cpu::IncDecOperand::X, None);
+        cpu.inc_dec(false, crate::cpu::IncDecOperand::X);
         assert_eq!(cpu.x, 0xFF);
         assert_eq!(cpu.p.read_flag(FlagPosition::Negative), true);
         assert_eq!(cpu.p.read_flag(FlagPosition::Zero), false);
@@ -2010,19 +3614,19 @@ mod test {
         let mut cpu = Cpu::new(memory);
 
         cpu.y = 0x05;
-        cpu.inc_dec(false, crate::cpu::IncDecOperand::Y, None);
+        cpu.inc_dec(false, crate::cpu::IncDecOperand::Y);
         assert_eq!(cpu.y, 0x04);
         assert_eq!(cpu.p.read_flag(FlagPosition::Negative), false);
         assert_eq!(cpu.p.read_flag(FlagPosition::Zero), false);
 
         cpu.y = 0x01;
-        cpu.inc_dec(false, crate::cpu::IncDecOperand::Y, None);
+        cpu.inc_dec(false, crate::cpu::IncDecOperand::Y);
         assert_eq!(cpu.y, 0x00);
         assert_eq!(cpu.p.read_flag(FlagPosition::Negative), false);
         assert_eq!(cpu.p.read_flag(FlagPosition::Zero), true);
 
         cpu.y = 0x00;
-        cpu.inc_dec(false, crate::cpu::IncDecOperand::Y, None);
+        cpu.inc_dec(false, crate::cpu::IncDecOperand::Y);
         assert_eq!(cpu.y, 0xFF);
         assert_eq!(cpu.p.read_flag(FlagPosition::Negative), true);
         assert_eq!(cpu.p.read_flag(FlagPosition::Zero), false);
@@ -2050,51 +3654,31 @@ mod test {
     fn inc() {
         let mut memory = MemoryBus::new();
 
-        memory.add_region(crate::memory_bus::MemoryRegion {
-            start: 0,
-            end: 0,
-            read_handler: Box::new(|addr: usize| unsafe { MEMORY[addr] }),
-            write_handler: Box::new(|addr: usize, value: u8| unsafe { MEMORY[addr] = value }),
-        });
+        memory.add_ram(0, 0x1);
 
-        unsafe {
-            MEMORY[0] = 0x5;
-        }
+        memory.write_byte(0, 0x5);
 
         let mut cpu = Cpu::new(memory);
 
-        cpu.inc_dec(
-            true,
-            unsafe { crate::cpu::IncDecOperand::Value(MEMORY[0]) },
-            Some(0),
-        );
-        assert_eq!(unsafe { MEMORY[0] }, 0x6);
+        let value = cpu.read_memory(0);
+        cpu.inc_dec(true, crate::cpu::IncDecOperand::Memory(value, 0));
+        assert_eq!(cpu.read_memory(0), 0x6);
         assert_eq!(cpu.p.read_flag(FlagPosition::Negative), false);
         assert_eq!(cpu.p.read_flag(FlagPosition::Zero), false);
 
-        unsafe {
-            MEMORY[0] = 0xFF;
-        }
+        cpu.write_memory(0, 0xFF);
 
-        cpu.inc_dec(
-            true,
-            unsafe { crate::cpu::IncDecOperand::Value(MEMORY[0]) },
-            Some(0),
-        );
-        assert_eq!(unsafe { MEMORY[0] }, 0x0);
+        let value = cpu.read_memory(0);
+        cpu.inc_dec(true, crate::cpu::IncDecOperand::Memory(value, 0));
+        assert_eq!(cpu.read_memory(0), 0x0);
         assert_eq!(cpu.p.read_flag(FlagPosition::Negative), false);
         assert_eq!(cpu.p.read_flag(FlagPosition::Zero), true);
 
-        unsafe {
-            MEMORY[0] = 0x7F;
-        }
+        cpu.write_memory(0, 0x7F);
 
-        cpu.inc_dec(
-            true,
-            unsafe { crate::cpu::IncDecOperand::Value(MEMORY[0]) },
-            Some(0),
-        );
-        assert_eq!(unsafe { MEMORY[0] }, 0x80);
+        let value = cpu.read_memory(0);
+        cpu.inc_dec(true, crate::cpu::IncDecOperand::Memory(value, 0));
+        assert_eq!(cpu.read_memory(0), 0x80);
         assert_eq!(cpu.p.read_flag(FlagPosition::Negative), true);
         assert_eq!(cpu.p.read_flag(FlagPosition::Zero), false);
     }
@@ -2105,19 +3689,19 @@ mod test {
         let mut cpu = Cpu::new(memory);
 
         cpu.x = 0x05;
-        cpu.inc_dec(true, crate::cpu::IncDecOperand::X, None);
+        cpu.inc_dec(true, crate::cpu::IncDecOperand::X);
         assert_eq!(cpu.x, 0x06);
         assert_eq!(cpu.p.read_flag(FlagPosition::Negative), false);
         assert_eq!(cpu.p.read_flag(FlagPosition::Zero), false);
 
         cpu.x = 0x7F;
-        cpu.inc_dec(true, crate::cpu::IncDecOperand::X, None);
+        cpu.inc_dec(true, crate::cpu::IncDecOperand::X);
         assert_eq!(cpu.x, 0x80);
         assert_eq!(cpu.p.read_flag(FlagPosition::Negative), true);
         assert_eq!(cpu.p.read_flag(FlagPosition::Zero), false);
 
         cpu.x = 0xFF;
-        cpu.inc_dec(true, crate::cpu::IncDecOperand::X, None);
+        cpu.inc_dec(true, crate::cpu::IncDecOperand::X);
         assert_eq!(cpu.x, 0x00);
         assert_eq!(cpu.p.read_flag(FlagPosition::Negative), false);
         assert_eq!(cpu.p.read_flag(FlagPosition::Zero), true);
@@ -2129,19 +3713,19 @@ mod test {
         let mut cpu = Cpu::new(memory);
 
         cpu.y = 0x05;
-        cpu.inc_dec(true, crate::cpu::IncDecOperand::Y, None);
+        cpu.inc_dec(true, crate::cpu::IncDecOperand::Y);
         assert_eq!(cpu.y, 0x06);
         assert_eq!(cpu.p.read_flag(FlagPosition::Negative), false);
         assert_eq!(cpu.p.read_flag(FlagPosition::Zero), false);
 
         cpu.y = 0x7F;
-        cpu.inc_dec(true, crate::cpu::IncDecOperand::Y, None);
+        cpu.inc_dec(true, crate::cpu::IncDecOperand::Y);
         assert_eq!(cpu.y, 0x80);
         assert_eq!(cpu.p.read_flag(FlagPosition::Negative), true);
         assert_eq!(cpu.p.read_flag(FlagPosition::Zero), false);
 
         cpu.y = 0xFF;
-        cpu.inc_dec(true, crate::cpu::IncDecOperand::Y, None);
+        cpu.inc_dec(true, crate::cpu::IncDecOperand::Y);
         assert_eq!(cpu.y, 0x00);
         assert_eq!(cpu.p.read_flag(FlagPosition::Negative), false);
         assert_eq!(cpu.p.read_flag(FlagPosition::Zero), true);
@@ -2150,17 +3734,10 @@ mod test {
     #[test]
     fn jmp_direct() {
         let mut memory = MemoryBus::new();
-        memory.add_region(crate::memory_bus::MemoryRegion {
-            start: 0,
-            end: 0xF,
-            read_handler: Box::new(|addr: usize| unsafe { MEMORY[addr] }),
-            write_handler: Box::new(|addr: usize, value: u8| unsafe { MEMORY[addr] = value }),
-        });
+        memory.add_ram(0, 0x10);
 
-        unsafe {
-            MEMORY[0xA] = 0xBE;
-            MEMORY[0xB] = 0xBA;
-        }
+        memory.write_byte(0xA, 0xBE);
+        memory.write_byte(0xB, 0xBA);
         let mut cpu = Cpu::new(memory);
 
         cpu.execute(super::DecodedInstruction {
@@ -2185,12 +3762,7 @@ mod test {
     #[test]
     fn pha() {
         let mut memory = MemoryBus::new();
-        memory.add_region(crate::memory_bus::MemoryRegion {
-            start: 0,
-            end: 0xFFF,
-            read_handler: Box::new(|addr: usize| unsafe { MEMORY[addr] }),
-            write_handler: Box::new(|addr: usize, value: u8| unsafe { MEMORY[addr] = value }),
-        });
+        memory.add_ram(0, 0x1000);
 
         let mut cpu = Cpu::new(memory);
         cpu.a = 0x42;
@@ -2200,18 +3772,13 @@ mod test {
             int: crate::instruction::Instruction::Pha,
             arg: super::Argument::Void,
         });
-        assert_eq!(unsafe { MEMORY[0x1FF] }, 0x42);
+        assert_eq!(cpu.read_memory(0x1FF), 0x42);
     }
 
     #[test]
     fn php() {
         let mut memory = MemoryBus::new();
-        memory.add_region(crate::memory_bus::MemoryRegion {
-            start: 0,
-            end: 0xFFF,
-            read_handler: Box::new(|addr: usize| unsafe { MEMORY[addr] }),
-            write_handler: Box::new(|addr: usize, value: u8| unsafe { MEMORY[addr] = value }),
-        });
+        memory.add_ram(0, 0x1000);
 
         let mut cpu = Cpu::new(memory);
         cpu.p.write_flag(FlagPosition::Carry, true);
@@ -2222,24 +3789,17 @@ mod test {
             arg: super::Argument::Void,
         });
         let correct_value = 0x01 | 0x1 << 5 | 0x1 << 4; // BRK and reserved bits should be set
-        assert_eq!(unsafe { MEMORY[0x1FF] }, correct_value);
+        assert_eq!(cpu.read_memory(0x1FF), correct_value);
     }
 
     #[test]
     fn pla() {
         let mut memory = MemoryBus::new();
-        memory.add_region(crate::memory_bus::MemoryRegion {
-            start: 0,
-            end: 0xFFF,
-            read_handler: Box::new(|addr: usize| unsafe { MEMORY[addr] }),
-            write_handler: Box::new(|addr: usize, value: u8| unsafe { MEMORY[addr] = value }),
-        });
+        memory.add_ram(0, 0x1000);
 
         let mut cpu = Cpu::new(memory);
         cpu.s = 0xFE;
-        unsafe {
-            MEMORY[0x1FF] = 0x42;
-        }
+        cpu.write_memory(0x1FF, 0x42);
 
         cpu.execute(super::DecodedInstruction {
             int: crate::instruction::Instruction::Pla,
@@ -2250,9 +3810,7 @@ mod test {
         assert_eq!(cpu.p.read_flag(FlagPosition::Negative), false);
 
         cpu.s = 0xFE;
-        unsafe {
-            MEMORY[0x1FF] = 0x0;
-        }
+        cpu.write_memory(0x1FF, 0x0);
 
         cpu.execute(super::DecodedInstruction {
             int: crate::instruction::Instruction::Pla,
@@ -2263,9 +3821,7 @@ mod test {
         assert_eq!(cpu.p.read_flag(FlagPosition::Negative), false);
 
         cpu.s = 0xFE;
-        unsafe {
-            MEMORY[0x1FF] = 0b1000_0011;
-        }
+        cpu.write_memory(0x1FF, 0b1000_0011);
 
         cpu.execute(super::DecodedInstruction {
             int: crate::instruction::Instruction::Pla,
@@ -2279,18 +3835,11 @@ mod test {
     #[test]
     fn plp() {
         let mut memory = MemoryBus::new();
-        memory.add_region(crate::memory_bus::MemoryRegion {
-            start: 0,
-            end: 0xFFF,
-            read_handler: Box::new(|addr: usize| unsafe { MEMORY[addr] }),
-            write_handler: Box::new(|addr: usize, value: u8| unsafe { MEMORY[addr] = value }),
-        });
+        memory.add_ram(0, 0x1000);
 
         let mut cpu = Cpu::new(memory);
         cpu.s = 0xFE;
-        unsafe {
-            MEMORY[0x1FF] = 0x42 | 0x1 << 5 | 0x1 << 4;
-        }
+        cpu.write_memory(0x1FF, 0x42 | 0x1 << 5 | 0x1 << 4);
 
         cpu.execute(super::DecodedInstruction {
             int: crate::instruction::Instruction::Plp,
@@ -2306,7 +3855,7 @@ mod test {
 
         cpu.a = 0b0100_1100;
         cpu.p.write_flag(FlagPosition::Carry, true);
-        cpu.rol(super::ShiftOperand::A, None);
+        cpu.rol(super::ShiftOperand::A);
 
         assert_eq!(cpu.a, 0b1001_1001);
         assert_eq!(cpu.p.read_flag(FlagPosition::Carry), false);
@@ -2315,7 +3864,7 @@ mod test {
 
         cpu.a = 0b1100_1100;
         cpu.p.write_flag(FlagPosition::Carry, true);
-        cpu.rol(super::ShiftOperand::A, None);
+        cpu.rol(super::ShiftOperand::A);
 
         assert_eq!(cpu.a, 0b1001_1001);
         assert_eq!(cpu.p.read_flag(FlagPosition::Carry), true);
@@ -2330,7 +3879,7 @@ mod test {
 
         cpu.a = 0b0100_1100;
         cpu.p.write_flag(FlagPosition::Carry, true);
-        cpu.ror(super::ShiftOperand::A, None);
+        cpu.ror(super::ShiftOperand::A);
 
         assert_eq!(cpu.a, 0b1010_0110);
         assert_eq!(cpu.p.read_flag(FlagPosition::Carry), false);
@@ -2339,7 +3888,7 @@ mod test {
 
         cpu.a = 0b0100_1101;
         cpu.p.write_flag(FlagPosition::Carry, true);
-        cpu.ror(super::ShiftOperand::A, None);
+        cpu.ror(super::ShiftOperand::A);
 
         assert_eq!(cpu.a, 0b1010_0110);
         assert_eq!(cpu.p.read_flag(FlagPosition::Carry), true);
@@ -2350,18 +3899,11 @@ mod test {
     #[test]
     fn rti() {
         let mut memory = MemoryBus::new();
-        memory.add_region(crate::memory_bus::MemoryRegion {
-            start: 0,
-            end: 0xFFF,
-            read_handler: Box::new(|addr: usize| unsafe { MEMORY[addr] }),
-            write_handler: Box::new(|addr: usize, value: u8| unsafe { MEMORY[addr] = value }),
-        });
+        memory.add_ram(0, 0x1000);
 
-        unsafe {
-            MEMORY[0x10C] = 0xBA;
-            MEMORY[0x10B] = 0xBE;
-            MEMORY[0x10A] = 0x3;
-        }
+        memory.write_byte(0x10C, 0xBA);
+        memory.write_byte(0x10B, 0xBE);
+        memory.write_byte(0x10A, 0x3);
         let mut cpu = Cpu::new(memory);
         cpu.s = 0x9;
 
@@ -2376,17 +3918,10 @@ mod test {
     #[test]
     fn rts() {
         let mut memory = MemoryBus::new();
-        memory.add_region(crate::memory_bus::MemoryRegion {
-            start: 0,
-            end: 0xFFF,
-            read_handler: Box::new(|addr: usize| unsafe { MEMORY[addr] }),
-            write_handler: Box::new(|addr: usize, value: u8| unsafe { MEMORY[addr] = value }),
-        });
+        memory.add_ram(0, 0x1000);
 
-        unsafe {
-            MEMORY[0x10C] = 0xBA;
-            MEMORY[0x10B] = 0xBE;
-        }
+        memory.write_byte(0x10C, 0xBA);
+        memory.write_byte(0x10B, 0xBE);
         let mut cpu = Cpu::new(memory);
         cpu.s = 0xA;
 
@@ -2506,52 +4041,41 @@ mod test {
     #[test]
     fn sta() {
         let mut memory = MemoryBus::new();
-        memory.add_region(crate::memory_bus::MemoryRegion {
-            start: 0,
-            end: 0xF,
-            read_handler: Box::new(|addr: usize| unsafe { MEMORY[addr] }),
-            write_handler: Box::new(|addr: usize, value: u8| unsafe { MEMORY[addr] = value }),
-        });
+        memory.add_ram(0, 0x10);
 
         let mut cpu = Cpu::new(memory);
         cpu.a = 0x42;
 
         cpu.x = 0x1;
-        unsafe {
-            MEMORY[0x1] = 0x7;
-        }
+        cpu.write_memory(0x1, 0x7);
         cpu.execute(super::DecodedInstruction {
             int: crate::instruction::Instruction::StaXIndexedZeroIndirect,
             arg: super::Argument::Byte(0x0),
         });
-        assert_eq!(unsafe { MEMORY[0x7] }, 0x42);
+        assert_eq!(cpu.read_memory(0x7), 0x42);
 
-        unsafe {
-            MEMORY[0x1] = 0x7;
-        }
+        cpu.write_memory(0x1, 0x7);
         cpu.execute(super::DecodedInstruction {
             int: crate::instruction::Instruction::StaZeroPage,
             arg: super::Argument::Byte(0x6),
         });
-        assert_eq!(unsafe { MEMORY[0x6] }, 0x42);
+        assert_eq!(cpu.read_memory(0x6), 0x42);
 
-        unsafe {
-            MEMORY[0x0] = 0x7;
-            MEMORY[0x1] = 0x0;
-            MEMORY[0x7] = 0x0;
-        }
+        cpu.write_memory(0x0, 0x7);
+        cpu.write_memory(0x1, 0x0);
+        cpu.write_memory(0x7, 0x0);
         cpu.execute(super::DecodedInstruction {
             int: crate::instruction::Instruction::StaZeroIndirectIndexed,
             arg: super::Argument::Byte(0x0),
         });
-        assert_eq!(unsafe { MEMORY[0x7] }, 0x42);
+        assert_eq!(cpu.read_memory(0x7), 0x42);
 
         cpu.a = 0xBB;
         cpu.execute(super::DecodedInstruction {
             int: crate::instruction::Instruction::StaAbsolute,
             arg: super::Argument::Addr(0x8),
         });
-        assert_eq!(unsafe { MEMORY[0x8] }, 0xBB);
+        assert_eq!(cpu.read_memory(0x8), 0xBB);
 
         cpu.a = 0xAA;
         cpu.x = 0x4;
@@ -2559,39 +4083,30 @@ mod test {
             int: crate::instruction::Instruction::StaXIndexedZero,
             arg: super::Argument::Byte(0x1),
         });
-        assert_eq!(unsafe { MEMORY[0x5] }, 0xAA);
+        assert_eq!(cpu.read_memory(0x5), 0xAA);
 
         cpu.a = 0x40;
-        unsafe {
-            MEMORY[0x5] = 0x0;
-        }
+        cpu.write_memory(0x5, 0x0);
         cpu.execute(super::DecodedInstruction {
             int: crate::instruction::Instruction::StaXIndexedAbsolute,
             arg: super::Argument::Addr(0x1),
         });
-        assert_eq!(unsafe { MEMORY[0x5] }, 0x40);
+        assert_eq!(cpu.read_memory(0x5), 0x40);
 
         cpu.a = 0x41;
         cpu.y = 0x3;
-        unsafe {
-            MEMORY[0x5] = 0x0;
-        }
+        cpu.write_memory(0x5, 0x0);
         cpu.execute(super::DecodedInstruction {
             int: crate::instruction::Instruction::StaYIndexedAbsolute,
             arg: super::Argument::Addr(0x2),
         });
-        assert_eq!(unsafe { MEMORY[0x5] }, 0x41);
+        assert_eq!(cpu.read_memory(0x5), 0x41);
     }
 
     #[test]
     fn stx() {
         let mut memory = MemoryBus::new();
-        memory.add_region(crate::memory_bus::MemoryRegion {
-            start: 0,
-            end: 0xF,
-            read_handler: Box::new(|addr: usize| unsafe { MEMORY[addr] }),
-            write_handler: Box::new(|addr: usize, value: u8| unsafe { MEMORY[addr] = value }),
-        });
+        memory.add_ram(0, 0x10);
 
         let mut cpu = Cpu::new(memory);
         cpu.x = 0x42;
@@ -2600,14 +4115,14 @@ mod test {
             int: crate::instruction::Instruction::StxZeroPage,
             arg: super::Argument::Byte(0x6),
         });
-        assert_eq!(unsafe { MEMORY[0x6] }, 0x42);
+        assert_eq!(cpu.read_memory(0x6), 0x42);
 
         cpu.x = 0xBB;
         cpu.execute(super::DecodedInstruction {
             int: crate::instruction::Instruction::StxAbsolute,
             arg: super::Argument::Addr(0x8),
         });
-        assert_eq!(unsafe { MEMORY[0x8] }, 0xBB);
+        assert_eq!(cpu.read_memory(0x8), 0xBB);
 
         cpu.x = 0xBA;
         cpu.y = 0x5;
@@ -2615,18 +4130,13 @@ mod test {
             int: crate::instruction::Instruction::StxYIndexedZero,
             arg: super::Argument::Byte(0x4),
         });
-        assert_eq!(unsafe { MEMORY[0x9] }, 0xBA);
+        assert_eq!(cpu.read_memory(0x9), 0xBA);
     }
 
     #[test]
     fn sty() {
         let mut memory = MemoryBus::new();
-        memory.add_region(crate::memory_bus::MemoryRegion {
-            start: 0,
-            end: 0xF,
-            read_handler: Box::new(|addr: usize| unsafe { MEMORY[addr] }),
-            write_handler: Box::new(|addr: usize, value: u8| unsafe { MEMORY[addr] = value }),
-        });
+        memory.add_ram(0, 0x10);
 
         let mut cpu = Cpu::new(memory);
         cpu.y = 0x42;
@@ -2635,14 +4145,14 @@ mod test {
             int: crate::instruction::Instruction::StyZeroPage,
             arg: super::Argument::Byte(0x6),
         });
-        assert_eq!(unsafe { MEMORY[0x6] }, 0x42);
+        assert_eq!(cpu.read_memory(0x6), 0x42);
 
         cpu.y = 0xBB;
         cpu.execute(super::DecodedInstruction {
             int: crate::instruction::Instruction::StyAbsolute,
             arg: super::Argument::Addr(0x8),
         });
-        assert_eq!(unsafe { MEMORY[0x8] }, 0xBB);
+        assert_eq!(cpu.read_memory(0x8), 0xBB);
 
         cpu.y = 0xBA;
         cpu.x = 0x5;
@@ -2650,7 +4160,7 @@ mod test {
             int: crate::instruction::Instruction::StyXIndexedZero,
             arg: super::Argument::Byte(0x4),
         });
-        assert_eq!(unsafe { MEMORY[0x9] }, 0xBA);
+        assert_eq!(cpu.read_memory(0x9), 0xBA);
     }
 
     #[test]
@@ -2834,4 +4344,2231 @@ mod test {
     }
 
     // TODO: Test for JSR (to check correct stack usage)
+
+    #[test]
+    fn effective_address_indexed() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        let mut cpu = Cpu::new(memory);
+        cpu.x = 0x04;
+        cpu.y = 0x10;
+
+        let lda = super::DecodedInstruction {
+            int: crate::instruction::Instruction::LdaXIndexedZero,
+            arg: super::Argument::Byte(0x44),
+        };
+        assert_eq!(cpu.effective_address(&lda), Some(0x48));
+
+        let lda = super::DecodedInstruction {
+            int: crate::instruction::Instruction::LdaXIndexedZero,
+            arg: super::Argument::Byte(0xFE),
+        };
+        assert_eq!(cpu.effective_address(&lda), Some(0x02)); // wraps within zero page
+
+        let lda = super::DecodedInstruction {
+            int: crate::instruction::Instruction::LdaYIndexedAbsolute,
+            arg: super::Argument::Addr(0x12F8),
+        };
+        assert_eq!(cpu.effective_address(&lda), Some(0x1308));
+
+        let lda = super::DecodedInstruction {
+            int: crate::instruction::Instruction::LdaImmediate,
+            arg: super::Argument::Byte(0x44),
+        };
+        assert_eq!(cpu.effective_address(&lda), None);
+
+        let tax = super::DecodedInstruction {
+            int: crate::instruction::Instruction::Tax,
+            arg: super::Argument::Void,
+        };
+        assert_eq!(cpu.effective_address(&tax), None);
+    }
+
+    #[test]
+    fn effective_address_indirect() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        let mut cpu = Cpu::new(memory);
+        cpu.x = 0x04;
+        cpu.y = 0x05;
+        cpu.a = 0x99;
+
+        cpu.write_memory(0x24, 0x00);
+        cpu.write_memory(0x25, 0x30);
+        cpu.write_memory(0x40, 0x10);
+        cpu.write_memory(0x41, 0x20);
+
+        let lda = super::DecodedInstruction {
+            int: crate::instruction::Instruction::LdaXIndexedZeroIndirect,
+            arg: super::Argument::Byte(0x20),
+        };
+        assert_eq!(cpu.effective_address(&lda), Some(0x3000));
+
+        let sta = super::DecodedInstruction {
+            int: crate::instruction::Instruction::StaZeroIndirectIndexed,
+            arg: super::Argument::Byte(0x40),
+        };
+        assert_eq!(cpu.effective_address(&sta), Some(0x2015));
+
+        // Executor must store to the very same address
+        cpu.execute(sta);
+        assert_eq!(cpu.read_memory(0x2015), 0x99);
+    }
+
+    #[test]
+    fn flag_log() {
+        let memory = MemoryBus::new();
+        let mut cpu = Cpu::new(memory);
+        cpu.pc = 0x0200;
+        cpu.a = 0x50;
+
+        // Disabled by default
+        cpu.execute(super::DecodedInstruction {
+            int: crate::instruction::Instruction::CmpImmediate,
+            arg: super::Argument::Byte(0x10),
+        });
+        assert!(cpu.take_flag_log().is_empty());
+
+        cpu.log_flag_changes(true);
+        cpu.p.write_flag(FlagPosition::Carry, false);
+        cpu.pc = 0x0200;
+
+        cpu.execute(super::DecodedInstruction {
+            int: crate::instruction::Instruction::CmpImmediate,
+            arg: super::Argument::Byte(0x10),
+        });
+        assert_eq!(
+            cpu.take_flag_log(),
+            vec![super::FlagChange {
+                pc: 0x0200,
+                instruction: crate::instruction::Instruction::CmpImmediate,
+                flag: FlagPosition::Carry,
+                old: false,
+                new: true,
+            }]
+        );
+        assert!(cpu.take_flag_log().is_empty());
+
+        // Flags already in their resulting state are not logged
+        cpu.execute(super::DecodedInstruction {
+            int: crate::instruction::Instruction::CmpImmediate,
+            arg: super::Argument::Byte(0x10),
+        });
+        assert!(cpu.take_flag_log().is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "illegal_opcodes")]
+    fn multi_byte_nops() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        load(
+            &mut memory,
+            0x0200,
+            &[0x04, 0x10, 0x1C, 0x00, 0x30, 0x80, 0xFF],
+        );
+        let mut cpu = Cpu::new(memory);
+        cpu.pc = 0x0200;
+        cpu.x = 0x01;
+
+        cpu.step(); // DOP $10
+        assert_eq!(cpu.pc, 0x0202);
+        cpu.step(); // TOP $3000,X
+        assert_eq!(cpu.pc, 0x0205);
+        cpu.step(); // DOP #$FF
+        assert_eq!(cpu.pc, 0x0207);
+
+        assert_eq!(cpu.a, 0x00);
+        assert_eq!(cpu.x, 0x01);
+        assert_eq!(cpu.y, 0x00);
+        assert_eq!(Into::<u8>::into(&cpu.p), 0x20);
+    }
+
+    #[test]
+    fn write_read_memory() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        let mut cpu = Cpu::new(memory);
+
+        cpu.write_memory(0x3000, 0x42);
+        assert_eq!(cpu.address_space.peek(0x3000), 0x42);
+        assert_eq!(cpu.read_memory(0x3000), 0x42);
+
+        cpu.write_memory(0x0200, 0xAD); // LDA $3000
+        cpu.write_memory(0x0201, 0x00);
+        cpu.write_memory(0x0202, 0x30);
+        cpu.pc = 0x0200;
+        cpu.step();
+        assert_eq!(cpu.a, 0x42);
+    }
+
+    #[test]
+    fn branch_cycles() {
+        let memory = MemoryBus::new();
+        let mut cpu = Cpu::new(memory);
+
+        // Not taken
+        cpu.pc = 0x0200;
+        cpu.p.write_flag(FlagPosition::Zero, true);
+        cpu.execute(super::DecodedInstruction {
+            int: crate::instruction::Instruction::Bne,
+            arg: super::Argument::Byte(0x10),
+        });
+        assert_eq!(cpu.pc, 0x0202);
+        assert_eq!(cpu.cycles(), 2);
+
+        // Taken, same page
+        cpu.pc = 0x0200;
+        cpu.p.write_flag(FlagPosition::Zero, false);
+        cpu.execute(super::DecodedInstruction {
+            int: crate::instruction::Instruction::Bne,
+            arg: super::Argument::Byte(0x10),
+        });
+        assert_eq!(cpu.pc, 0x0212);
+        assert_eq!(cpu.cycles(), 2 + 3);
+
+        // Taken, next page
+        cpu.pc = 0x02F0;
+        cpu.execute(super::DecodedInstruction {
+            int: crate::instruction::Instruction::Bne,
+            arg: super::Argument::Byte(0x20),
+        });
+        assert_eq!(cpu.pc, 0x0312);
+        assert_eq!(cpu.cycles(), 2 + 3 + 4);
+
+        // Taken, previous page
+        cpu.pc = 0x0300;
+        cpu.execute(super::DecodedInstruction {
+            int: crate::instruction::Instruction::Bne,
+            arg: super::Argument::Byte(-0x10i8 as u8),
+        });
+        assert_eq!(cpu.pc, 0x02F2);
+        assert_eq!(cpu.cycles(), 2 + 3 + 4 + 4);
+    }
+
+    #[test]
+    fn branch_offset_boundaries() {
+        let memory = MemoryBus::new();
+        let mut cpu = Cpu::new(memory);
+
+        // BNE * at $0802 branches back onto itself
+        cpu.pc = 0x0802;
+        cpu.execute(super::DecodedInstruction {
+            int: crate::instruction::Instruction::Bne,
+            arg: super::Argument::Byte(-2i8 as u8),
+        });
+        assert_eq!(cpu.pc, 0x0802);
+
+        // Offsets are relative to the instruction following the branch
+        cpu.pc = 0x0804;
+        let super::BranchResult(taken, page_crossed) = cpu.branch(-2, FlagPosition::Zero, false);
+        assert!(taken && !page_crossed);
+        assert_eq!(cpu.pc, 0x0802);
+
+        // Largest forward offset crosses into the next page
+        cpu.pc = 0x08F2;
+        let super::BranchResult(taken, page_crossed) = cpu.branch(0x7F, FlagPosition::Zero, false);
+        assert!(taken && page_crossed);
+        assert_eq!(cpu.pc, 0x0971);
+
+        // Largest backward offset crosses into the previous page
+        cpu.pc = 0x0812;
+        let super::BranchResult(taken, page_crossed) = cpu.branch(-0x80, FlagPosition::Zero, false);
+        assert!(taken && page_crossed);
+        assert_eq!(cpu.pc, 0x0792);
+
+        // Landing on the first byte of the same page is not a crossing
+        cpu.pc = 0x0810;
+        let super::BranchResult(taken, page_crossed) = cpu.branch(-0x10, FlagPosition::Zero, false);
+        assert!(taken && !page_crossed);
+        assert_eq!(cpu.pc, 0x0800);
+
+        // Wraps around the top of the address space
+        cpu.pc = 0xFFF0;
+        let super::BranchResult(taken, page_crossed) = cpu.branch(0x20, FlagPosition::Zero, false);
+        assert!(taken && page_crossed);
+        assert_eq!(cpu.pc, 0x0010);
+    }
+
+    #[test]
+    fn public_stack_operations() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        let mut cpu = Cpu::new(memory);
+        cpu.s = 0xFF;
+
+        cpu.push_word(0xBEEF);
+        assert_eq!(cpu.s, 0xFD);
+        assert_eq!(cpu.read_memory(0x1FF), 0xBE);
+        assert_eq!(cpu.read_memory(0x1FE), 0xEF);
+
+        cpu.push_byte(0x42);
+        assert_eq!(cpu.s, 0xFC);
+        assert_eq!(cpu.read_memory(0x1FD), 0x42);
+
+        assert_eq!(cpu.pop_byte(), 0x42);
+        assert_eq!(cpu.pop_word(), 0xBEEF);
+        assert_eq!(cpu.s, 0xFF);
+
+        // Stack pointer wraps within page one
+        cpu.s = 0x00;
+        cpu.push_word(0x1234);
+        assert_eq!(cpu.s, 0xFE);
+        assert_eq!(cpu.read_memory(0x100), 0x12);
+        assert_eq!(cpu.read_memory(0x1FF), 0x34);
+        assert_eq!(cpu.pop_word(), 0x1234);
+        assert_eq!(cpu.s, 0x00);
+    }
+
+    #[test]
+    fn adc_overflow() {
+        let memory = MemoryBus::new();
+        let mut cpu = Cpu::new(memory);
+
+        // (A, operand, carry in, expected V)
+        let cases = [
+            (0x50, 0x50, false, true),  // pos + pos = neg
+            (0xD0, 0x90, false, true),  // neg + neg = pos, carry out
+            (0x50, 0xD0, false, false), // pos + neg
+            (0xD0, 0xD0, false, false), // neg + neg = neg, carry out
+            (0x7F, 0x00, true, true),   // carry in pushes past 0x7F
+            (0xFF, 0x80, true, false),  // -1 + -128 + 1 = -128
+            (0x80, 0xFF, false, true),  // -128 + -1 = 127
+            (0xFF, 0xFF, true, false),  // carry out without overflow
+        ];
+
+        for (a, operand, carry, overflow) in cases {
+            cpu.a = a;
+            cpu.p.write_flag(FlagPosition::Carry, carry);
+            cpu.adc(operand);
+            assert_eq!(
+                cpu.p.read_flag(FlagPosition::Overflow),
+                overflow,
+                "{a:#X} + {operand:#X} + {carry}"
+            );
+        }
+    }
+
+    #[test]
+    fn adc_overflow_exhaustive() {
+        let memory = MemoryBus::new();
+        let mut cpu = Cpu::new(memory);
+
+        for a in 0..=u8::MAX {
+            for operand in 0..=u8::MAX {
+                for carry in [false, true] {
+                    cpu.a = a;
+                    cpu.p.write_flag(FlagPosition::Carry, carry);
+                    cpu.adc(operand);
+
+                    let signed = a as i8 as i16 + operand as i8 as i16 + carry as i16;
+                    let unsigned = a as u16 + operand as u16 + carry as u16;
+
+                    assert_eq!(cpu.a, unsigned as u8);
+                    assert_eq!(
+                        cpu.p.read_flag(FlagPosition::Overflow),
+                        !(-128..=127).contains(&signed),
+                        "{a:#X} + {operand:#X} + {carry}"
+                    );
+                    assert_eq!(cpu.p.read_flag(FlagPosition::Carry), unsigned > 0xFF);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn stack_base() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        let mut cpu = Cpu::new(memory);
+        assert_eq!(cpu.stack_base(), 0x0100);
+
+        cpu.s = 0xFF;
+        cpu.a = 0x42;
+        cpu.execute(super::DecodedInstruction {
+            int: crate::instruction::Instruction::Pha,
+            arg: super::Argument::Void,
+        });
+        assert_eq!(cpu.read_memory(0x01FF), 0x42);
+
+        cpu.set_stack_base(0x0300);
+        cpu.a = 0x17;
+        cpu.execute(super::DecodedInstruction {
+            int: crate::instruction::Instruction::Pha,
+            arg: super::Argument::Void,
+        });
+        assert_eq!(cpu.read_memory(0x03FE), 0x17);
+        assert_eq!(cpu.read_memory(0x01FE), 0x00);
+    }
+
+    #[derive(Clone, Default)]
+    #[cfg(not(feature = "no_std"))]
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    #[cfg(not(feature = "no_std"))]
+    impl std::io::Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_std"))]
+    fn trace_to_writer() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        load(&mut memory, 0x0600, &[0xA9, 0x01, 0xAA, 0x8D, 0x00, 0x02]);
+        let mut cpu = Cpu::new(memory);
+        cpu.pc = 0x0600;
+        cpu.s = 0xFD;
+
+        let buffer = SharedBuffer::default();
+        cpu.trace_to(Box::new(buffer.clone()));
+        cpu.step();
+        cpu.step();
+        cpu.step();
+        cpu.stop_trace();
+        cpu.step();
+
+        let trace = String::from_utf8(buffer.0.borrow().clone()).unwrap();
+        assert_eq!(
+            trace,
+            "0600  A9 01     LDA #$01                 A:00 X:00 Y:00 P:20 SP:FD CYC:0\n\
+             0602  AA        TAX                      A:01 X:00 Y:00 P:20 SP:FD CYC:2\n\
+             0603  8D 00 02  STA $0200                A:01 X:01 Y:00 P:20 SP:FD CYC:4\n"
+        );
+    }
+    #[test]
+    fn cpu_state_diff() {
+        let memory = MemoryBus::new();
+        let mut cpu = Cpu::new(memory);
+        cpu.a = 0x01;
+        let before = cpu.state();
+        assert!(before.diff(&cpu.state()).is_empty());
+
+        cpu.a = 0x02;
+        cpu.p.write_flag(FlagPosition::Zero, true);
+        let after = cpu.state();
+
+        assert_eq!(
+            before.diff(&after),
+            vec!["A: 0x01 != 0x02", "flag Zero: false != true"]
+        );
+    }
+
+    #[test]
+    fn new_deterministic() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        memory.write_byte(0xFFFC, 0x00);
+        memory.write_byte(0xFFFD, 0x06);
+        let cpu = Cpu::new_deterministic(memory);
+
+        assert_eq!(cpu.a, 0x00);
+        assert_eq!(cpu.x, 0x00);
+        assert_eq!(cpu.y, 0x00);
+        assert_eq!(cpu.s, 0xFD);
+        assert_eq!(cpu.pc, 0x0600);
+        assert_eq!(Into::<u8>::into(&cpu.p), 0x24);
+        assert_eq!(cpu.cycles(), 0);
+
+        // Without a mapped reset vector PC starts at 0
+        let cpu = Cpu::new_deterministic(MemoryBus::new());
+        assert_eq!(cpu.pc, 0x0000);
+        assert_eq!(cpu.s, 0xFD);
+        assert_eq!(Into::<u8>::into(&cpu.p), 0x24);
+    }
+
+    // Handler at $0300 loading X with 1 when entered by IRQ and 2 when entered by BRK
+    fn break_flag_dispatch_cpu() -> Cpu {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        load(
+            &mut memory,
+            0x0300,
+            &[
+                0x68, // PLA
+                0x48, // PHA
+                0x29, 0x10, // AND #$10
+                0xD0, 0x04, // BNE $030A
+                0xA2, 0x01, // LDX #$01
+                0xEA, // NOP
+                0xEA, // NOP
+                0xA2, 0x02, // LDX #$02
+            ],
+        );
+        memory.write_byte(0xFFFE, 0x00);
+        memory.write_byte(0xFFFF, 0x03);
+        memory.write_byte(0x0600, 0x00); // BRK
+        let mut cpu = Cpu::new(memory);
+        cpu.s = 0xFF;
+        cpu.pc = 0x0600;
+
+        cpu
+    }
+
+    #[test]
+    fn irq_clears_break_flag() {
+        let mut cpu = break_flag_dispatch_cpu();
+
+        assert!(cpu.irq());
+        assert_eq!(cpu.pc, 0x0300);
+        assert_eq!(cpu.p.read_flag(FlagPosition::IrqDisable), true);
+        assert_eq!(cpu.read_memory(0x1FD) & 0x30, 0x20);
+        assert_eq!(cpu.cycles(), 7);
+
+        for _ in 0..5 {
+            cpu.step();
+        }
+        assert_eq!(cpu.x, 0x01);
+
+        // Masked while the handler runs
+        assert!(!cpu.irq());
+    }
+
+    #[test]
+    fn brk_sets_break_flag() {
+        let mut cpu = break_flag_dispatch_cpu();
+
+        cpu.step();
+        assert_eq!(cpu.pc, 0x0300);
+        assert_eq!(cpu.read_memory(0x1FD) & 0x30, 0x30);
+
+        for _ in 0..5 {
+            cpu.step();
+        }
+        assert_eq!(cpu.x, 0x02);
+    }
+
+    #[test]
+    fn nmi() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        memory.write_byte(0xFFFA, 0x00);
+        memory.write_byte(0xFFFB, 0x04);
+        let mut cpu = Cpu::new(memory);
+        cpu.s = 0xFF;
+        cpu.pc = 0x0612;
+        cpu.p.write_flag(FlagPosition::IrqDisable, true);
+
+        cpu.nmi();
+        assert_eq!(cpu.pc, 0x0400);
+        assert_eq!(cpu.read_memory(0x1FF), 0x06);
+        assert_eq!(cpu.read_memory(0x1FE), 0x12);
+        assert_eq!(cpu.read_memory(0x1FD) & 0x30, 0x20);
+    }
+
+    #[test]
+    fn decimal_mode_cycles() {
+        // (variant, decimal mode) -> cycles of ADC #$01 and SBC #$01
+        let cycles = |variant: super::Variant, decimal: bool| {
+            let mut cpu = Cpu::new(MemoryBus::new());
+            cpu.set_variant(variant);
+            cpu.p.write_flag(FlagPosition::DecimalMode, decimal);
+
+            cpu.execute(super::DecodedInstruction {
+                int: crate::instruction::Instruction::AdcImmediate,
+                arg: super::Argument::Byte(0x01),
+            });
+            let adc = cpu.cycles();
+            cpu.execute(super::DecodedInstruction {
+                int: crate::instruction::Instruction::SbcImmediate,
+                arg: super::Argument::Byte(0x01),
+            });
+
+            (adc, cpu.cycles() - adc)
+        };
+
+        assert_eq!(cycles(super::Variant::Nmos, false), (2, 2));
+        assert_eq!(cycles(super::Variant::Nmos, true), (2, 2));
+        assert_eq!(cycles(super::Variant::Cmos, false), (2, 2));
+        assert_eq!(cycles(super::Variant::Cmos, true), (3, 3));
+    }
+
+    #[cfg(feature = "illegal_opcodes")]
+    fn run_rmw_combo(program: &[u8], setup: impl FnOnce(&mut Cpu)) -> Cpu {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        let mut cpu = Cpu::new(memory);
+        load(&mut cpu.address_space, 0x0200, program);
+        setup(&mut cpu);
+        cpu.pc = 0x0200;
+        cpu.step();
+
+        assert_eq!(cpu.pc, 0x0200 + program.len() as u16);
+        cpu
+    }
+
+    #[test]
+    #[cfg(feature = "illegal_opcodes")]
+    fn slo() {
+        let mut cpu = run_rmw_combo(&[0x07, 0x10], |cpu| {
+            cpu.write_memory(0x0010, 0x81);
+            cpu.a = 0x40;
+        });
+
+        assert_eq!(cpu.read_memory(0x0010), 0x02);
+        assert_eq!(cpu.a, 0x42);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Carry), true);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Zero), false);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Negative), false);
+        assert_eq!(cpu.cycles(), 5);
+    }
+
+    #[test]
+    #[cfg(feature = "illegal_opcodes")]
+    fn rla() {
+        let mut cpu = run_rmw_combo(&[0x37, 0x10], |cpu| {
+            cpu.write_memory(0x0011, 0x81);
+            cpu.x = 0x01;
+            cpu.a = 0xFF;
+            cpu.p.write_flag(FlagPosition::Carry, true);
+        });
+
+        assert_eq!(cpu.read_memory(0x0011), 0x03);
+        assert_eq!(cpu.a, 0x03);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Carry), true);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Zero), false);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Negative), false);
+        assert_eq!(cpu.cycles(), 6);
+    }
+
+    #[test]
+    #[cfg(feature = "illegal_opcodes")]
+    fn sre() {
+        let mut cpu = run_rmw_combo(&[0x4F, 0x00, 0x30], |cpu| {
+            cpu.write_memory(0x3000, 0x03);
+            cpu.a = 0x01;
+        });
+
+        assert_eq!(cpu.read_memory(0x3000), 0x01);
+        assert_eq!(cpu.a, 0x00);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Carry), true);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Zero), true);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Negative), false);
+        assert_eq!(cpu.cycles(), 6);
+    }
+
+    #[test]
+    #[cfg(feature = "illegal_opcodes")]
+    fn rra() {
+        let mut cpu = run_rmw_combo(&[0x73, 0x20], |cpu| {
+            cpu.write_memory(0x0020, 0x00);
+            cpu.write_memory(0x0021, 0x30);
+            cpu.write_memory(0x3001, 0x02);
+            cpu.y = 0x01;
+            cpu.a = 0x01;
+            cpu.p.write_flag(FlagPosition::Carry, true);
+        });
+
+        // ROR: 0x02 with carry in -> 0x81, carry out clear; ADC: 0x01 + 0x81 + 0
+        assert_eq!(cpu.read_memory(0x3001), 0x81);
+        assert_eq!(cpu.a, 0x82);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Carry), false);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Overflow), false);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Negative), true);
+        assert_eq!(cpu.cycles(), 8);
+    }
+
+    #[test]
+    fn custom_bus() {
+        // Every read returns the same byte, writes are only recorded
+        struct MockBus {
+            value: u8,
+            writes: Vec<(u16, u8)>,
+        }
+
+        impl crate::memory_bus::Bus for MockBus {
+            fn read(&self, _addr: u16) -> u8 {
+                self.value
+            }
+
+            fn write(&mut self, addr: u16, val: u8) {
+                self.writes.push((addr, val));
+            }
+        }
+
+        let mut cpu = Cpu::new(MockBus {
+            value: 0x85, // STA $85
+            writes: Vec::new(),
+        });
+        cpu.a = 0x42;
+        cpu.pc = 0x0200;
+
+        cpu.step();
+        assert_eq!(cpu.pc, 0x0202);
+        assert_eq!(cpu.read_memory(0x1234), 0x85);
+
+        cpu.write_memory(0x1234, 0x01);
+        cpu.push_byte(0x02);
+        assert_eq!(
+            cpu.address_space.writes,
+            vec![(0x0085, 0x42), (0x1234, 0x01), (0x0100, 0x02)]
+        );
+    }
+
+    #[test]
+    fn open_bus_read_returns_operand_high_byte() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x8000);
+        memory.set_unmapped_read_policy(crate::memory_bus::UnmappedReadPolicy::OpenBus);
+        load(&mut memory, 0x0200, &[0xAD, 0x34, 0x92]); // LDA $9234
+
+        let mut cpu = Cpu::new(memory);
+        cpu.pc = 0x0200;
+        cpu.step();
+
+        // The last byte on the bus before the unmapped read is the operand's high byte
+        assert_eq!(cpu.a, 0x92);
+    }
+
+    #[test]
+    fn try_step_indexed_memory_fault() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x3100);
+        load(&mut memory, 0x0200, &[0xBD, 0xF0, 0x30]); // LDA $30F0,X
+        load(&mut memory, 0x30FE, &[0xAD, 0x42]); // LDA $xx42
+
+        let mut cpu = Cpu::new(memory);
+        cpu.pc = 0x0200;
+        cpu.x = 0x20;
+
+        assert_eq!(
+            cpu.try_step(),
+            Err(crate::error::StepError::MemoryFault(0x3110))
+        );
+        assert_eq!(cpu.pc, 0x0200);
+        assert_eq!(cpu.cycles(), 0);
+
+        cpu.x = 0x0F;
+        assert_eq!(cpu.try_step(), Ok(()));
+        assert_eq!(cpu.a, 0x42);
+        assert_eq!(cpu.pc, 0x0203);
+
+        // Operand bytes run past the end of the region
+        cpu.pc = 0x30FE;
+        assert_eq!(
+            cpu.try_step(),
+            Err(crate::error::StepError::MemoryFault(0x3100))
+        );
+    }
+
+    #[test]
+    fn php_plp_round_trip() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        load(&mut memory, 0x0200, &[0x08, 0x28]); // PHP, PLP
+        let mut cpu = Cpu::new(memory);
+        cpu.pc = 0x0200;
+        cpu.s = 0xFF;
+        cpu.p = FlagsRegister::new(0b1100_1011);
+
+        cpu.step();
+        assert_eq!(cpu.read_memory(0x01FF), 0b1111_1011);
+
+        cpu.p = FlagsRegister::default();
+        cpu.step();
+        assert_eq!(Into::<u8>::into(&cpu.p), 0b1110_1011);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Negative), true);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Overflow), true);
+        assert_eq!(cpu.p.read_flag(FlagPosition::DecimalMode), true);
+        assert_eq!(cpu.p.read_flag(FlagPosition::IrqDisable), false);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Zero), true);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Carry), true);
+    }
+
+    #[test]
+    fn run_cycles() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        load(&mut memory, 0x0200, &[0xEA; 0x100]); // NOP
+        let mut cpu = Cpu::new(memory);
+        cpu.pc = 0x0200;
+
+        assert_eq!(cpu.run_cycles(10), 10);
+        assert_eq!(cpu.pc, 0x0205);
+
+        // Instructions are atomic, an odd budget overshoots
+        assert_eq!(cpu.run_cycles(3), 4);
+        assert_eq!(cpu.pc, 0x0207);
+        assert_eq!(cpu.cycles(), 14);
+    }
+
+    #[test]
+    fn register_state_diff() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        load(&mut memory, 0x0200, &[0x69, 0x80, 0xEA]); // ADC #$80
+        let mut cpu = Cpu::new(memory);
+        cpu.pc = 0x0200;
+        cpu.a = 0x90;
+
+        let before = cpu.registers();
+        cpu.step();
+        let mut after = cpu.registers();
+        assert_eq!(after.pc, 0x0202);
+        after.pc = before.pc;
+
+        assert_eq!(
+            before.diff(&after),
+            vec![
+                "A: 0x90 != 0x10",
+                "flag Overflow: false != true",
+                "flag Carry: false != true"
+            ]
+        );
+
+        // Only A and the carry differ
+        let mut other = before;
+        other.a = 0x91;
+        other.p |= 0x01;
+        assert_eq!(
+            before.diff(&other),
+            vec!["A: 0x90 != 0x91", "flag Carry: false != true"]
+        );
+    }
+
+    #[test]
+    fn decimal_flags_per_variant() {
+        // (variant, instruction, A, operand, carry) -> (A, Zero, Negative)
+        let run = |variant: super::Variant, adc: bool, a: u8, operand: u8, carry: bool| {
+            let mut cpu = Cpu::new(MemoryBus::new());
+            cpu.set_variant(variant);
+            cpu.p.write_flag(FlagPosition::DecimalMode, true);
+            cpu.p.write_flag(FlagPosition::Carry, carry);
+            cpu.a = a;
+
+            if adc {
+                cpu.adc(operand);
+            } else {
+                cpu.sbc(operand);
+            }
+
+            (
+                cpu.a,
+                cpu.p.read_flag(FlagPosition::Zero),
+                cpu.p.read_flag(FlagPosition::Negative),
+            )
+        };
+        use super::Variant::{Cmos, Nmos};
+
+        // 79 + 81 = 160: binary sum 0xFA, decimal result 0x60
+        assert_eq!(run(Nmos, true, 0x79, 0x81, false), (0x60, false, false));
+        assert_eq!(run(Cmos, true, 0x79, 0x81, false), (0x60, false, false));
+
+        // 99 + 1 = 100: binary sum 0x9A, decimal result 0x00
+        assert_eq!(run(Nmos, true, 0x99, 0x01, false), (0x00, false, true));
+        assert_eq!(run(Cmos, true, 0x99, 0x01, false), (0x00, true, false));
+
+        // 0 - 21 = -21: binary difference 0xDF, decimal result 0x79
+        assert_eq!(run(Nmos, false, 0x00, 0x21, true), (0x79, false, true));
+        assert_eq!(run(Cmos, false, 0x00, 0x21, true), (0x79, false, false));
+    }
+
+    #[test]
+    fn irq_latency_after_cli() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        load(&mut memory, 0x0200, &[0x58, 0xEA, 0xEA]); // CLI, NOP, NOP
+        memory.write_byte(0xFFFE, 0x00);
+        memory.write_byte(0xFFFF, 0x03);
+        memory.write_byte(0x0300, 0xEA);
+        let mut cpu = Cpu::new(memory);
+        cpu.pc = 0x0200;
+        cpu.s = 0xFF;
+        cpu.p.write_flag(FlagPosition::IrqDisable, true);
+        cpu.set_irq_line(true);
+
+        cpu.step(); // CLI
+        assert_eq!(cpu.pc, 0x0201);
+        cpu.step(); // NOP, the IRQ is delayed past it
+        assert_eq!(cpu.pc, 0x0202);
+        cpu.step(); // IRQ
+        assert_eq!(cpu.pc, 0x0300);
+        assert_eq!(cpu.pop_byte(), 0x20); // P with I still clear
+        assert_eq!(cpu.pop_word(), 0x0202);
+
+        // With I set the asserted line is ignored
+        cpu.pc = 0x0202;
+        cpu.step();
+        assert_eq!(cpu.pc, 0x0203);
+    }
+
+    #[test]
+    fn argument_conversion_errors() {
+        use crate::error::DecodeError;
+
+        let result: Result<u8, DecodeError> = super::Argument::Addr(0x1234).try_into();
+        let err = result.unwrap_err();
+        assert!(matches!(err, DecodeError::ByteExpectedArgument("Addr")));
+        assert_eq!(err.to_string(), "Expected byte argument, found Addr");
+
+        let result: Result<u16, DecodeError> = super::Argument::Void.try_into();
+        let err = result.unwrap_err();
+        assert!(matches!(err, DecodeError::AddrExpectedArgument("Void")));
+        assert_eq!(err.to_string(), "Expected address argument, found Void");
+
+        let result: Result<u16, DecodeError> = super::Argument::Byte(0x12).try_into();
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Expected address argument, found Byte"
+        );
+        let result: Result<u8, DecodeError> = super::Argument::Void.try_into();
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Expected byte argument, found Void"
+        );
+    }
+
+    #[test]
+    fn vectors() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        let mut cpu = Cpu::new(memory);
+        for (address, value) in [(0xFFFA, 0x0300u16), (0xFFFC, 0x0600), (0xFFFE, 0xE000)] {
+            cpu.write_memory(address, value as u8);
+            cpu.write_memory(address + 1, (value >> 8) as u8);
+        }
+
+        assert_eq!(cpu.nmi_vector(), 0x0300);
+        assert_eq!(cpu.reset_vector(), 0x0600);
+        assert_eq!(cpu.irq_vector(), 0xE000);
+    }
+
+    #[test]
+    fn cycle_callback() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        // LDA #$01 (2), STA $0200 (4), LDA $3000,X (4), NOP (2)
+        load(
+            &mut memory,
+            0x0600,
+            &[0xA9, 0x01, 0x8D, 0x00, 0x02, 0xBD, 0x00, 0x30, 0xEA, 0x00],
+        );
+        let mut cpu = Cpu::new(memory);
+        cpu.pc = 0x0600;
+        cpu.x = 0x01;
+
+        let ticks = Rc::new(RefCell::new(0u64));
+        let callback_ticks = Rc::clone(&ticks);
+        cpu.set_cycle_callback(Box::new(move || *callback_ticks.borrow_mut() += 1));
+
+        cpu.step();
+        assert_eq!(*ticks.borrow(), 2);
+        for _ in 0..3 {
+            cpu.step();
+        }
+        assert_eq!(*ticks.borrow(), 12);
+        assert_eq!(*ticks.borrow(), cpu.cycles());
+
+        cpu.clear_cycle_callback();
+        cpu.step();
+        assert_eq!(*ticks.borrow(), 12);
+    }
+
+    #[test]
+    fn reset_status() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        memory.write_byte(0xFFFC, 0x00);
+        memory.write_byte(0xFFFD, 0x06);
+        let mut cpu = Cpu::new(memory);
+        cpu.p = FlagsRegister::new(0xF7);
+
+        cpu.reset();
+        assert_eq!(Into::<u8>::into(&cpu.p), 0x24);
+        assert_eq!(cpu.p.read_flag(FlagPosition::IrqDisable), true);
+        assert_eq!(cpu.pc, 0x0600);
+    }
+
+    #[test]
+    fn save_restore_context() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        let mut cpu = Cpu::new(memory);
+        cpu.s = 0xFF;
+        cpu.a = 0x11;
+        cpu.x = 0x22;
+        cpu.y = 0x33;
+        cpu.p = FlagsRegister::new(0xC3);
+
+        cpu.save_context();
+        assert_eq!(cpu.s, 0xFB);
+        let stack: Vec<u8> = (0x01FC..0x0200).map(|addr| cpu.read_memory(addr)).collect();
+        assert_eq!(stack, [0xE3, 0x33, 0x22, 0x11]);
+
+        cpu.a = 0x00;
+        cpu.x = 0x00;
+        cpu.y = 0x00;
+        cpu.p = FlagsRegister::default();
+
+        cpu.restore_context();
+        assert_eq!(cpu.s, 0xFF);
+        assert_eq!(cpu.a, 0x11);
+        assert_eq!(cpu.x, 0x22);
+        assert_eq!(cpu.y, 0x33);
+        assert_eq!(Into::<u8>::into(&cpu.p), 0xE3);
+    }
+
+    #[test]
+    fn breakpoints() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        // loop: INX; CPX #$10; BNE loop; BRK
+        load(&mut memory, 0x0600, &[0xE8, 0xE0, 0x10, 0xD0, 0xFB, 0x00]);
+        let mut cpu = Cpu::new(memory);
+        cpu.pc = 0x0600;
+
+        cpu.add_conditional_breakpoint(Box::new(|cpu| cpu.x == 0x05));
+        assert_eq!(cpu.run(1_000), super::StopReason::Breakpoint(0x0601));
+        assert_eq!(cpu.x, 0x05);
+
+        // Resuming steps off the breakpoint, the next match is an address one
+        cpu.clear_breakpoints();
+        cpu.add_breakpoint(0x0600);
+        assert_eq!(cpu.run(1_000), super::StopReason::Breakpoint(0x0600));
+        assert_eq!(cpu.x, 0x05);
+        assert_eq!(cpu.run(1_000), super::StopReason::Breakpoint(0x0600));
+        assert_eq!(cpu.x, 0x06);
+
+        cpu.remove_breakpoint(0x0600);
+        assert_eq!(cpu.run(1_000), super::StopReason::Brk);
+        assert_eq!(cpu.x, 0x10);
+    }
+
+    #[test]
+    fn stack_error_detection() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        // loop: PHA; JMP loop
+        load(&mut memory, 0x0600, &[0x48, 0x4C, 0x00, 0x06]);
+        let mut cpu = Cpu::new(memory);
+        cpu.pc = 0x0600;
+        cpu.s = 0xFF;
+
+        // S wraps silently by default
+        assert_eq!(cpu.run(2_000), super::StopReason::CycleLimit);
+
+        cpu.detect_stack_errors(true);
+        cpu.s = 0xFF;
+        cpu.pc = 0x0600;
+        assert_eq!(cpu.run(10_000), super::StopReason::StackOverflow);
+        assert_eq!(cpu.s, 0xFF);
+        assert_eq!(cpu.pc, 0x0601);
+
+        // A pull from an empty stack
+        cpu.write_memory(0x0700, 0x68); // PLA
+        cpu.pc = 0x0700;
+        assert_eq!(cpu.run(10_000), super::StopReason::StackUnderflow);
+        assert_eq!(cpu.s, 0x00);
+    }
+
+    #[test]
+    fn load_asm_with_labels() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        let mut cpu = Cpu::new(memory);
+
+        cpu.load_asm(
+            "        LDX #$05 ; loop counter
+                     LDA #$00
+            loop:    CLC
+                     ADC #$03
+                     DEX
+                     BNE loop ; back to the label
+            done:    JMP done",
+            0x0600,
+        )
+        .unwrap();
+
+        assert_eq!(cpu.reset_vector(), 0x0600);
+        assert_eq!(cpu.pc, 0x0600);
+
+        let reason = cpu.run(1_000);
+        assert_eq!(reason, super::StopReason::InfiniteLoop(0x060A));
+        assert_eq!(cpu.a, 15);
+        assert_eq!(cpu.x, 0);
+    }
+
+    #[test]
+    fn last_instruction() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        load(&mut memory, 0x0600, &[0xA9, 0x42]);
+
+        let mut cpu = Cpu::new(memory);
+        cpu.set_pc(0x0600);
+        assert_eq!(cpu.last_instruction(), None);
+
+        cpu.step();
+        assert_eq!(
+            cpu.last_instruction(),
+            Some((
+                crate::instruction::Instruction::LdaImmediate,
+                super::Argument::Byte(0x42)
+            ))
+        );
+    }
+
+    #[test]
+    fn decode_at() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        load(&mut memory, 0x0600, &[0xEA, 0xAD, 0x34, 0x12, 0xEA]);
+
+        let cpu = Cpu::new(memory);
+        let first = cpu.decode_at(0x0601);
+        let second = cpu.decode_at(0x0601);
+
+        assert_eq!(first.int, crate::instruction::Instruction::LdaAbsolute);
+        assert_eq!(first.arg, super::Argument::Addr(0x1234));
+        assert_eq!(first.int, second.int);
+        assert_eq!(first.arg, second.arg);
+        assert_eq!(cpu.pc, 0);
+    }
+
+    #[test]
+    fn execute_opcode() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        let mut cpu = Cpu::new(memory);
+        cpu.set_pc(0x0600);
+
+        cpu.execute_opcode(0xA9, 0x42, 0x00);
+        assert_eq!(cpu.a, 0x42);
+        assert_eq!(cpu.pc, 0x0602);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Zero), false);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Negative), false);
+
+        cpu.execute_opcode(0xA9, 0x80, 0x00);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Negative), true);
+
+        // STA $1234 writes through the bus
+        cpu.execute_opcode(0x8D, 0x34, 0x12);
+        assert_eq!(cpu.read_memory(0x1234), 0x80);
+        assert_eq!(cpu.read_memory(0x0605), 0x00);
+    }
+
+    #[test]
+    fn code_write_callback() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        // STA $0607; INC $0607; STA $2000
+        load(
+            &mut memory,
+            0x0600,
+            &[0x8D, 0x07, 0x06, 0xEE, 0x07, 0x06, 0x8D, 0x00, 0x20],
+        );
+
+        let writes = Rc::new(RefCell::new(Vec::new()));
+        let callback_writes = Rc::clone(&writes);
+
+        let mut cpu = Cpu::new(memory);
+        cpu.set_pc(0x0600);
+        cpu.set_code_range(0x0600, 0x0608);
+        cpu.set_code_write_callback(Box::new(move |address| {
+            callback_writes.borrow_mut().push(address)
+        }));
+
+        cpu.step();
+        cpu.step();
+        cpu.step();
+
+        assert_eq!(*writes.borrow(), vec![0x0607, 0x0607]);
+    }
+
+    #[test]
+    fn bit_immediate() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        memory.write_byte(0x10, 0b1100_0000);
+
+        let mut cpu = Cpu::new(memory);
+        cpu.set_variant(super::Variant::Cmos);
+        cpu.a = 0xFF;
+        cpu.p.write_flag(FlagPosition::Negative, true);
+        cpu.p.write_flag(FlagPosition::Overflow, false);
+
+        // BIT #$00 only sets Z
+        cpu.execute_opcode(0x89, 0x00, 0x00);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Zero), true);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Negative), true);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Overflow), false);
+
+        // BIT $10 still copies N and V from the operand
+        cpu.execute_opcode(0x24, 0x10, 0x00);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Zero), false);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Negative), true);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Overflow), true);
+    }
+
+    #[test]
+    fn bit_immediate_on_nmos() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        memory.write_byte(0x0600, 0x89); // BIT #$00
+        let mut cpu = Cpu::new(memory);
+        cpu.pc = 0x0600;
+
+        // Only the undocumented DOP, so unknown without illegal_opcodes
+        if cfg!(feature = "illegal_opcodes") {
+            assert_eq!(cpu.try_step(), Ok(()));
+            assert_eq!(cpu.p.read_flag(FlagPosition::Zero), false);
+            assert_eq!(cpu.pc, 0x0602);
+        } else {
+            assert_eq!(
+                cpu.try_step(),
+                Err(crate::error::StepError::UnknownOpcode(0x89))
+            );
+            assert_eq!(cpu.run(100), super::StopReason::IllegalOpcode(0x89));
+        }
+    }
+
+    #[test]
+    fn is_implemented() {
+        let cpu = Cpu::new(MemoryBus::new());
+
+        assert!(cpu.is_implemented(0xA9));
+        assert!(!cpu.is_implemented(0x02));
+        assert_eq!(cpu.is_implemented(0x07), cfg!(feature = "illegal_opcodes"));
+        assert_eq!(cpu.is_implemented(0x89), cfg!(feature = "illegal_opcodes"));
+
+        let mut cpu = Cpu::new(MemoryBus::new());
+        cpu.set_variant(super::Variant::Cmos);
+        assert!(cpu.is_implemented(0x89));
+    }
+
+    #[test]
+    fn implemented_opcodes_execute() {
+        for variant in [super::Variant::Nmos, super::Variant::Cmos] {
+            for opcode in 0..=255u8 {
+                let mut memory = MemoryBus::new();
+                memory.add_ram(0, 0x10000);
+                load(&mut memory, 0x0200, &[opcode, 0x10, 0x20]);
+
+                let mut cpu = Cpu::new(memory);
+                cpu.set_variant(variant);
+                if !cpu.is_implemented(opcode) {
+                    continue;
+                }
+                cpu.pc = 0x0200;
+                cpu.set_trap_unimplemented(true);
+
+                assert!(
+                    !matches!(
+                        cpu.try_step(),
+                        Err(crate::error::StepError::Unimplemented(_))
+                    ),
+                    "{opcode:#04X} on {variant:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn opcode_coverage() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        // LDX #$02; DEX; BNE -3
+        load(&mut memory, 0x0600, &[0xA2, 0x02, 0xCA, 0xD0, 0xFD]);
+
+        let mut cpu = Cpu::new(memory);
+        cpu.set_pc(0x0600);
+        for _ in 0..5 {
+            cpu.step();
+        }
+
+        let coverage = cpu.opcode_coverage();
+        assert_eq!(coverage[0xA2], 1);
+        assert_eq!(coverage[0xCA], 2);
+        assert_eq!(coverage[0xD0], 2);
+        assert_eq!(coverage.iter().sum::<u64>(), 5);
+
+        cpu.clear_opcode_coverage();
+        assert!(cpu.opcode_coverage().iter().all(|count| *count == 0));
+    }
+
+    #[test]
+    fn php_sets_break_and_unused_in_pushed_copy() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        let mut cpu = Cpu::new(memory);
+        cpu.s = 0xFF;
+        cpu.p = FlagsRegister::new(0x00);
+
+        cpu.execute_opcode(0x08, 0x00, 0x00);
+
+        assert_eq!(cpu.read_memory(0x01FF), 0b0011_0000);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Break), false);
+        assert_eq!(Into::<u8>::into(&cpu.p), 0b0010_0000);
+    }
+
+    #[test]
+    fn asl_memory() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        memory.write_byte(0x0010, 0b1100_0001);
+        let mut cpu = Cpu::new(memory);
+
+        let result = cpu.asl(crate::cpu::ShiftOperand::Memory(0b1100_0001, 0x0010));
+        assert_eq!(result, 0b1000_0010);
+        assert_eq!(cpu.read_memory(0x0010), 0b1000_0010);
+        assert_eq!(cpu.a, 0);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Carry), true);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Negative), true);
+
+        // INC shares the same write-back
+        cpu.inc_dec(true, crate::cpu::IncDecOperand::Memory(0xFF, 0x0010));
+        assert_eq!(cpu.read_memory(0x0010), 0x00);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Zero), true);
+    }
+
+    #[test]
+    fn traps() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        let mut cpu = Cpu::new(memory);
+        cpu.load_asm(
+            "LDA #$AA
+            STA $6000
+            LDA #$01
+            STA $F000
+            pass: JMP pass",
+            0x0600,
+        )
+        .unwrap();
+
+        cpu.set_trap(0xF000, super::TrapMode::Write);
+        assert_eq!(cpu.run(1_000), super::StopReason::Trap { value: 0x01 });
+        assert_eq!(cpu.pc, 0x060A);
+        assert_eq!(cpu.read_memory(0xF000), 0x01);
+
+        cpu.set_pc(0x0600);
+        cpu.set_trap(0x060A, super::TrapMode::Execute);
+        assert_eq!(cpu.run(1_000), super::StopReason::Trap { value: 0x01 });
+
+        cpu.clear_trap();
+        assert_eq!(cpu.run(1_000), super::StopReason::InfiniteLoop(0x060A));
+    }
+
+    #[test]
+    fn step_back() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        let mut cpu = Cpu::new(memory);
+        cpu.load_asm("LDX #$01\nLDA #$42\nSTA $10\nPHA\nINX", 0x0600)
+            .unwrap();
+        cpu.s = 0xFF;
+        cpu.set_history_depth(1000);
+
+        cpu.step();
+        let snapshot = cpu.state();
+        cpu.step();
+        cpu.step();
+        cpu.step();
+        assert_eq!(cpu.read_memory(0x10), 0x42);
+        assert_eq!(cpu.read_memory(0x01FF), 0x42);
+
+        for _ in 0..3 {
+            assert!(cpu.step_back());
+        }
+        assert!(cpu.state().diff(&snapshot).is_empty());
+        assert_eq!(cpu.read_memory(0x10), 0x00);
+        assert_eq!(cpu.read_memory(0x01FF), 0x00);
+
+        // Only the most recent steps are kept
+        cpu.set_history_depth(2);
+        assert!(cpu.step_back());
+        assert!(!cpu.step_back());
+        assert_eq!(cpu.pc, 0x0600);
+    }
+
+    #[test]
+    fn jsr_at_top_of_memory() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        load(&mut memory, 0xFFFD, &[0x20, 0x00, 0x06]); // JSR $0600
+        memory.write_byte(0x0600, 0x60); // RTS
+        let mut cpu = Cpu::new(memory);
+        cpu.pc = 0xFFFD;
+        cpu.s = 0xFF;
+
+        cpu.step();
+        assert_eq!(cpu.pc, 0x0600);
+        assert_eq!(cpu.s, 0xFD);
+        assert_eq!(cpu.read_memory(0x01FF), 0xFF);
+        assert_eq!(cpu.read_memory(0x01FE), 0xFF);
+
+        cpu.step();
+        assert_eq!(cpu.pc, 0x0000);
+        assert_eq!(cpu.s, 0xFF);
+    }
+
+    #[test]
+    fn reset_decimal_flag_per_variant() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        let mut cpu = Cpu::new(memory);
+
+        cpu.p.write_flag(FlagPosition::DecimalMode, true);
+        cpu.reset();
+        assert_eq!(cpu.p.read_flag(FlagPosition::DecimalMode), true);
+
+        cpu.set_variant(super::Variant::Cmos);
+        cpu.reset();
+        assert_eq!(cpu.p.read_flag(FlagPosition::DecimalMode), false);
+        assert_eq!(Into::<u8>::into(&cpu.p), 0x24);
+    }
+
+    #[test]
+    fn reset_cycle_counter() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        let mut cpu = Cpu::new(memory);
+        // 8 cycles per iteration
+        cpu.load_asm("loop: LDA $10\nNOP\nJMP loop", 0x0600)
+            .unwrap();
+
+        for _ in 0..3 {
+            assert_eq!(cpu.run_cycles(16), 16);
+            assert_eq!(cpu.reset_cycle_counter(), 16);
+            assert_eq!(cpu.cycles(), 0);
+        }
+
+        // A frame ending mid-instruction overshoots into the counter total
+        assert_eq!(cpu.run_cycles(10), 11);
+        assert_eq!(cpu.reset_cycle_counter(), 11);
+    }
+
+    #[test]
+    fn read_u16() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        load(&mut memory, 0x0300, &[0x34, 0x12]);
+        memory.write_byte(0x02FF, 0x78);
+        memory.write_byte(0x0200, 0x99);
+        memory.write_byte(0xFFFF, 0xCD);
+        memory.write_byte(0x0000, 0xAB);
+        let mut cpu = Cpu::new(memory);
+
+        assert_eq!(cpu.read_u16(0x0300), 0x1234);
+        // Across a page boundary and the top of memory
+        assert_eq!(cpu.read_u16(0x02FF), 0x3478);
+        assert_eq!(cpu.read_u16(0xFFFF), 0xABCD);
+
+        // JMP ($02FF) takes the high byte from $0200 on NMOS only
+        cpu.execute_opcode(0x6C, 0xFF, 0x02);
+        assert_eq!(cpu.pc, 0x9978);
+
+        cpu.set_variant(super::Variant::Cmos);
+        cpu.execute_opcode(0x6C, 0xFF, 0x02);
+        assert_eq!(cpu.pc, 0x3478);
+    }
+
+    #[test]
+    fn fetch_from_unmapped_address() {
+        let mut memory = MemoryBus::new();
+        memory.add_region(MemoryRegion {
+            start: 0,
+            end: 0x7FFF,
+            read_handler: Box::new(|_| 0xEA),
+            write_handler: Box::new(|_, _| {}),
+        });
+        memory.set_unmapped_read_policy(crate::memory_bus::UnmappedReadPolicy::OpenBus);
+
+        let mut cpu = Cpu::new(memory);
+        cpu.pc = 0x8000;
+        assert_eq!(
+            cpu.try_step(),
+            Err(crate::error::StepError::MemoryFault(0x8000))
+        );
+        assert_eq!(cpu.pc, 0x8000);
+
+        // Without the check the bus policy decides: open bus last held a NOP
+        cpu.read_memory(0x0000);
+        cpu.step();
+        assert_eq!(cpu.pc, 0x8001);
+    }
+
+    #[test]
+    fn peek_opcode() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        let mut cpu = Cpu::new(memory);
+        cpu.load_asm("LDA #$01\nBRK", 0x0600).unwrap();
+
+        assert_eq!(cpu.peek_opcode(), 0xA9);
+        assert_eq!(cpu.pc, 0x0600);
+        assert_eq!(cpu.address_space.last_bus_value(), 0x06);
+
+        cpu.step();
+        assert_eq!(cpu.peek_opcode(), 0x00);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unsupported clear flag instruction for flag 4")]
+    fn clear_flag_rejects_break() {
+        let mut cpu = Cpu::new(MemoryBus::new());
+        cpu.clear_flag(FlagPosition::Break);
+    }
+
+    #[test]
+    fn set_vectors() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        let mut cpu = Cpu::new(memory);
+
+        cpu.set_vectors(super::Vectors {
+            reset: 0x8000,
+            irq: 0x9010,
+            nmi: 0xA020,
+        });
+        let vectors: Vec<u8> = (0xFFFA..=0xFFFF)
+            .map(|addr| cpu.read_memory(addr))
+            .collect();
+        assert_eq!(vectors, [0x20, 0xA0, 0x00, 0x80, 0x10, 0x90]);
+
+        cpu.reset();
+        assert_eq!(cpu.pc, 0x8000);
+        assert_eq!(cpu.irq_vector(), 0x9010);
+        assert_eq!(cpu.nmi_vector(), 0xA020);
+    }
+
+    // Addressing mode of every opcode as listed in the datasheet opcode matrix,
+    // undocumented ones included. Kept apart from the decoder tables so the sweep
+    // below checks them instead of restating them. An empty entry fails the sweep
+    // as soon as that opcode decodes, so new opcodes need a row here.
+    #[rustfmt::skip]
+    const OPCODE_MODES: [&str; 256] = [
+        "imp", "izx", "",    "izx", "zp",  "zp",  "zp",  "zp",  "imp", "imm", "acc", "imm", "abs", "abs", "abs", "abs",
+        "rel", "izy", "",    "izy", "zpx", "zpx", "zpx", "zpx", "imp", "aby", "imp", "aby", "abx", "abx", "abx", "abx",
+        "abs", "izx", "",    "izx", "zp",  "zp",  "zp",  "zp",  "imp", "imm", "acc", "imm", "abs", "abs", "abs", "abs",
+        "rel", "izy", "",    "izy", "zpx", "zpx", "zpx", "zpx", "imp", "aby", "imp", "aby", "abx", "abx", "abx", "abx",
+        "imp", "izx", "",    "izx", "zp",  "zp",  "zp",  "zp",  "imp", "imm", "acc", "imm", "abs", "abs", "abs", "abs",
+        "rel", "izy", "",    "izy", "zpx", "zpx", "zpx", "zpx", "imp", "aby", "imp", "aby", "abx", "abx", "abx", "abx",
+        "imp", "izx", "",    "izx", "zp",  "zp",  "zp",  "zp",  "imp", "imm", "acc", "imm", "ind", "abs", "abs", "abs",
+        "rel", "izy", "",    "izy", "zpx", "zpx", "zpx", "zpx", "imp", "aby", "imp", "aby", "abx", "abx", "abx", "abx",
+        "imm", "izx", "imm", "izx", "zp",  "zp",  "zp",  "zp",  "imp", "imm", "imp", "imm", "abs", "abs", "abs", "abs",
+        "rel", "izy", "",    "izy", "zpx", "zpx", "zpy", "zpy", "imp", "aby", "imp", "aby", "abx", "abx", "aby", "aby",
+        "imm", "izx", "imm", "izx", "zp",  "zp",  "zp",  "zp",  "imp", "imm", "imp", "imm", "abs", "abs", "abs", "abs",
+        "rel", "izy", "",    "izy", "zpx", "zpx", "zpy", "zpy", "imp", "aby", "imp", "aby", "abx", "abx", "aby", "aby",
+        "imm", "izx", "imm", "izx", "zp",  "zp",  "zp",  "zp",  "imp", "imm", "imp", "imm", "abs", "abs", "abs", "abs",
+        "rel", "izy", "",    "izy", "zpx", "zpx", "zpx", "zpx", "imp", "aby", "imp", "aby", "abx", "abx", "abx", "abx",
+        "imm", "izx", "imm", "izx", "zp",  "zp",  "zp",  "zp",  "imp", "imm", "imp", "imm", "abs", "abs", "abs", "abs",
+        "rel", "izy", "",    "izy", "zpx", "zpx", "zpx", "zpx", "imp", "aby", "imp", "aby", "abx", "abx", "abx", "abx",
+    ];
+
+    // Runs `opcode $80 $12` at $0400 with X = $05, Y = $10 and every flag set to `flags`,
+    // returning the CPU afterwards, the effective address reported before the step
+    // and every address the step touched.
+    fn sweep_step(opcode: u8, flags: u8) -> (Cpu, Option<u16>, Vec<u16>) {
+        let storage = Rc::new(RefCell::new(vec![0; 0x10000]));
+        let accesses = Rc::new(RefCell::new(Vec::new()));
+        {
+            let mut mem = storage.borrow_mut();
+            mem[0x0400..0x0403].copy_from_slice(&[opcode, 0x80, 0x12]);
+            // ($80,X) -> $3412, ($80),Y -> $5634 + Y
+            mem[0x0085..0x0087].copy_from_slice(&[0x12, 0x34]);
+            mem[0x0080..0x0082].copy_from_slice(&[0x34, 0x56]);
+            // JMP ($1280) -> $2000
+            mem[0x1280..0x1282].copy_from_slice(&[0x00, 0x20]);
+            // Pulled status, then $2233 for RTI or $3300 + 1 for RTS
+            mem[0x01FD..0x0200].copy_from_slice(&[0x00, 0x33, 0x22]);
+            // BRK -> $3000
+            mem[0xFFFE..0x10000].copy_from_slice(&[0x00, 0x30]);
+        }
+
+        let read_storage = Rc::clone(&storage);
+        let write_storage = Rc::clone(&storage);
+        let read_accesses = Rc::clone(&accesses);
+        let write_accesses = Rc::clone(&accesses);
+        let mut memory = MemoryBus::new();
+        memory.add_region(MemoryRegion {
+            start: 0,
+            end: 0xFFFF,
+            read_handler: Box::new(move |addr: usize| {
+                read_accesses.borrow_mut().push(addr as u16);
+                read_storage.borrow()[addr]
+            }),
+            write_handler: Box::new(move |addr: usize, value: u8| {
+                write_accesses.borrow_mut().push(addr as u16);
+                write_storage.borrow_mut()[addr] = value
+            }),
+        });
+
+        let mut cpu = Cpu::new(memory);
+        if !cpu.is_implemented(opcode) {
+            // 65C02 addition such as BIT #imm
+            cpu.set_variant(super::Variant::Cmos);
+        }
+        cpu.pc = 0x0400;
+        cpu.x = 0x05;
+        cpu.y = 0x10;
+        cpu.s = 0xFC;
+        cpu.p.set_byte(flags);
+
+        let decoded = cpu.decode_at(0x0400);
+        let effective = cpu.effective_address(&decoded);
+        accesses.borrow_mut().clear();
+        cpu.step();
+        let touched = accesses.borrow().clone();
+
+        (cpu, effective, touched)
+    }
+
+    #[test]
+    fn addressing_mode_sweep() {
+        use crate::instruction::{opcode_length, Instruction};
+
+        for opcode in 0..=255u8 {
+            let Ok(instr) = Instruction::try_from(opcode) else {
+                continue;
+            };
+            let mode = OPCODE_MODES[opcode as usize];
+            assert!(
+                !mode.is_empty(),
+                "{instr:?} ({opcode:#04X}) has no addressing table entry"
+            );
+
+            if mode == "rel" {
+                // Exactly one of the two flag states takes the branch to $0402 - $80
+                let (clear, _, _) = sweep_step(opcode, 0x00);
+                let (set, _, _) = sweep_step(opcode, 0xFF);
+                let mut targets = [clear.pc, set.pc];
+                targets.sort();
+                assert_eq!(targets, [0x0382, 0x0402], "{instr:?} branch targets");
+                continue;
+            }
+
+            let (cpu, effective, touched) = sweep_step(opcode, 0x00);
+
+            let expected_pc = match instr {
+                Instruction::Jmp | Instruction::Jsr => 0x1280,
+                Instruction::JmpIndirect => 0x2000,
+                Instruction::Brk => 0x3000,
+                Instruction::Rti => 0x2233,
+                Instruction::Rts => 0x3301,
+                _ => 0x0400 + opcode_length(opcode).unwrap() as u16,
+            };
+            assert_eq!(
+                cpu.pc, expected_pc,
+                "{instr:?} ({opcode:#04X}) PC after step"
+            );
+
+            if mode == "ind" {
+                assert!(
+                    touched.contains(&0x1281),
+                    "{instr:?} did not read its pointer"
+                );
+                continue;
+            }
+
+            let expected_address = match mode {
+                "zp" => Some(0x0080),
+                "zpx" => Some(0x0085),
+                "zpy" => Some(0x0090),
+                "abs" if !matches!(instr, Instruction::Jmp | Instruction::Jsr) => Some(0x1280),
+                "abx" => Some(0x1285),
+                "aby" => Some(0x1290),
+                "izx" => Some(0x3412),
+                "izy" => Some(0x5644),
+                _ => None,
+            };
+            assert_eq!(
+                effective, expected_address,
+                "{instr:?} ({opcode:#04X}) effective address"
+            );
+            if let Some(address) = expected_address {
+                assert!(
+                    touched.contains(&address),
+                    "{instr:?} ({opcode:#04X}) never accessed {address:#06X}: {touched:X?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn strict_bcd() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        load(&mut memory, 0x0200, &[0x69, 0x01]); // ADC #$01
+
+        let mut cpu = Cpu::new(memory);
+        cpu.pc = 0x0200;
+        cpu.a = 0x0A;
+        cpu.p.write_flag(FlagPosition::DecimalMode, true);
+
+        // Off by default
+        assert_eq!(cpu.try_step(), Ok(()));
+
+        cpu.set_strict_bcd(true);
+        cpu.pc = 0x0200;
+        cpu.a = 0x0A;
+        assert_eq!(
+            cpu.try_step(),
+            Err(crate::error::StepError::InvalidBcd(0x0A))
+        );
+        assert_eq!(cpu.pc, 0x0200);
+
+        cpu.a = 0x09;
+        assert_eq!(cpu.try_step(), Ok(()));
+        assert_eq!(cpu.a, 0x10);
+
+        // Binary mode is left alone
+        cpu.p.write_flag(FlagPosition::DecimalMode, false);
+        cpu.pc = 0x0200;
+        cpu.a = 0x0A;
+        assert_eq!(cpu.try_step(), Ok(()));
+    }
+
+    #[test]
+    #[cfg(not(feature = "no_std"))]
+    fn trace_filter() {
+        use crate::instruction::Instruction;
+
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        // LDX #$02; DEX; BNE -3; STX $0200
+        load(
+            &mut memory,
+            0x0600,
+            &[0xA2, 0x02, 0xCA, 0xD0, 0xFD, 0x8E, 0x00, 0x02],
+        );
+        let mut cpu = Cpu::new(memory);
+        cpu.pc = 0x0600;
+        cpu.s = 0xFD;
+
+        let buffer = SharedBuffer::default();
+        cpu.trace_to(Box::new(buffer.clone()));
+        cpu.set_trace_filter(Box::new(|entry| {
+            matches!(
+                entry.instruction,
+                Instruction::Bcc
+                    | Instruction::Bcs
+                    | Instruction::Beq
+                    | Instruction::Bne
+                    | Instruction::Bmi
+                    | Instruction::Bpl
+                    | Instruction::Bvc
+                    | Instruction::Bvs
+            )
+        }));
+        for _ in 0..6 {
+            cpu.step();
+        }
+
+        let trace = String::from_utf8(buffer.0.borrow().clone()).unwrap();
+        let lines: Vec<&str> = trace.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|line| line.starts_with("0603  D0 FD")));
+    }
+
+    #[test]
+    fn operand_types_match_addressing_modes() {
+        use crate::instruction::{AddressingType, Instruction};
+        use crate::opcode_decoders::{
+            ArgumentType, INSTRUCTIONS_ADDRESSING, INSTRUCTIONS_ADDRESSING_MODE,
+        };
+
+        // The operand fetch expects the argument width implied by the addressing mode
+        for opcode in 0..=255u8 {
+            let Ok(instr) = Instruction::try_from(opcode) else {
+                continue;
+            };
+            let Some(mode) = INSTRUCTIONS_ADDRESSING_MODE.get(&instr) else {
+                continue;
+            };
+            let argument = INSTRUCTIONS_ADDRESSING.get(&instr).unwrap();
+            let matches = match mode {
+                AddressingType::Absolute
+                | AddressingType::XIndexedAbsolute
+                | AddressingType::YIndexedAbsolute => matches!(argument, ArgumentType::Addr),
+                _ => matches!(argument, ArgumentType::Byte),
+            };
+            assert!(matches, "{instr:?}: {mode:?} with {argument:?}");
+        }
+    }
+
+    #[test]
+    fn try_step_random_programs() {
+        // xorshift32, fixed seed so failures reproduce
+        let mut seed = 0x2545_F491u32;
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed
+        };
+
+        let mut rom_writes = 0;
+        for round in 0..64 {
+            let storage = Rc::new(RefCell::new(vec![0u8; 0x10000]));
+            storage
+                .borrow_mut()
+                .iter_mut()
+                .for_each(|byte| *byte = next() as u8);
+            let read_storage = Rc::clone(&storage);
+            let write_storage = Rc::clone(&storage);
+
+            // Odd rounds leave the top half unmapped, every fourth round maps
+            // the top quarter as ROM that faults on writes
+            let (ram_end, end) = match round % 4 {
+                1 | 3 => (0x7FFF, 0x7FFF),
+                2 => (0xBFFF, 0xFFFF),
+                _ => (0xFFFF, 0xFFFF),
+            };
+            let mut memory = MemoryBus::new();
+            memory.add_region(MemoryRegion {
+                start: 0,
+                end: ram_end,
+                read_handler: Box::new(move |addr: usize| read_storage.borrow()[addr]),
+                write_handler: Box::new(move |addr: usize, value: u8| {
+                    write_storage.borrow_mut()[addr] = value
+                }),
+            });
+            if ram_end < end {
+                let rom = storage.borrow()[ram_end + 1..].to_vec();
+                memory.add_rom(ram_end + 1, rom).unwrap();
+                memory.set_rom_write_policy(crate::memory_bus::RomWritePolicy::Fault);
+            }
+
+            let mut cpu = Cpu::new(memory);
+            cpu.pc = next() as u16 & end as u16;
+            cpu.a = next() as u8;
+            cpu.x = next() as u8;
+            cpu.y = next() as u8;
+            cpu.s = next() as u8;
+            cpu.p.set_byte(next() as u8);
+
+            for _ in 0..500 {
+                match cpu.try_step() {
+                    Ok(()) => {}
+                    Err(error) => {
+                        if let crate::error::StepError::RomWrite(_) = error {
+                            rom_writes += 1;
+                        }
+                        // Skip the offending byte and carry on
+                        cpu.pc = cpu.pc.wrapping_add(1) & end as u16;
+                    }
+                }
+            }
+        }
+        assert!(rom_writes > 0);
+    }
+
+    #[test]
+    fn set_pc_then_step() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        load(&mut memory, 0x0600, &[0xA9, 0x01]); // LDA #$01
+        load(&mut memory, 0x0700, &[0xA9, 0x02]); // LDA #$02
+
+        let mut cpu = Cpu::new(memory);
+        cpu.set_pc(0x0700);
+        assert_eq!(cpu.pc(), 0x0700);
+        cpu.step();
+
+        assert_eq!(cpu.a, 0x02);
+        assert_eq!(cpu.pc(), 0x0702);
+    }
+
+    // Runs one LDA at $0600 with X = $10, Y = $20 after `setup` prepared memory
+    fn lda_step(program: &[u8], setup: &[(usize, u8)]) -> Cpu {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        load(&mut memory, 0x0600, program);
+        for &(address, value) in setup {
+            memory.write_byte(address, value);
+        }
+
+        let mut cpu = Cpu::new(memory);
+        cpu.set_pc(0x0600);
+        cpu.x = 0x10;
+        cpu.y = 0x20;
+        cpu.step();
+        assert_eq!(cpu.pc, 0x0600 + program.len() as u16);
+
+        cpu
+    }
+
+    #[test]
+    fn lda_immediate_step() {
+        let cpu = lda_step(&[0xA9, 0x00], &[]);
+        assert_eq!(cpu.a, 0x00);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Zero), true);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Negative), false);
+
+        let cpu = lda_step(&[0xA9, 0x80], &[]);
+        assert_eq!(cpu.a, 0x80);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Zero), false);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Negative), true);
+    }
+
+    #[test]
+    fn lda_zero_page_step() {
+        let cpu = lda_step(&[0xA5, 0x42], &[(0x0042, 0x37)]);
+        assert_eq!(cpu.a, 0x37);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Zero), false);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Negative), false);
+    }
+
+    #[test]
+    fn lda_zero_page_x_step() {
+        let cpu = lda_step(&[0xB5, 0x42], &[(0x0052, 0x91)]);
+        assert_eq!(cpu.a, 0x91);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Negative), true);
+
+        // Index wraps within the zero page
+        let cpu = lda_step(&[0xB5, 0xF8], &[(0x0008, 0x00), (0x0108, 0x55)]);
+        assert_eq!(cpu.a, 0x00);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Zero), true);
+    }
+
+    #[test]
+    fn lda_absolute_step() {
+        let cpu = lda_step(&[0xAD, 0x34, 0x12], &[(0x1234, 0x7F)]);
+        assert_eq!(cpu.a, 0x7F);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Zero), false);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Negative), false);
+    }
+
+    #[test]
+    fn lda_absolute_x_step() {
+        // $12F8 + $10 crosses into the next page
+        let cpu = lda_step(&[0xBD, 0xF8, 0x12], &[(0x1308, 0xC0), (0x1208, 0x01)]);
+        assert_eq!(cpu.a, 0xC0);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Negative), true);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Zero), false);
+    }
+
+    #[test]
+    fn lda_x_indexed_indirect_step() {
+        // ($40,X) reads the pointer at $50
+        let cpu = lda_step(
+            &[0xA1, 0x40],
+            &[(0x0050, 0x00), (0x0051, 0x30), (0x3000, 0x00)],
+        );
+        assert_eq!(cpu.a, 0x00);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Zero), true);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Negative), false);
+    }
+
+    #[test]
+    fn lda_indirect_y_indexed_step() {
+        // ($40),Y with the pointer $30F0 at $40 lands on $3110
+        let cpu = lda_step(
+            &[0xB1, 0x40],
+            &[(0x0040, 0xF0), (0x0041, 0x30), (0x3110, 0xFF)],
+        );
+        assert_eq!(cpu.a, 0xFF);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Negative), true);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Zero), false);
+    }
+
+    #[test]
+    fn soft_reset_keeps_pc() {
+        // Nothing mapped, so reading the reset vector would panic
+        let mut cpu = Cpu::new(MemoryBus::new());
+        cpu.set_pc(0x0600);
+        cpu.a = 0x12;
+        cpu.s = 0x80;
+        cpu.p.set_byte(0x00);
+
+        cpu.soft_reset();
+
+        assert_eq!(cpu.pc, 0x0600);
+        assert_eq!(cpu.a, 0x00);
+        assert_eq!(cpu.s, 0x00);
+        assert_eq!(cpu.p.read_flag(FlagPosition::IrqDisable), true);
+    }
+
+    #[test]
+    fn shift_accumulator_and_memory_targets() {
+        // (accumulator opcode, zero page opcode, result of shifting $41 with carry clear)
+        let shifts = [
+            (0x0A, 0x06, 0x82), // ASL
+            (0x4A, 0x46, 0x20), // LSR
+            (0x2A, 0x26, 0x82), // ROL
+            (0x6A, 0x66, 0x20), // ROR
+        ];
+
+        for (accumulator, zero_page, expected) in shifts {
+            let mut memory = MemoryBus::new();
+            memory.add_ram(0, 0x10000);
+            load(&mut memory, 0x0600, &[accumulator, zero_page, 0x10]);
+            memory.write_byte(0x0010, 0x41);
+
+            let mut cpu = Cpu::new(memory);
+            cpu.set_pc(0x0600);
+            cpu.a = 0x41;
+
+            cpu.step();
+            assert_eq!(cpu.a, expected, "{accumulator:#04X}");
+            assert_eq!(cpu.read_memory(0x0010), 0x41, "{accumulator:#04X}");
+
+            cpu.a = 0x41;
+            cpu.p.write_flag(FlagPosition::Carry, false);
+            cpu.step();
+            assert_eq!(cpu.a, 0x41, "{zero_page:#04X}");
+            assert_eq!(cpu.read_memory(0x0010), expected, "{zero_page:#04X}");
+        }
+    }
+
+    #[test]
+    fn read_hook_supplies_fresh_values() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        // LDA $D010; LDA $D010
+        load(&mut memory, 0x0600, &[0xAD, 0x10, 0xD0, 0xAD, 0x10, 0xD0]);
+        let mut counter = 0u8;
+        memory.add_read_hook(
+            0xD010,
+            Box::new(move || {
+                counter += 1;
+                counter
+            }),
+        );
+
+        let mut cpu = Cpu::new(memory);
+        cpu.set_pc(0x0600);
+        cpu.step();
+        assert_eq!(cpu.a, 1);
+        cpu.step();
+        assert_eq!(cpu.a, 2);
+    }
+
+    #[test]
+    fn indirect_pointer_wraps_in_zero_page() {
+        // ($FF),Y: low byte at $FF, high byte at $00 rather than $0100
+        let cpu = lda_step(
+            &[0xB1, 0xFF],
+            &[
+                (0x00FF, 0x00),
+                (0x0000, 0x30),
+                (0x0100, 0x40),
+                (0x3020, 0x11),
+            ],
+        );
+        assert_eq!(cpu.a, 0x11);
+
+        // ($EF,X) with X = $10 reads the same split pointer
+        let cpu = lda_step(
+            &[0xA1, 0xEF],
+            &[
+                (0x00FF, 0x00),
+                (0x0000, 0x30),
+                (0x0100, 0x40),
+                (0x3000, 0x22),
+            ],
+        );
+        assert_eq!(cpu.a, 0x22);
+    }
+
+    #[test]
+    fn save_state_round_trip() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        load(&mut memory, 0x0600, &[0xA9, 0x80]); // LDA #$80
+
+        let mut cpu = Cpu::new(memory);
+        cpu.set_pc(0x0600);
+        cpu.p.write_flag(FlagPosition::Carry, true);
+        cpu.step();
+        cpu.write_memory(0x1234, 0x56);
+
+        let saved_state = cpu.state();
+        let saved = cpu.save_state();
+
+        cpu.step();
+        cpu.a = 0x00;
+        cpu.p.set_byte(0x00);
+        cpu.write_memory(0x1234, 0x00);
+
+        cpu.load_state(&saved).unwrap();
+        assert_eq!(cpu.state(), saved_state);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Negative), true);
+        assert_eq!(cpu.p.read_flag(FlagPosition::Carry), true);
+        assert_eq!(cpu.cycles(), 2);
+        assert_eq!(cpu.read_memory(0x1234), 0x56);
+
+        let mut bad = saved.clone();
+        bad[4] = 2;
+        assert_eq!(
+            cpu.load_state(&bad),
+            Err(crate::error::StateError::UnsupportedVersion(2))
+        );
+        assert_eq!(
+            cpu.load_state(&saved[..100]),
+            Err(crate::error::StateError::Truncated(100, saved.len()))
+        );
+        assert_eq!(
+            cpu.load_state(b"NES\x1a"),
+            Err(crate::error::StateError::BadHeader)
+        );
+    }
+
+    #[test]
+    fn scheduled_irq() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        // CLI; JMP $0601
+        load(&mut memory, 0x0600, &[0x58, 0x4C, 0x01, 0x06]);
+        load(&mut memory, 0xFFFE, &[0x00, 0x07]);
+
+        let mut cpu = Cpu::new(memory);
+        cpu.set_pc(0x0600);
+        cpu.s = 0xFF;
+        cpu.schedule(100, Box::new(|cpu| cpu.set_irq_line(true)));
+
+        let mut steps = 0;
+        while cpu.pc != 0x0700 {
+            assert!(cpu.cycles() < 100 || cpu.irq_line);
+            cpu.step();
+            steps += 1;
+            assert!(steps < 100, "IRQ never serviced");
+        }
+
+        // Serviced after at most one more JMP plus the interrupt sequence
+        assert!(
+            (100..100 + 3 + 7 + 3).contains(&cpu.cycles()),
+            "{}",
+            cpu.cycles()
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "illegal_opcodes"))]
+    fn trap_unimplemented() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        load(&mut memory, 0x0200, &[0x89, 0x01]); // BIT #$01, CMOS only
+
+        let mut cpu = Cpu::new(memory);
+        cpu.pc = 0x0200;
+        cpu.set_trap_unimplemented(true);
+
+        cpu.step();
+        assert_eq!(cpu.pc, 0x0200);
+        assert_eq!(cpu.instructions(), 1);
+
+        // `run_cycles` gives up instead of spinning on the same instruction
+        assert_eq!(cpu.run_cycles(100), 0);
+        assert_eq!(cpu.pc, 0x0200);
+        assert_eq!(cpu.instructions(), 2);
+
+        // `try_step` refuses it before executing anything
+        assert_eq!(
+            cpu.try_step(),
+            Err(crate::error::StepError::UnknownOpcode(0x89))
+        );
+        assert_eq!(cpu.instructions(), 2);
+    }
+
+    #[test]
+    #[cfg(not(feature = "illegal_opcodes"))]
+    #[should_panic(expected = "Unknown instruction BitImmediate")]
+    fn unimplemented_panics_without_trap() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        load(&mut memory, 0x0200, &[0x89, 0x01]); // BIT #$01, CMOS only
+
+        let mut cpu = Cpu::new(memory);
+        cpu.pc = 0x0200;
+        cpu.step();
+    }
+
+    #[test]
+    fn debug_output_includes_counters() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        load(&mut memory, 0x0600, &[0xA9, 0x00, 0xEA]); // LDA #$00; NOP
+
+        let mut cpu = Cpu::new(memory);
+        cpu.set_pc(0x0600);
+        cpu.step();
+        cpu.step();
+        assert_eq!(cpu.instructions(), 2);
+
+        let debug = format!("{cpu:?}");
+        assert!(debug.contains("Cycles: 4 Instructions: 2"), "{debug}");
+        assert!(debug.contains("P: nv-bdiZc"), "{debug}");
+    }
+
+    #[test]
+    fn jsr_rts_round_trip() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        // $0600: JSR $0700; LDX #$01
+        load(&mut memory, 0x0600, &[0x20, 0x00, 0x07, 0xA2, 0x01]);
+        // $0700: LDA #$42; RTS
+        load(&mut memory, 0x0700, &[0xA9, 0x42, 0x60]);
+
+        let mut cpu = Cpu::new(memory);
+        cpu.set_pc(0x0600);
+        cpu.s = 0xFF;
+
+        cpu.step();
+        assert_eq!(cpu.pc, 0x0700);
+        // Pushed address is the last byte of the JSR, RTS adds one
+        assert_eq!(cpu.read_memory(0x01FF), 0x06);
+        assert_eq!(cpu.read_memory(0x01FE), 0x02);
+
+        cpu.step();
+        cpu.step();
+        assert_eq!(cpu.pc, 0x0603);
+        assert_eq!(cpu.s, 0xFF);
+
+        cpu.step();
+        assert_eq!(cpu.a, 0x42);
+        assert_eq!(cpu.x, 0x01);
+    }
+
+    #[test]
+    fn call_stack() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, 0x10000);
+        // $0600: JSR $0700 / $0700: PHA; JSR $0800 / $0800: NOP
+        load(&mut memory, 0x0600, &[0x20, 0x00, 0x07]);
+        load(&mut memory, 0x0700, &[0x48, 0x20, 0x00, 0x08]);
+        memory.write_byte(0x0800, 0xEA);
+
+        let mut cpu = Cpu::new(memory);
+        cpu.set_pc(0x0600);
+        cpu.s = 0xFF;
+        assert!(cpu.call_stack().is_empty());
+
+        for _ in 0..3 {
+            cpu.step();
+        }
+        assert_eq!(cpu.pc, 0x0800);
+        assert_eq!(cpu.call_stack(), vec![0x0704, 0x0603]);
+    }
+
+    #[test]
+    fn rom_write_fault() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0x0000, 0x8000);
+        memory.add_rom(0xF000, vec![0xEA; 0x10]).unwrap();
+        memory.set_rom_write_policy(crate::memory_bus::RomWritePolicy::Fault);
+
+        let mut cpu = Cpu::new(memory);
+        cpu.write_memory(0x0200, 0x8D); // STA $F000
+        cpu.write_memory(0x0201, 0x00);
+        cpu.write_memory(0x0202, 0xF0);
+        cpu.pc = 0x0200;
+        assert_eq!(
+            cpu.try_step(),
+            Err(crate::error::StepError::RomWrite(0xF000))
+        );
+        assert_eq!(cpu.pc, 0x0200);
+        assert_eq!(cpu.read_memory(0xF000), 0xEA);
+
+        assert_eq!(cpu.run(100), super::StopReason::RomWrite(0xF000));
+        assert_eq!(cpu.read_memory(0xF000), 0xEA);
+    }
 }
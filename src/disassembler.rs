@@ -0,0 +1,230 @@
+#[cfg(feature = "no_std")]
+use alloc::{
+    format,
+    string::{String, ToString},
+};
+#[cfg(feature = "no_std")]
+use hashbrown::HashMap;
+#[cfg(not(feature = "no_std"))]
+use std::collections::HashMap;
+
+use crate::{
+    assembler::{mnemonic, syntax_mode, SyntaxMode},
+    cpu::Argument,
+    error::SymbolError,
+    instruction::{opcode_length, AddressingType, Instruction},
+    memory_bus::Bus,
+    util::dword_from_bytes,
+};
+
+/// Renders instructions in the syntax accepted by the assembler.
+/// Address operands found in the symbol table are shown as their label.
+#[derive(Debug, Default)]
+pub struct Disassembler {
+    symbols: HashMap<u16, String>,
+}
+
+/// Parses `addr=name` lines, with `addr` in hex and an optional `$` prefix.
+/// Blank lines and lines starting with `;` are skipped.
+pub fn parse_symbols(text: &str) -> Result<HashMap<u16, String>, SymbolError> {
+    let mut symbols = HashMap::new();
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+
+        let invalid = || SymbolError::InvalidLine(number + 1, line.to_string());
+        let (address, name) = line.split_once('=').ok_or_else(invalid)?;
+        let address = u16::from_str_radix(address.trim().trim_start_matches('$'), 16)
+            .map_err(|_| invalid())?;
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(invalid());
+        }
+
+        symbols.insert(address, name.to_string());
+    }
+
+    Ok(symbols)
+}
+
+/// Decodes instructions one after another from `start`, reading with `Bus::peek`.
+/// Yields `(address, instruction, argument)` for every instruction starting before
+/// `end` (exclusive). Stops early at the first byte that isn't a known opcode.
+pub fn instructions<B: Bus>(
+    mem: &B,
+    start: u16,
+    end: u16,
+) -> impl Iterator<Item = (u16, Instruction, Argument)> + '_ {
+    let mut address = u32::from(start);
+
+    core::iter::from_fn(move || {
+        if address >= u32::from(end) {
+            return None;
+        }
+
+        let pc = address as u16;
+        let opcode = mem.peek(pc);
+        let instr = Instruction::try_from(opcode).ok()?;
+        let length = opcode_length(opcode)?;
+
+        let byte = mem.peek(pc.wrapping_add(1));
+        let argument = match length {
+            1 => Argument::Void,
+            2 => Argument::Byte(byte),
+            _ => Argument::Addr(dword_from_bytes(byte, mem.peek(pc.wrapping_add(2)))),
+        };
+        address += u32::from(length);
+
+        Some((pc, instr, argument))
+    })
+}
+
+impl Disassembler {
+    pub fn new() -> Disassembler {
+        Disassembler::default()
+    }
+
+    pub fn with_symbols(symbols: HashMap<u16, String>) -> Disassembler {
+        Disassembler { symbols }
+    }
+
+    fn address(&self, address: u16) -> String {
+        match self.symbols.get(&address) {
+            Some(name) => name.clone(),
+            None => format!("${address:04X}"),
+        }
+    }
+
+    /// Disassembles the instruction at `address`, read with `Bus::peek`.
+    /// Returns the text and the instruction length, or `None` for an unknown opcode.
+    pub fn disassemble<B: Bus>(&self, mem: &B, address: u16) -> Option<(String, u8)> {
+        let opcode = mem.peek(address);
+        let instr = Instruction::try_from(opcode).ok()?;
+        let length = opcode_length(opcode)?;
+
+        let byte = mem.peek(address.wrapping_add(1));
+        let word = dword_from_bytes(byte, mem.peek(address.wrapping_add(2)));
+
+        let operand = match syntax_mode(instr) {
+            SyntaxMode::Implied => String::new(),
+            SyntaxMode::Accumulator => "A".to_string(),
+            SyntaxMode::Relative => {
+                let target = address.wrapping_add(2).wrapping_add(byte as i8 as u16);
+                self.address(target)
+            }
+            SyntaxMode::Indirect => format!("({})", self.address(word)),
+            SyntaxMode::Addressing(addressing_type) => match addressing_type {
+                AddressingType::Immediate => format!("#${byte:02X}"),
+                AddressingType::ZeroPage => format!("${byte:02X}"),
+                AddressingType::XIndexedZero => format!("${byte:02X},X"),
+                AddressingType::YIndexedZero => format!("${byte:02X},Y"),
+                AddressingType::Absolute => self.address(word),
+                AddressingType::XIndexedAbsolute => format!("{},X", self.address(word)),
+                AddressingType::YIndexedAbsolute => format!("{},Y", self.address(word)),
+                AddressingType::XIndexedZeroIndirect => format!("(${byte:02X},X)"),
+                AddressingType::ZeroIndirectIndexed => format!("(${byte:02X}),Y"),
+            },
+        };
+
+        let text = if operand.is_empty() {
+            mnemonic(instr).to_string()
+        } else {
+            format!("{} {operand}", mnemonic(instr))
+        };
+
+        Some((text, length))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        assembler::load_asm,
+        memory_bus::{MemoryBus, MEM_SPACE_END},
+    };
+
+    fn memory_with(source: &str) -> MemoryBus {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0, MEM_SPACE_END + 1);
+        load_asm(&mut memory, source, 0x0600).unwrap();
+
+        memory
+    }
+
+    #[test]
+    fn disassemble() {
+        let memory =
+            memory_with("LDA #$01\nSTA $0200,X\nLDA ($20),Y\nBNE $0600\nASL A\nJMP ($FFFC)");
+        let disassembler = Disassembler::new();
+
+        let mut address = 0x0600;
+        let mut lines = Vec::new();
+        while address < 0x060D {
+            let (text, length) = disassembler.disassemble(&memory, address).unwrap();
+            lines.push(text);
+            address += length as u16;
+        }
+
+        assert_eq!(
+            lines,
+            vec![
+                "LDA #$01",
+                "STA $0200,X",
+                "LDA ($20),Y",
+                "BNE $0600",
+                "ASL A",
+                "JMP ($FFFC)"
+            ]
+        );
+    }
+
+    #[test]
+    fn symbols() {
+        let memory = memory_with("JSR $FF00\nLDA $0200,X\nBEQ $0600");
+        let symbols =
+            parse_symbols("; ROM entry points\nFF00=PRINT_CHAR\n\n$0600 = START").unwrap();
+        let disassembler = Disassembler::with_symbols(symbols);
+
+        assert_eq!(
+            disassembler.disassemble(&memory, 0x0600),
+            Some(("JSR PRINT_CHAR".to_string(), 3))
+        );
+        assert_eq!(
+            disassembler.disassemble(&memory, 0x0603),
+            Some(("LDA $0200,X".to_string(), 3))
+        );
+        assert_eq!(
+            disassembler.disassemble(&memory, 0x0606),
+            Some(("BEQ START".to_string(), 2))
+        );
+
+        assert!(matches!(
+            parse_symbols("FF00=PRINT_CHAR\nnot a symbol"),
+            Err(SymbolError::InvalidLine(2, _))
+        ));
+    }
+
+    #[test]
+    fn instruction_stream() {
+        let memory = memory_with("LDA #$01\nSTA $0200\nTAX\nINX");
+        let decoded: Vec<_> = instructions(&memory, 0x0600, 0x0606).collect();
+
+        assert_eq!(
+            decoded,
+            vec![
+                (0x0600, Instruction::LdaImmediate, Argument::Byte(0x01)),
+                (0x0602, Instruction::StaAbsolute, Argument::Addr(0x0200)),
+                (0x0605, Instruction::Tax, Argument::Void),
+            ]
+        );
+
+        // 0x02 is not an opcode
+        let mut memory = memory_with("TAX\nJMP $0200");
+        memory.write_byte(0x0601, 0x02);
+        assert_eq!(instructions(&memory, 0x0600, 0x0700).count(), 1);
+    }
+}
@@ -1,11 +1,17 @@
+#[cfg(feature = "no_std")]
+use alloc::string::String;
+use core::fmt;
+
+use crate::instruction::Instruction;
+
 #[derive(thiserror::Error, Debug)]
 pub enum DecodeError {
     #[error("Unknown opcode: {0}")]
     UnknownOpcode(String),
-    #[error("Expected byte argument, found #OTHERTYPE#")] // TODO: Fill #OTHERTYPE#
-    ByteExpectedArgument,
-    #[error("Expected address argument, found #OTHERTYPE#")] // TODO: Fill #OTHERTYPE#
-    AddrExpectedArgument,
+    #[error("Expected byte argument, found {0}")]
+    ByteExpectedArgument(&'static str), // Name of the argument variant found
+    #[error("Expected address argument, found {0}")]
+    AddrExpectedArgument(&'static str),
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -14,4 +20,134 @@ pub enum MemoryBusError {
     ROMLoadOutOfBounds,
     #[error("Offset out of region bounds: {0:#X}")]
     OffsetOutOfBounds(usize),
+    #[error("Write to ROM address {0:#X}")]
+    RomWrite(usize),
+}
+
+/// 1-based line and column in assembler source, 0 where unknown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SourcePos {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for SourcePos {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum AsmError {
+    #[error("Unknown mnemonic {0} at {1}")]
+    UnknownMnemonic(String, SourcePos),
+    #[error("Invalid operand {0} at {1}")]
+    InvalidOperand(String, SourcePos),
+    #[error("{mnemonic} has no addressing mode for operand {operand} at {pos}")]
+    InvalidAddressingMode {
+        mnemonic: String,
+        operand: String,
+        pos: SourcePos,
+    },
+    #[error("Operand out of range: {0} at {1}")]
+    OperandOutOfRange(String, SourcePos),
+    #[error("Unknown label {0} at {1}")]
+    UnknownLabel(String, SourcePos),
+    #[error("Duplicate label {0} at {1}")]
+    DuplicateLabel(String, SourcePos),
+    #[error("Failed to write program: {0} at {1}")]
+    Write(MemoryBusError, SourcePos),
+}
+
+impl AsmError {
+    /// Where in the source the error was found
+    pub fn pos(&self) -> SourcePos {
+        match self {
+            AsmError::UnknownMnemonic(_, pos)
+            | AsmError::InvalidOperand(_, pos)
+            | AsmError::InvalidAddressingMode { pos, .. }
+            | AsmError::OperandOutOfRange(_, pos)
+            | AsmError::UnknownLabel(_, pos)
+            | AsmError::DuplicateLabel(_, pos)
+            | AsmError::Write(_, pos) => *pos,
+        }
+    }
+
+    pub(crate) fn pos_mut(&mut self) -> &mut SourcePos {
+        match self {
+            AsmError::UnknownMnemonic(_, pos)
+            | AsmError::InvalidOperand(_, pos)
+            | AsmError::InvalidAddressingMode { pos, .. }
+            | AsmError::OperandOutOfRange(_, pos)
+            | AsmError::UnknownLabel(_, pos)
+            | AsmError::DuplicateLabel(_, pos)
+            | AsmError::Write(_, pos) => pos,
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum StepError {
+    #[error("Access to unmapped address {0:#06X}")]
+    MemoryFault(u16),
+    #[error("Unknown opcode {0:#04X}")]
+    UnknownOpcode(u8),
+    #[error("Invalid BCD value {0:#04X} in decimal mode arithmetic")]
+    InvalidBcd(u8),
+    #[error("Write to ROM address {0:#06X}")]
+    RomWrite(u16),
+    #[error("No execute arm for {0:?}")]
+    Unimplemented(Instruction),
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum StateError {
+    #[error("Not a save state")]
+    BadHeader,
+    #[error("Unsupported save state version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Save state is {0} bytes, expected {1}")]
+    Truncated(usize, usize),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum HexdumpError {
+    #[error("Invalid hexdump on line {0}: {1}")]
+    InvalidLine(usize, String),
+    #[error(transparent)]
+    Write(#[from] MemoryBusError),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SymbolError {
+    #[error("Invalid symbol on line {0}: {1}")]
+    InvalidLine(usize, String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_error_variants() {
+        let errors = [
+            DecodeError::UnknownOpcode("0x02".to_string()),
+            DecodeError::ByteExpectedArgument("Addr"),
+            DecodeError::AddrExpectedArgument("Byte"),
+        ];
+
+        // No wildcard arm: renaming or adding a variant has to update this match
+        for error in errors {
+            let message = match &error {
+                DecodeError::UnknownOpcode(opcode) => format!("Unknown opcode: {opcode}"),
+                DecodeError::ByteExpectedArgument(found) => {
+                    format!("Expected byte argument, found {found}")
+                }
+                DecodeError::AddrExpectedArgument(found) => {
+                    format!("Expected address argument, found {found}")
+                }
+            };
+            assert_eq!(error.to_string(), message);
+        }
+    }
 }
@@ -1,5 +1,8 @@
+use core::fmt;
+
 pub struct FlagsRegister(u8);
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FlagPosition {
     Negative = 7,
     Overflow = 6,
@@ -11,15 +14,28 @@ pub enum FlagPosition {
     Carry = 0,
 }
 
-impl Into<u8> for FlagPosition {
-    fn into(self) -> u8 {
-        self as u8
+impl FlagPosition {
+    pub const ALL: [FlagPosition; 8] = [
+        FlagPosition::Negative,
+        FlagPosition::Overflow,
+        FlagPosition::Unused,
+        FlagPosition::Break,
+        FlagPosition::DecimalMode,
+        FlagPosition::IrqDisable,
+        FlagPosition::Zero,
+        FlagPosition::Carry,
+    ];
+}
+
+impl From<FlagPosition> for u8 {
+    fn from(flag: FlagPosition) -> u8 {
+        flag as u8
     }
 }
 
-impl Into<u8> for &FlagsRegister {
-    fn into(self) -> u8 {
-        self.0
+impl From<&FlagsRegister> for u8 {
+    fn from(flags: &FlagsRegister) -> u8 {
+        FlagsRegister::new(flags.0).0
     }
 }
 
@@ -30,8 +46,20 @@ impl Default for FlagsRegister {
 }
 
 impl FlagsRegister {
+    /// Bit 5 always reads as 1 on hardware, and Break only exists in copies
+    /// pushed to the stack, so both are normalized here.
     pub fn new(bits: u8) -> FlagsRegister {
-        FlagsRegister(bits)
+        FlagsRegister(bits & !(1 << FlagPosition::Break as u8) | 1 << FlagPosition::Unused as u8)
+    }
+
+    /// Replaces the whole register, normalized like `new`.
+    pub fn set_byte(&mut self, bits: u8) {
+        *self = FlagsRegister::new(bits);
+    }
+
+    /// Clears every flag. Unused still reads as 1.
+    pub fn clear(&mut self) {
+        self.set_byte(0);
     }
 
     pub fn write_flag(&mut self, flag: FlagPosition, set: bool) {
@@ -50,7 +78,25 @@ impl FlagsRegister {
     }
 }
 
+/// Renders the flags in `NV-BDIZC` order, upper case when set and lower case
+/// when clear. Bit 5 is always shown as `-`.
+impl fmt::Display for FlagsRegister {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (flag, letter) in FlagPosition::ALL.iter().zip("NV-BDIZC".chars()) {
+            let letter = match flag {
+                FlagPosition::Unused => '-',
+                _ if self.read_flag(*flag) => letter,
+                _ => letter.to_ascii_lowercase(),
+            };
+            write!(f, "{letter}")?;
+        }
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
 
@@ -115,7 +161,10 @@ mod tests {
     #[test]
     fn flags_into_u8() {
         let flags = FlagsRegister(0b10000001);
-        assert_eq!(Into::<u8>::into(&flags), 0b10000001);
+        assert_eq!(Into::<u8>::into(&flags), 0b10100001);
+
+        let flags = FlagsRegister(0b11111111);
+        assert_eq!(Into::<u8>::into(&flags), 0b11101111);
     }
 
     #[test]
@@ -123,5 +172,56 @@ mod tests {
         let flags = FlagsRegister::new(0b10000001);
         assert_eq!(flags.read_flag(FlagPosition::Negative), true);
         assert_eq!(flags.read_flag(FlagPosition::Carry), true);
+
+        let flags = FlagsRegister::new(0b00010000);
+        assert_eq!(flags.read_flag(FlagPosition::Break), false);
+        assert_eq!(flags.read_flag(FlagPosition::Unused), true);
+    }
+
+    #[test]
+    fn set_byte_and_clear() {
+        let mut flags = FlagsRegister::default();
+
+        flags.set_byte(0xFF);
+        for flag in FlagPosition::ALL {
+            assert_eq!(
+                flags.read_flag(flag),
+                flag != FlagPosition::Break,
+                "{flag:?}"
+            );
+        }
+
+        flags.clear();
+        for flag in FlagPosition::ALL {
+            assert_eq!(
+                flags.read_flag(flag),
+                flag == FlagPosition::Unused,
+                "{flag:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn break_and_unused_positions() {
+        let mut flags = FlagsRegister(0);
+
+        flags.write_flag(FlagPosition::Break, true);
+        assert_eq!(flags.0, 0b0001_0000);
+        assert_eq!(flags.read_flag(FlagPosition::Break), true);
+        flags.write_flag(FlagPosition::Unused, true);
+        assert_eq!(flags.0, 0b0011_0000);
+        assert_eq!(flags.read_flag(FlagPosition::Unused), true);
+
+        flags.write_flag(FlagPosition::Break, false);
+        assert_eq!(flags.read_flag(FlagPosition::Break), false);
+        assert_eq!(u8::from(FlagPosition::Break), 4);
+        assert_eq!(u8::from(FlagPosition::Unused), 5);
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(FlagsRegister::new(0x00).to_string(), "nv-bdizc");
+        assert_eq!(FlagsRegister::new(0xFF).to_string(), "NV-bDIZC");
+        assert_eq!(FlagsRegister::new(0x83).to_string(), "Nv-bdiZC");
     }
 }
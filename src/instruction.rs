@@ -1,5 +1,7 @@
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 
+use crate::opcode_decoders::{ArgumentType, INSTRUCTIONS_ADDRESSING, INSTRUCTIONS_CYCLES};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum AddressingType {
     XIndexedZeroIndirect,
@@ -51,6 +53,8 @@ pub enum Instruction {
 
     BitZeroPage = 0x24,
     BitAbsolute = 0x2C,
+    // 65C02 only, a two-byte NOP on NMOS parts
+    BitImmediate = 0x89,
 
     Brk = 0x00,
 
@@ -200,4 +204,249 @@ pub enum Instruction {
     Txa = 0x8A,
     Txs = 0x9A,
     Tya = 0x98,
+
+    // Undocumented NMOS NOPs consuming two (DOP) or three (TOP) bytes
+    #[cfg(feature = "illegal_opcodes")]
+    Dop80 = 0x80,
+    #[cfg(feature = "illegal_opcodes")]
+    Dop82 = 0x82,
+    #[cfg(feature = "illegal_opcodes")]
+    DopC2 = 0xC2,
+    #[cfg(feature = "illegal_opcodes")]
+    DopE2 = 0xE2,
+    #[cfg(feature = "illegal_opcodes")]
+    Dop04 = 0x04,
+    #[cfg(feature = "illegal_opcodes")]
+    Dop44 = 0x44,
+    #[cfg(feature = "illegal_opcodes")]
+    Dop64 = 0x64,
+    #[cfg(feature = "illegal_opcodes")]
+    Dop14 = 0x14,
+    #[cfg(feature = "illegal_opcodes")]
+    Dop34 = 0x34,
+    #[cfg(feature = "illegal_opcodes")]
+    Dop54 = 0x54,
+    #[cfg(feature = "illegal_opcodes")]
+    Dop74 = 0x74,
+    #[cfg(feature = "illegal_opcodes")]
+    DopD4 = 0xD4,
+    #[cfg(feature = "illegal_opcodes")]
+    DopF4 = 0xF4,
+    #[cfg(feature = "illegal_opcodes")]
+    Top0C = 0x0C,
+    #[cfg(feature = "illegal_opcodes")]
+    Top1C = 0x1C,
+    #[cfg(feature = "illegal_opcodes")]
+    Top3C = 0x3C,
+    #[cfg(feature = "illegal_opcodes")]
+    Top5C = 0x5C,
+    #[cfg(feature = "illegal_opcodes")]
+    Top7C = 0x7C,
+    #[cfg(feature = "illegal_opcodes")]
+    TopDC = 0xDC,
+    #[cfg(feature = "illegal_opcodes")]
+    TopFC = 0xFC,
+
+    // Undocumented NMOS read-modify-write combinations: shift/rotate memory, then combine with A
+    #[cfg(feature = "illegal_opcodes")]
+    SloZeroPage = 0x07,
+    #[cfg(feature = "illegal_opcodes")]
+    SloXIndexedZero = 0x17,
+    #[cfg(feature = "illegal_opcodes")]
+    SloAbsolute = 0x0F,
+    #[cfg(feature = "illegal_opcodes")]
+    SloXIndexedAbsolute = 0x1F,
+    #[cfg(feature = "illegal_opcodes")]
+    SloYIndexedAbsolute = 0x1B,
+    #[cfg(feature = "illegal_opcodes")]
+    SloXIndexedZeroIndirect = 0x03,
+    #[cfg(feature = "illegal_opcodes")]
+    SloZeroIndirectIndexed = 0x13,
+    #[cfg(feature = "illegal_opcodes")]
+    RlaZeroPage = 0x27,
+    #[cfg(feature = "illegal_opcodes")]
+    RlaXIndexedZero = 0x37,
+    #[cfg(feature = "illegal_opcodes")]
+    RlaAbsolute = 0x2F,
+    #[cfg(feature = "illegal_opcodes")]
+    RlaXIndexedAbsolute = 0x3F,
+    #[cfg(feature = "illegal_opcodes")]
+    RlaYIndexedAbsolute = 0x3B,
+    #[cfg(feature = "illegal_opcodes")]
+    RlaXIndexedZeroIndirect = 0x23,
+    #[cfg(feature = "illegal_opcodes")]
+    RlaZeroIndirectIndexed = 0x33,
+    #[cfg(feature = "illegal_opcodes")]
+    SreZeroPage = 0x47,
+    #[cfg(feature = "illegal_opcodes")]
+    SreXIndexedZero = 0x57,
+    #[cfg(feature = "illegal_opcodes")]
+    SreAbsolute = 0x4F,
+    #[cfg(feature = "illegal_opcodes")]
+    SreXIndexedAbsolute = 0x5F,
+    #[cfg(feature = "illegal_opcodes")]
+    SreYIndexedAbsolute = 0x5B,
+    #[cfg(feature = "illegal_opcodes")]
+    SreXIndexedZeroIndirect = 0x43,
+    #[cfg(feature = "illegal_opcodes")]
+    SreZeroIndirectIndexed = 0x53,
+    #[cfg(feature = "illegal_opcodes")]
+    RraZeroPage = 0x67,
+    #[cfg(feature = "illegal_opcodes")]
+    RraXIndexedZero = 0x77,
+    #[cfg(feature = "illegal_opcodes")]
+    RraAbsolute = 0x6F,
+    #[cfg(feature = "illegal_opcodes")]
+    RraXIndexedAbsolute = 0x7F,
+    #[cfg(feature = "illegal_opcodes")]
+    RraYIndexedAbsolute = 0x7B,
+    #[cfg(feature = "illegal_opcodes")]
+    RraXIndexedZeroIndirect = 0x63,
+    #[cfg(feature = "illegal_opcodes")]
+    RraZeroIndirectIndexed = 0x73,
+}
+
+impl Instruction {
+    /// Whether `opcode` is a documented NMOS 6502 instruction. Undocumented opcodes
+    /// are not legal even when the `illegal_opcodes` feature implements them.
+    pub fn is_legal(opcode: u8) -> bool {
+        match Instruction::try_from(opcode) {
+            Ok(instr) => !instr.is_undocumented() && instr != Instruction::BitImmediate,
+            Err(_) => false,
+        }
+    }
+
+    fn is_undocumented(self) -> bool {
+        match self {
+            #[cfg(feature = "illegal_opcodes")]
+            Instruction::Dop80
+            | Instruction::Dop82
+            | Instruction::DopC2
+            | Instruction::DopE2
+            | Instruction::Dop04
+            | Instruction::Dop44
+            | Instruction::Dop64
+            | Instruction::Dop14
+            | Instruction::Dop34
+            | Instruction::Dop54
+            | Instruction::Dop74
+            | Instruction::DopD4
+            | Instruction::DopF4
+            | Instruction::Top0C
+            | Instruction::Top1C
+            | Instruction::Top3C
+            | Instruction::Top5C
+            | Instruction::Top7C
+            | Instruction::TopDC
+            | Instruction::TopFC
+            | Instruction::SloZeroPage
+            | Instruction::SloXIndexedZero
+            | Instruction::SloAbsolute
+            | Instruction::SloXIndexedAbsolute
+            | Instruction::SloYIndexedAbsolute
+            | Instruction::SloXIndexedZeroIndirect
+            | Instruction::SloZeroIndirectIndexed
+            | Instruction::RlaZeroPage
+            | Instruction::RlaXIndexedZero
+            | Instruction::RlaAbsolute
+            | Instruction::RlaXIndexedAbsolute
+            | Instruction::RlaYIndexedAbsolute
+            | Instruction::RlaXIndexedZeroIndirect
+            | Instruction::RlaZeroIndirectIndexed
+            | Instruction::SreZeroPage
+            | Instruction::SreXIndexedZero
+            | Instruction::SreAbsolute
+            | Instruction::SreXIndexedAbsolute
+            | Instruction::SreYIndexedAbsolute
+            | Instruction::SreXIndexedZeroIndirect
+            | Instruction::SreZeroIndirectIndexed
+            | Instruction::RraZeroPage
+            | Instruction::RraXIndexedZero
+            | Instruction::RraAbsolute
+            | Instruction::RraXIndexedAbsolute
+            | Instruction::RraYIndexedAbsolute
+            | Instruction::RraXIndexedZeroIndirect
+            | Instruction::RraZeroIndirectIndexed => true,
+            _ => false,
+        }
+    }
+}
+
+/// Documented base cycle count of `instr`, before page-cross and branch penalties.
+pub fn base_cycles(instr: Instruction) -> u8 {
+    *INSTRUCTIONS_CYCLES
+        .get(&instr)
+        .unwrap_or_else(|| panic!("No cycle count for opcode {instr:?}"))
+}
+
+/// Length in bytes (opcode plus operand) of the instruction encoded by `opcode`,
+/// or `None` if the byte doesn't decode to a known instruction.
+pub fn opcode_length(opcode: u8) -> Option<u8> {
+    let instr = Instruction::try_from(opcode).ok()?;
+
+    match INSTRUCTIONS_ADDRESSING.get(&instr)? {
+        ArgumentType::Void => Some(1),
+        ArgumentType::Byte => Some(2),
+        ArgumentType::Addr => Some(3),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base_cycles_known_values() {
+        assert_eq!(base_cycles(Instruction::LdaImmediate), 2);
+        assert_eq!(base_cycles(Instruction::JmpIndirect), 5);
+        assert_eq!(base_cycles(Instruction::Brk), 7);
+        assert_eq!(base_cycles(Instruction::StaXIndexedAbsolute), 5);
+        assert_eq!(base_cycles(Instruction::AslXIndexedAbsolute), 7);
+    }
+
+    #[test]
+    fn base_cycles_cover_all_instructions() {
+        for opcode in 0..=u8::MAX {
+            if let Ok(instr) = Instruction::try_from(opcode) {
+                assert!(INSTRUCTIONS_CYCLES.contains_key(&instr), "{instr:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn opcode_lengths() {
+        assert_eq!(opcode_length(0xEA), Some(1)); // NOP
+        assert_eq!(opcode_length(0x0A), Some(1)); // ASL A
+        assert_eq!(opcode_length(0x00), Some(1)); // BRK
+        assert_eq!(opcode_length(0xA9), Some(2)); // LDA #nn
+        assert_eq!(opcode_length(0xB1), Some(2)); // LDA (nn),Y
+        assert_eq!(opcode_length(0xD0), Some(2)); // BNE
+        assert_eq!(opcode_length(0xAD), Some(3)); // LDA nnnn
+        assert_eq!(opcode_length(0x6C), Some(3)); // JMP (nnnn)
+        assert_eq!(opcode_length(0x20), Some(3)); // JSR
+        assert_eq!(opcode_length(0x02), None); // KIL
+        assert_eq!(opcode_length(0xFF), None);
+    }
+
+    #[test]
+    #[cfg(feature = "illegal_opcodes")]
+    fn multi_byte_nop_lengths() {
+        assert_eq!(opcode_length(0x04), Some(2)); // DOP nn
+        assert_eq!(opcode_length(0x80), Some(2)); // DOP #nn
+        assert_eq!(opcode_length(0x0C), Some(3)); // TOP nnnn
+        assert_eq!(opcode_length(0x1C), Some(3)); // TOP nnnn,X
+        assert_eq!(base_cycles(Instruction::Dop14), 4);
+        assert_eq!(base_cycles(Instruction::Top0C), 4);
+    }
+
+    #[test]
+    fn legal_opcodes() {
+        for opcode in [0xA9, 0x00, 0x6C, 0xEA, 0x24] {
+            assert!(Instruction::is_legal(opcode), "{opcode:#04X}");
+        }
+        // Undocumented NMOS opcodes and the 65C02 BIT #imm
+        for opcode in [0x02, 0x07, 0x80, 0xFF, 0x89] {
+            assert!(!Instruction::is_legal(opcode), "{opcode:#04X}");
+        }
+    }
 }
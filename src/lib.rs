@@ -1,9 +1,71 @@
+#![cfg_attr(all(feature = "no_std", not(test)), no_std)]
+
 #[macro_use]
 extern crate lazy_static;
+#[cfg(feature = "no_std")]
+extern crate alloc;
 
+pub mod assembler;
 pub mod cpu;
+pub mod disassembler;
 pub mod error;
-mod flags_register;
-mod instruction;
+pub mod flags_register;
+pub mod instruction;
 pub mod memory_bus;
 mod opcode_decoders;
+pub mod util;
+
+use cpu::Cpu;
+use error::MemoryBusError;
+use memory_bus::{Bus, MemoryBus, MEM_SPACE_END};
+
+/// Builds a CPU on a flat 64K RAM bus with `program` written at `load_addr`
+/// and PC set to `entry`, ready to step. Fails if the program runs past $FFFF.
+pub fn emulator_from_program(
+    program: &[u8],
+    load_addr: u16,
+    entry: u16,
+) -> Result<Cpu, MemoryBusError> {
+    if load_addr as usize + program.len() > MEM_SPACE_END + 1 {
+        return Err(MemoryBusError::ROMLoadOutOfBounds);
+    }
+
+    let mut memory = MemoryBus::new();
+    memory.add_ram(0, MEM_SPACE_END + 1);
+    for (offset, byte) in program.iter().enumerate() {
+        memory.write(load_addr.wrapping_add(offset as u16), *byte);
+    }
+
+    let mut cpu = Cpu::new(memory);
+    cpu.set_pc(entry);
+
+    Ok(cpu)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emulator_from_program_steps() {
+        // LDA #$42; STA $0200
+        let mut cpu =
+            emulator_from_program(&[0xA9, 0x42, 0x8D, 0x00, 0x02], 0x0600, 0x0600).unwrap();
+
+        cpu.step();
+        assert_eq!(cpu.a, 0x42);
+        assert_eq!(cpu.pc, 0x0602);
+
+        cpu.step();
+        assert_eq!(cpu.read_memory(0x0200), 0x42);
+    }
+
+    #[test]
+    fn emulator_from_program_out_of_bounds() {
+        assert!(emulator_from_program(&[0xEA; 0x10], 0xFFF0, 0xFFF0).is_ok());
+        assert!(matches!(
+            emulator_from_program(&[0xEA; 0x11], 0xFFF0, 0xFFF0),
+            Err(MemoryBusError::ROMLoadOutOfBounds)
+        ));
+    }
+}
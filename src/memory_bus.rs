@@ -1,8 +1,55 @@
-use std::fmt::Debug;
+#[cfg(feature = "no_std")]
+use alloc::{
+    boxed::Box,
+    collections::VecDeque,
+    rc::Rc,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{
+    cell::{Cell, RefCell},
+    fmt::Debug,
+};
+#[cfg(not(feature = "no_std"))]
+use std::{collections::VecDeque, rc::Rc};
+
+use crate::error::{HexdumpError, MemoryBusError};
 
 pub const MEM_SPACE_END: usize = 0xFFFF;
 pub const STACK_BOTTOM: usize = 0x0100;
 
+/// Memory interface driven by the CPU.
+/// Implement it to plug in custom memory models, e.g. banked or access-recording ones.
+pub trait Bus {
+    fn read(&self, addr: u16) -> u8;
+    fn write(&mut self, addr: u16, val: u8);
+
+    /// Reads a byte without side effects, for debuggers. Defaults to `read`.
+    fn peek(&self, addr: u16) -> u8 {
+        self.read(addr)
+    }
+
+    /// Whether `addr` is backed by anything. Defaults to the whole address space.
+    fn is_mapped(&self, _addr: u16) -> bool {
+        true
+    }
+
+    /// Whether a write to `addr` is accepted rather than faulting. Defaults to true.
+    fn is_writable(&self, _addr: u16) -> bool {
+        true
+    }
+}
+
+/// Memory-mapped I/O device. Offsets are relative to the start of the mapped range.
+pub trait Device {
+    fn read(&mut self, offset: u16) -> u8;
+    fn write(&mut self, offset: u16, value: u8);
+}
+
+/// Supplies the value of a hooked address on every CPU read
+pub type ReadHook = Box<dyn FnMut() -> u8>;
+
 pub struct MemoryRegion {
     pub start: usize,
     pub end: usize,
@@ -10,52 +57,1028 @@ pub struct MemoryRegion {
     pub write_handler: Box<dyn FnMut(usize, u8)>,
 }
 
+/// Copies ROM image `data` to the start of the region backing `storage`.
+/// Fails instead of panicking when the image doesn't fit.
+pub fn load_rom(storage: &mut [u8], data: &[u8]) -> Result<(), MemoryBusError> {
+    if data.len() > storage.len() {
+        return Err(MemoryBusError::ROMLoadOutOfBounds);
+    }
+
+    storage[..data.len()].copy_from_slice(data);
+
+    Ok(())
+}
+
+/// Loads `address: bytes` lines such as `0600: A9 01 8D 00 02`, the hexdump
+/// format printed by easy6502 and many assembler listings. Addresses and bytes are
+/// hex, the address may carry a `$` prefix. Blank lines and lines starting with `;`
+/// are skipped. The whole text is parsed before anything is written.
+pub fn load_hexdump(mem: &mut MemoryBus, text: &str) -> Result<(), HexdumpError> {
+    let mut records = Vec::new();
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+
+        let invalid = || HexdumpError::InvalidLine(number + 1, line.to_string());
+        let (address, bytes) = line.split_once(':').ok_or_else(invalid)?;
+        let address = usize::from_str_radix(address.trim().trim_start_matches('$'), 16)
+            .map_err(|_| invalid())?;
+        let bytes = bytes
+            .split_whitespace()
+            .map(|byte| match byte.len() {
+                1 | 2 => u8::from_str_radix(byte, 16).map_err(|_| invalid()),
+                _ => Err(invalid()),
+            })
+            .collect::<Result<Vec<u8>, _>>()?;
+        if address + bytes.len() > MEM_SPACE_END + 1 {
+            return Err(invalid());
+        }
+
+        records.push((address, bytes));
+    }
+
+    for (address, bytes) in records {
+        for (offset, byte) in bytes.into_iter().enumerate() {
+            mem.try_write_byte(address + offset, byte)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Default)]
+struct RegionAttributes {
+    read_only: bool, // Added with add_rom
+    priority: i32,   // Higher priority regions shadow overlapping lower ones
+}
+
+#[derive(Default)]
+struct RegionStats {
+    reads: Cell<u64>,
+    writes: u64,
+}
+
+/// What a read from an address without a mapped region returns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnmappedReadPolicy {
+    #[default]
+    Panic,
+    OpenBus, // Last value on the bus, like a floating data bus on real hardware
+}
+
+/// What an access above the top decoded address does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PartialDecodePolicy {
+    #[default]
+    Mirror, // Undecoded high address lines are ignored, folding the access down
+    Fault, // The access is treated as unmapped
+}
+
+/// What a write to a region added with `MemoryBus::add_rom` does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RomWritePolicy {
+    #[default]
+    Ignore, // Dropped, as on hardware
+    Fault, // Rejected, to catch code clobbering its own program
+}
+
 pub struct MemoryBus {
     region_maps: Vec<MemoryRegion>,
+    region_stats: Vec<RegionStats>, // Access counters, indexed like region_maps
+    region_attributes: Vec<RegionAttributes>, // Indexed like region_maps
+    rom_write_policy: RomWritePolicy,
+    unmapped_read_policy: UnmappedReadPolicy,
+    last_bus_value: Cell<u8>,
+    top_address: usize, // Highest address decoded by the board
+    partial_decode_policy: PartialDecodePolicy,
+    read_hooks: Vec<(usize, RefCell<ReadHook>)>,
 }
 
 impl MemoryBus {
     pub fn new() -> MemoryBus {
         MemoryBus {
             region_maps: Vec::new(),
+            region_stats: Vec::new(),
+            region_attributes: Vec::new(),
+            rom_write_policy: RomWritePolicy::default(),
+            unmapped_read_policy: UnmappedReadPolicy::default(),
+            last_bus_value: Cell::new(0),
+            top_address: MEM_SPACE_END,
+            partial_decode_policy: PartialDecodePolicy::default(),
+            read_hooks: Vec::new(),
+        }
+    }
+
+    /// Bus for a board decoding only `0..=top_address`, e.g. `0x7FFF` for 32 KB.
+    /// Accesses above it are mirrored or fault according to `policy`.
+    pub fn with_top_address(top_address: usize, policy: PartialDecodePolicy) -> MemoryBus {
+        MemoryBus {
+            top_address,
+            partial_decode_policy: policy,
+            ..MemoryBus::new()
         }
     }
 
     pub fn add_region(&mut self, region: MemoryRegion) {
+        self.add_region_with_priority(region, 0);
+    }
+
+    /// Maps `region` over any lower-priority regions covering the same addresses,
+    /// e.g. RAM shadowing ROM. `add_region` uses priority 0; among regions with
+    /// equal priority the one added first wins.
+    pub fn add_region_with_priority(&mut self, region: MemoryRegion, priority: i32) {
         self.region_maps.push(region);
+        self.region_stats.push(RegionStats::default());
+        self.region_attributes.push(RegionAttributes {
+            read_only: false,
+            priority,
+        });
     }
 
-    pub fn read_byte(&self, address: usize) -> u8 {
-        println!("Read from addr {address:#X}");
-        let mapped_region: Option<&MemoryRegion> = self
-            .region_maps
+    /// Maps `size` bytes of zeroed RAM starting at `start`. A `size` of 0 maps nothing.
+    pub fn add_ram(&mut self, start: usize, size: usize) {
+        self.add_ram_filled(start, size, 0x00);
+    }
+
+    /// Maps `size` bytes of RAM starting at `start`, every byte set to `fill`.
+    pub fn add_ram_filled(&mut self, start: usize, size: usize, fill: u8) {
+        self.add_ram_with(start, vec![fill; size]);
+    }
+
+    /// Maps `size` bytes of RAM starting at `start` holding pseudo-random bytes,
+    /// like memory at power-up. The same `seed` always gives the same contents.
+    pub fn add_ram_random(&mut self, start: usize, size: usize, seed: u64) {
+        // splitmix64, any seed including 0 is fine
+        let mut state = seed;
+        let contents = (0..size)
+            .map(|_| {
+                state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+                let mut z = state;
+                z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+                (z ^ (z >> 31)) as u8
+            })
+            .collect();
+
+        self.add_ram_with(start, contents);
+    }
+
+    fn add_ram_with(&mut self, start: usize, contents: Vec<u8>) {
+        if contents.is_empty() {
+            return;
+        }
+
+        let end = start + contents.len() - 1;
+        let storage = Rc::new(RefCell::new(contents));
+        let write_storage = Rc::clone(&storage);
+
+        self.add_region(MemoryRegion {
+            start,
+            end,
+            read_handler: Box::new(move |offset| storage.borrow()[offset]),
+            write_handler: Box::new(move |offset, value| {
+                write_storage.borrow_mut()[offset] = value
+            }),
+        });
+    }
+
+    /// Maps `data` read-only starting at `start`. Writes to it follow the
+    /// `RomWritePolicy`.
+    pub fn add_rom(&mut self, start: usize, data: Vec<u8>) -> Result<(), MemoryBusError> {
+        if data.is_empty() || start + data.len() - 1 > MEM_SPACE_END {
+            return Err(MemoryBusError::ROMLoadOutOfBounds);
+        }
+
+        self.add_region(MemoryRegion {
+            start,
+            end: start + data.len() - 1,
+            read_handler: Box::new(move |offset| data[offset]),
+            write_handler: Box::new(|_, _| {}),
+        });
+        if let Some(attributes) = self.region_attributes.last_mut() {
+            attributes.read_only = true;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_rom_write_policy(&mut self, policy: RomWritePolicy) {
+        self.rom_write_policy = policy;
+    }
+
+    /// Maps `device` to the inclusive range `start..=end`.
+    pub fn add_device(&mut self, start: usize, end: usize, device: Box<dyn Device>) {
+        let device = Rc::new(RefCell::new(device));
+        let write_device = Rc::clone(&device);
+
+        self.add_region(MemoryRegion {
+            start,
+            end,
+            read_handler: Box::new(move |offset| device.borrow_mut().read(offset as u16)),
+            write_handler: Box::new(move |offset, value| {
+                write_device.borrow_mut().write(offset as u16, value)
+            }),
+        });
+    }
+
+    /// Maps a write-only character output port at `addr`. Each byte written is
+    /// appended to the returned string as a char; reads return 0.
+    pub fn add_char_output(&mut self, addr: usize) -> Rc<RefCell<String>> {
+        let output = Rc::new(RefCell::new(String::new()));
+        let port_output = Rc::clone(&output);
+
+        self.add_region(MemoryRegion {
+            start: addr,
+            end: addr,
+            read_handler: Box::new(|_| 0),
+            write_handler: Box::new(move |_, value| port_output.borrow_mut().push(value as char)),
+        });
+
+        output
+    }
+
+    /// Maps a read-only character input port at `addr`. Each read consumes the
+    /// next byte queued in the returned buffer, or returns 0 when it's empty.
+    pub fn add_char_input(&mut self, addr: usize) -> Rc<RefCell<VecDeque<u8>>> {
+        self.add_char_input_with_empty(addr, 0)
+    }
+
+    /// Like `add_char_input`, returning `empty` while no input is queued.
+    pub fn add_char_input_with_empty(
+        &mut self,
+        addr: usize,
+        empty: u8,
+    ) -> Rc<RefCell<VecDeque<u8>>> {
+        let input = Rc::new(RefCell::new(VecDeque::new()));
+        let port_input = Rc::clone(&input);
+
+        self.add_region(MemoryRegion {
+            start: addr,
+            end: addr,
+            read_handler: Box::new(move |_| port_input.borrow_mut().pop_front().unwrap_or(empty)),
+            write_handler: Box::new(|_, _| {}),
+        });
+
+        input
+    }
+
+    // Index of the region an access to `address` reaches, with the address as decoded
+    fn find_region(&self, address: usize) -> Option<(usize, usize)> {
+        let address = if address <= self.top_address {
+            address
+        } else {
+            match self.partial_decode_policy {
+                PartialDecodePolicy::Mirror => address % (self.top_address + 1),
+                PartialDecodePolicy::Fault => return None,
+            }
+        };
+
+        let mut found: Option<usize> = None;
+        for (index, region) in self.region_maps.iter().enumerate() {
+            if region.start > address || region.end < address {
+                continue;
+            }
+            let priority = self.region_attributes[index].priority;
+            if found.is_none_or(|best| priority > self.region_attributes[best].priority) {
+                found = Some(index);
+            }
+        }
+
+        found.map(|index| (index, address))
+    }
+
+    /// Answers reads of `address` with `hook` instead of the region handler, for
+    /// input registers whose value changes between reads. The address doesn't
+    /// need a region behind it. Replaces an earlier hook on the same address.
+    /// `peek` never runs hooks.
+    pub fn add_read_hook(&mut self, address: usize, hook: ReadHook) {
+        self.remove_read_hook(address);
+        self.read_hooks.push((address, RefCell::new(hook)));
+    }
+
+    pub fn remove_read_hook(&mut self, address: usize) {
+        self.read_hooks.retain(|(hooked, _)| *hooked != address);
+    }
+
+    fn read_hook(&self, address: usize) -> Option<&RefCell<ReadHook>> {
+        self.read_hooks
             .iter()
-            .find(|region| region.start <= address && region.end >= address);
+            .find(|(hooked, _)| *hooked == address)
+            .map(|(_, hook)| hook)
+    }
+
+    pub fn set_unmapped_read_policy(&mut self, policy: UnmappedReadPolicy) {
+        self.unmapped_read_policy = policy;
+    }
+
+    /// Last byte transferred by a read or write on the bus
+    pub fn last_bus_value(&self) -> u8 {
+        self.last_bus_value.get()
+    }
 
-        match mapped_region {
-            Some(region) => (region.read_handler)(address - region.start),
-            None => panic!("No region found for address {address:#X}"), // TODO: return Result to delegate error handling to the caller
+    fn read_unmapped(&self, address: usize) -> u8 {
+        match self.unmapped_read_policy {
+            UnmappedReadPolicy::Panic => panic!("No region found for address {address:#X}"), // TODO: return Result to delegate error handling to the caller
+            UnmappedReadPolicy::OpenBus => self.last_bus_value.get(),
+        }
+    }
+
+    pub fn is_mapped(&self, address: usize) -> bool {
+        self.find_region(address).is_some() || self.read_hook(address).is_some()
+    }
+
+    /// Region an access to `address` currently reaches, as its index in `regions()`
+    /// order and the offset its handlers receive. Accounts for priorities and
+    /// partial address decoding, `None` when nothing answers.
+    pub fn resolve(&self, address: usize) -> Option<(usize, usize)> {
+        self.find_region(address)
+            .map(|(index, address)| (index, address - self.region_maps[index].start))
+    }
+
+    pub fn read_byte(&self, address: usize) -> u8 {
+        if let Some(hook) = self.read_hook(address) {
+            let value = (hook.borrow_mut())();
+            self.last_bus_value.set(value);
+            return value;
+        }
+
+        let value = match self.find_region(address) {
+            Some((index, address)) => {
+                let reads = &self.region_stats[index].reads;
+                reads.set(reads.get() + 1);
+
+                let region = &self.region_maps[index];
+                (region.read_handler)(address - region.start)
+            }
+            None => self.read_unmapped(address),
+        };
+
+        self.last_bus_value.set(value);
+        value
+    }
+
+    /// Reads a byte without any bus side effects, for debuggers and monitors.
+    pub fn peek(&self, address: usize) -> u8 {
+        match self.find_region(address) {
+            Some((index, address)) => {
+                let region = &self.region_maps[index];
+                (region.read_handler)(address - region.start)
+            }
+            // A hooked input register with nothing behind it
+            None if self.read_hook(address).is_some() => self.last_bus_value.get(),
+            None => self.read_unmapped(address),
         }
     }
 
     pub fn write_byte(&mut self, address: usize, value: u8) {
-        println!("write {value:#X} to addr {address:#X}");
-        let mapped_region: Option<&mut MemoryRegion> = self
-            .region_maps
-            .iter_mut()
-            .find(|region| region.start <= address && region.end >= address);
-
-        match mapped_region {
-            Some(region) => (region.write_handler)(address - region.start, value),
+        match self.find_region(address) {
+            Some((index, address)) => {
+                if self.faults_rom_write(index) {
+                    panic!("Write to ROM address {address:#X}");
+                }
+                self.last_bus_value.set(value);
+                self.region_stats[index].writes += 1;
+                let region = &mut self.region_maps[index];
+                (region.write_handler)(address - region.start, value)
+            }
             None => panic!("No region found for address {address:#X}"),
         }
     }
+
+    /// Like `write_byte`, but returns an error instead of panicking for unmapped
+    /// addresses and, under `RomWritePolicy::Fault`, for ROM.
+    pub fn try_write_byte(&mut self, address: usize, value: u8) -> Result<(), MemoryBusError> {
+        let (index, _) = self
+            .find_region(address)
+            .ok_or(MemoryBusError::OffsetOutOfBounds(address))?;
+        if self.faults_rom_write(index) {
+            return Err(MemoryBusError::RomWrite(address));
+        }
+
+        self.write_byte(address, value);
+
+        Ok(())
+    }
+
+    /// False for ROM under `RomWritePolicy::Fault`, where `write_byte` would panic.
+    /// Unmapped addresses are left to `is_mapped`.
+    pub fn is_writable(&self, address: usize) -> bool {
+        !matches!(self.find_region(address), Some((index, _)) if self.faults_rom_write(index))
+    }
+
+    fn faults_rom_write(&self, index: usize) -> bool {
+        self.region_attributes[index].read_only && self.rom_write_policy == RomWritePolicy::Fault
+    }
+
+    /// Writes `value` to every address in the inclusive range `start..=end`, each
+    /// through the region that maps it, like `write_byte`. Fails with the first
+    /// unmapped address, or the first address in ROM under `RomWritePolicy::Fault`,
+    /// before anything is written.
+    pub fn fill(&mut self, start: usize, end: usize, value: u8) -> Result<(), MemoryBusError> {
+        if end < start {
+            return Err(MemoryBusError::OffsetOutOfBounds(start));
+        }
+        for address in start..=end {
+            if !self.is_mapped(address) {
+                return Err(MemoryBusError::OffsetOutOfBounds(address));
+            }
+            if !self.is_writable(address) {
+                return Err(MemoryBusError::RomWrite(address));
+            }
+        }
+
+        for address in start..=end {
+            self.write_byte(address, value);
+        }
+
+        Ok(())
+    }
+
+    /// Copies `len` bytes from `src` to `dst`, reading with `peek`. Overlapping
+    /// ranges are handled like `memmove`. Fails with the first unmapped address,
+    /// or the first destination in ROM under `RomWritePolicy::Fault`, before
+    /// anything is written.
+    pub fn copy_region(
+        &mut self,
+        src: usize,
+        dst: usize,
+        len: usize,
+    ) -> Result<(), MemoryBusError> {
+        for offset in 0..len {
+            for address in [src + offset, dst + offset] {
+                if !self.is_mapped(address) {
+                    return Err(MemoryBusError::OffsetOutOfBounds(address));
+                }
+            }
+            if !self.is_writable(dst + offset) {
+                return Err(MemoryBusError::RomWrite(dst + offset));
+            }
+        }
+
+        let bytes: Vec<u8> = (src..src + len).map(|address| self.peek(address)).collect();
+        for (offset, byte) in bytes.into_iter().enumerate() {
+            self.write_byte(dst + offset, byte);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the bytes in the inclusive range `start..=end`, read with `peek`,
+    /// e.g. to save a framebuffer after a run. Fails with the first unmapped address.
+    pub fn dump_region(&self, start: usize, end: usize) -> Result<Vec<u8>, MemoryBusError> {
+        if end < start {
+            return Err(MemoryBusError::OffsetOutOfBounds(start));
+        }
+
+        (start..=end)
+            .map(|address| match self.is_mapped(address) {
+                true => Ok(self.peek(address)),
+                false => Err(MemoryBusError::OffsetOutOfBounds(address)),
+            })
+            .collect()
+    }
+
+    /// FNV-1a hash of the inclusive range `start..=end`, read with `peek`.
+    /// Unmapped addresses hash as 0xFF, so comparing checksums taken at the same
+    /// checkpoint of two runs quickly shows whether their memory diverged.
+    pub fn checksum(&self, start: usize, end: usize) -> u64 {
+        (start..=end).fold(0xCBF2_9CE4_8422_2325, |hash, address| {
+            let byte = if self.is_mapped(address) {
+                self.peek(address)
+            } else {
+                0xFF
+            };
+            (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01B3)
+        })
+    }
+
+    /// Yields `(start, end)` of every region, in insertion order.
+    pub fn regions(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.region_maps
+            .iter()
+            .map(|region| (region.start, region.end))
+    }
+
+    /// Returns `(start, end, reads, writes)` for every region, in insertion order.
+    /// `peek` accesses are not counted.
+    pub fn region_stats(&self) -> Vec<(usize, usize, u64, u64)> {
+        self.region_maps
+            .iter()
+            .zip(self.region_stats.iter())
+            .map(|(region, stats)| (region.start, region.end, stats.reads.get(), stats.writes))
+            .collect()
+    }
+}
+
+impl Bus for MemoryBus {
+    fn read(&self, addr: u16) -> u8 {
+        self.read_byte(addr as usize)
+    }
+
+    fn write(&mut self, addr: u16, val: u8) {
+        self.write_byte(addr as usize, val)
+    }
+
+    fn peek(&self, addr: u16) -> u8 {
+        MemoryBus::peek(self, addr as usize)
+    }
+
+    fn is_mapped(&self, addr: u16) -> bool {
+        MemoryBus::is_mapped(self, addr as usize)
+    }
+
+    fn is_writable(&self, addr: u16) -> bool {
+        MemoryBus::is_writable(self, addr as usize)
+    }
+}
+
+impl Default for MemoryBus {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Debug for MemoryBus {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         self.region_maps
             .iter()
             .try_for_each(|region| writeln!(f, "Region: {:#X} - {:#X}", region.start, region.end))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_rom_fits() {
+        let mut storage = [0u8; 0x10];
+
+        load_rom(&mut storage, &[0xA9, 0x01, 0xEA]).unwrap();
+        assert_eq!(storage[..4], [0xA9, 0x01, 0xEA, 0x00]);
+
+        load_rom(&mut storage, &[0xEA; 0x10]).unwrap();
+        assert_eq!(storage, [0xEA; 0x10]);
+    }
+
+    #[test]
+    fn load_rom_out_of_bounds() {
+        let mut storage = [0u8; 0x1000];
+        let data = vec![0xEA; 0x1001];
+
+        let result = load_rom(&mut storage, &data);
+        assert!(matches!(result, Err(MemoryBusError::ROMLoadOutOfBounds)));
+        assert_eq!(storage[0], 0x00);
+    }
+
+    #[test]
+    fn region_stats() {
+        let mut memory = MemoryBus::new();
+        memory.add_region(MemoryRegion {
+            start: 0x0000,
+            end: 0x00FF,
+            read_handler: Box::new(|_| 0),
+            write_handler: Box::new(|_, _| {}),
+        });
+        memory.add_region(MemoryRegion {
+            start: 0x0100,
+            end: 0x01FF,
+            read_handler: Box::new(|_| 0),
+            write_handler: Box::new(|_, _| {}),
+        });
+
+        for address in 0x0100..0x0105 {
+            memory.read_byte(address);
+        }
+        memory.write_byte(0x01FF, 0x42);
+        memory.write_byte(0x0100, 0x42);
+        memory.read_byte(0x0010);
+        memory.peek(0x0010);
+
+        assert_eq!(
+            memory.region_stats(),
+            vec![(0x0000, 0x00FF, 1, 0), (0x0100, 0x01FF, 5, 2)]
+        );
+    }
+
+    #[test]
+    fn open_bus_read() {
+        let mut memory = MemoryBus::new();
+        memory.add_region(MemoryRegion {
+            start: 0x0000,
+            end: 0x00FF,
+            read_handler: Box::new(|addr| addr as u8),
+            write_handler: Box::new(|_, _| {}),
+        });
+        memory.set_unmapped_read_policy(UnmappedReadPolicy::OpenBus);
+
+        memory.write_byte(0x0010, 0x42);
+        assert_eq!(memory.last_bus_value(), 0x42);
+        assert_eq!(memory.read_byte(0x8000), 0x42);
+
+        assert_eq!(memory.read_byte(0x0017), 0x17);
+        assert_eq!(memory.read_byte(0x8000), 0x17);
+        assert_eq!(memory.peek(0x8000), 0x17);
+
+        // Peeking doesn't drive the bus
+        assert_eq!(memory.peek(0x0020), 0x20);
+        assert_eq!(memory.last_bus_value(), 0x17);
+    }
+
+    #[test]
+    #[should_panic(expected = "No region found for address 0x8000")]
+    fn unmapped_read_panics_by_default() {
+        let memory = MemoryBus::new();
+        memory.read_byte(0x8000);
+    }
+
+    #[test]
+    fn device() {
+        // Returns the last written byte from any offset
+        struct Latch(u8);
+
+        impl Device for Latch {
+            fn read(&mut self, _offset: u16) -> u8 {
+                self.0
+            }
+
+            fn write(&mut self, _offset: u16, value: u8) {
+                self.0 = value;
+            }
+        }
+
+        let mut memory = MemoryBus::new();
+        memory.add_device(0xD000, 0xD00F, Box::new(Latch(0)));
+
+        assert_eq!(memory.read_byte(0xD000), 0x00);
+        memory.write_byte(0xD003, 0x42);
+        assert_eq!(memory.read_byte(0xD000), 0x42);
+        assert_eq!(memory.read_byte(0xD00F), 0x42);
+        assert!(!memory.is_mapped(0xD010));
+    }
+
+    #[test]
+    fn fill() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0x0200, 0x100);
+
+        memory.fill(0x0200, 0x02FF, 0xEA).unwrap();
+        for address in [0x0200, 0x0201, 0x027F, 0x02AA, 0x02FF] {
+            assert_eq!(memory.read_byte(address), 0xEA);
+        }
+
+        assert!(matches!(
+            memory.fill(0x0280, 0x0300, 0x00),
+            Err(MemoryBusError::OffsetOutOfBounds(0x0300))
+        ));
+        assert!(matches!(
+            memory.fill(0x0100, 0x0110, 0x00),
+            Err(MemoryBusError::OffsetOutOfBounds(0x0100))
+        ));
+        assert_eq!(memory.read_byte(0x0280), 0xEA);
+
+        // Each address goes to the region that maps it
+        memory.add_ram(0x0300, 0x100);
+        memory.fill(0x02F0, 0x030F, 0x42).unwrap();
+        assert_eq!(memory.read_byte(0x02F0), 0x42);
+        assert_eq!(memory.read_byte(0x030F), 0x42);
+        assert_eq!(memory.read_byte(0x0310), 0x00);
+
+        memory.add_rom(0x0400, vec![0xEA; 0x100]).unwrap();
+        memory.set_rom_write_policy(RomWritePolicy::Fault);
+        assert!(matches!(
+            memory.fill(0x03F0, 0x0400, 0x00),
+            Err(MemoryBusError::RomWrite(0x0400))
+        ));
+        assert_eq!(memory.read_byte(0x03F0), 0x00);
+        assert_eq!(memory.read_byte(0x0400), 0xEA);
+    }
+
+    #[test]
+    fn regions() {
+        let mut memory = MemoryBus::new();
+        for (start, end) in [(0x0000, 0x07FF), (0x8000, 0xFFFF), (0x6000, 0x600F)] {
+            memory.add_region(MemoryRegion {
+                start,
+                end,
+                read_handler: Box::new(|_| 0),
+                write_handler: Box::new(|_, _| {}),
+            });
+        }
+
+        assert_eq!(
+            memory.regions().collect::<Vec<_>>(),
+            vec![(0x0000, 0x07FF), (0x8000, 0xFFFF), (0x6000, 0x600F)]
+        );
+    }
+
+    #[test]
+    fn char_output() {
+        let mut memory = MemoryBus::new();
+        let output = memory.add_char_output(0xF001);
+
+        for byte in b"HI" {
+            memory.write_byte(0xF001, *byte);
+        }
+
+        assert_eq!(*output.borrow(), "HI");
+        assert_eq!(memory.read_byte(0xF001), 0);
+    }
+
+    #[test]
+    fn char_input() {
+        let mut memory = MemoryBus::new();
+        let input = memory.add_char_input_with_empty(0xF004, 0xFF);
+        input.borrow_mut().extend(b"OK");
+
+        assert_eq!(memory.read_byte(0xF004), b'O');
+        assert_eq!(memory.read_byte(0xF004), b'K');
+        assert_eq!(memory.read_byte(0xF004), 0xFF);
+
+        let input = memory.add_char_input(0xF005);
+        assert_eq!(memory.read_byte(0xF005), 0);
+        input.borrow_mut().push_back(b'A');
+        assert_eq!(memory.read_byte(0xF005), b'A');
+    }
+
+    #[test]
+    fn partial_address_decoding() {
+        let mut memory = MemoryBus::with_top_address(0x7FFF, PartialDecodePolicy::Mirror);
+        memory.add_ram(0x0000, 0x8000);
+        memory.write_byte(0x0001, 0x42);
+        assert_eq!(memory.read_byte(0x8001), 0x42);
+        memory.write_byte(0xFFFF, 0x24);
+        assert_eq!(memory.read_byte(0x7FFF), 0x24);
+        assert!(memory.is_mapped(0x8001));
+
+        let mut memory = MemoryBus::with_top_address(0x7FFF, PartialDecodePolicy::Fault);
+        memory.add_ram(0x0000, 0x8000);
+        memory.set_unmapped_read_policy(UnmappedReadPolicy::OpenBus);
+        memory.write_byte(0x0001, 0x42);
+        assert!(!memory.is_mapped(0x8001));
+        assert_eq!(memory.read_byte(0x0000), 0x00);
+        assert_eq!(memory.read_byte(0x8001), 0x00);
+    }
+
+    #[test]
+    fn checksum() {
+        let mut memory = MemoryBus::new();
+        memory.add_region(MemoryRegion {
+            start: 0x0000,
+            end: 0x00FF,
+            read_handler: Box::new(|_| 0x00),
+            write_handler: Box::new(|_, _| {}),
+        });
+        memory.add_ram(0x0200, 0x100);
+
+        let before = memory.checksum(0x0000, 0x02FF);
+        assert_eq!(memory.checksum(0x0000, 0x02FF), before);
+
+        memory.write_byte(0x0280, 0x01);
+        let after = memory.checksum(0x0000, 0x02FF);
+        assert_ne!(after, before);
+        assert_eq!(
+            memory.checksum(0x0000, 0x01FF),
+            memory.checksum(0x0000, 0x01FF)
+        );
+        assert_ne!(
+            memory.checksum(0x0000, 0x0000),
+            memory.checksum(0x0100, 0x0100)
+        );
+    }
+
+    #[test]
+    fn copy_region() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0x0000, 0x1000);
+        for (offset, byte) in [1, 2, 3, 4].into_iter().enumerate() {
+            memory.write_byte(0x0200 + offset, byte);
+        }
+
+        memory.copy_region(0x0200, 0x0800, 4).unwrap();
+        assert_eq!(memory.dump_region(0x0800, 0x0803).unwrap(), [1, 2, 3, 4]);
+        assert_eq!(memory.dump_region(0x0200, 0x0203).unwrap(), [1, 2, 3, 4]);
+
+        // Overlapping forward copy
+        memory.copy_region(0x0200, 0x0202, 4).unwrap();
+        assert_eq!(
+            memory.dump_region(0x0200, 0x0205).unwrap(),
+            [1, 2, 1, 2, 3, 4]
+        );
+
+        assert!(matches!(
+            memory.copy_region(0x0FFE, 0x0100, 4),
+            Err(MemoryBusError::OffsetOutOfBounds(0x1000))
+        ));
+        assert_eq!(memory.read_byte(0x0100), 0);
+    }
+
+    #[test]
+    fn copy_region_into_rom() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0x0000, 0x1000);
+        memory.add_rom(0x1000, vec![0xEA; 0x1000]).unwrap();
+        memory.write_byte(0x0200, 0x42);
+        memory.set_rom_write_policy(RomWritePolicy::Fault);
+
+        assert!(matches!(
+            memory.copy_region(0x0200, 0x0FFF, 2),
+            Err(MemoryBusError::RomWrite(0x1000))
+        ));
+        // Nothing is written, not even the bytes before the ROM
+        assert_eq!(memory.read_byte(0x0FFF), 0x00);
+        assert_eq!(memory.read_byte(0x1000), 0xEA);
+
+        // Ignored writes leave the ROM as it was
+        memory.set_rom_write_policy(RomWritePolicy::Ignore);
+        memory.copy_region(0x0200, 0x1000, 1).unwrap();
+        assert_eq!(memory.read_byte(0x1000), 0xEA);
+    }
+
+    #[test]
+    fn dump_region() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0x0200, 0x0400);
+        for (offset, byte) in (0..0x10).enumerate() {
+            memory.write_byte(0x0300 + offset, byte * 3);
+        }
+
+        let expected: Vec<u8> = (0..0x10).map(|byte| byte * 3).collect();
+        assert_eq!(memory.dump_region(0x0300, 0x030F).unwrap(), expected);
+        assert_eq!(memory.dump_region(0x0200, 0x0200).unwrap(), vec![0]);
+
+        assert!(matches!(
+            memory.dump_region(0x05FE, 0x0601),
+            Err(MemoryBusError::OffsetOutOfBounds(0x0600))
+        ));
+        assert!(matches!(
+            memory.dump_region(0x0300, 0x02FF),
+            Err(MemoryBusError::OffsetOutOfBounds(0x0300))
+        ));
+    }
+
+    #[test]
+    fn rom_write_policies() {
+        let mut memory = MemoryBus::new();
+        memory.add_rom(0xF000, vec![0xA9, 0x01]).unwrap();
+
+        memory.write_byte(0xF000, 0x00);
+        assert_eq!(memory.read_byte(0xF000), 0xA9);
+        assert!(memory.try_write_byte(0xF001, 0x00).is_ok());
+        assert_eq!(memory.read_byte(0xF001), 0x01);
+
+        assert!(memory.is_writable(0xF001));
+
+        memory.set_rom_write_policy(RomWritePolicy::Fault);
+        assert!(!memory.is_writable(0xF001));
+        assert!(matches!(
+            memory.try_write_byte(0xF001, 0x00),
+            Err(MemoryBusError::RomWrite(0xF001))
+        ));
+        assert_eq!(memory.read_byte(0xF001), 0x01);
+        assert!(matches!(
+            memory.try_write_byte(0xF002, 0x00),
+            Err(MemoryBusError::OffsetOutOfBounds(0xF002))
+        ));
+
+        assert!(matches!(
+            memory.add_rom(0xFFFF, vec![0; 2]),
+            Err(MemoryBusError::ROMLoadOutOfBounds)
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "Write to ROM address 0xF000")]
+    fn rom_write_faults() {
+        let mut memory = MemoryBus::new();
+        memory.add_rom(0xF000, vec![0xEA]).unwrap();
+        memory.set_rom_write_policy(RomWritePolicy::Fault);
+
+        memory.write_byte(0xF000, 0x00);
+    }
+
+    #[test]
+    fn region_priority() {
+        let mut memory = MemoryBus::new();
+        memory.add_region_with_priority(
+            MemoryRegion {
+                start: 0xE000,
+                end: 0xFFFF,
+                read_handler: Box::new(|_| 0xEA),
+                write_handler: Box::new(|_, _| {}),
+            },
+            -1,
+        );
+        memory.add_ram(0xE000, 0x2000);
+
+        memory.write_byte(0xE010, 0x42);
+        assert_eq!(memory.read_byte(0xE010), 0x42);
+        assert_eq!(memory.read_byte(0xE011), 0x00);
+
+        // A region added later with the default priority stays shadowed
+        memory.add_region(MemoryRegion {
+            start: 0xE000,
+            end: 0xE0FF,
+            read_handler: Box::new(|_| 0x00),
+            write_handler: Box::new(|_, _| {}),
+        });
+        assert_eq!(memory.read_byte(0xE010), 0x42);
+    }
+
+    #[test]
+    fn add_ram() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0x0000, 0);
+        memory.add_ram(0x0200, 0x100);
+
+        assert_eq!(memory.regions().collect::<Vec<_>>(), vec![(0x0200, 0x02FF)]);
+        memory.write_byte(0x02FF, 0x42);
+        assert_eq!(memory.read_byte(0x02FF), 0x42);
+        assert_eq!(memory.read_byte(0x0200), 0x00);
+    }
+
+    #[test]
+    fn resolve() {
+        let mut memory = MemoryBus::with_top_address(0x7FFF, PartialDecodePolicy::Mirror);
+        memory.add_ram(0x0000, 0x2000);
+        memory.add_rom(0x6000, vec![0xEA; 0x2000]).unwrap();
+        memory.add_region_with_priority(
+            MemoryRegion {
+                start: 0x7000,
+                end: 0x7FFF,
+                read_handler: Box::new(|_| 0x00),
+                write_handler: Box::new(|_, _| {}),
+            },
+            1,
+        );
+
+        assert_eq!(memory.resolve(0x1234), Some((0, 0x1234)));
+        assert_eq!(memory.resolve(0x6010), Some((1, 0x0010)));
+        assert_eq!(memory.resolve(0x7010), Some((2, 0x0010)));
+        // Mirrored down from $E010
+        assert_eq!(memory.resolve(0xE010), Some((1, 0x0010)));
+        assert_eq!(memory.resolve(0x3000), None);
+    }
+
+    #[test]
+    fn add_ram_filled_and_random() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram_filled(0x0000, 0x100, 0xA5);
+        memory.add_ram_random(0x1000, 0x100, 42);
+        memory.add_ram_random(0x2000, 0x100, 42);
+        memory.add_ram_random(0x3000, 0x100, 43);
+
+        assert!((0x0000..0x0100).all(|address| memory.read_byte(address) == 0xA5));
+
+        let contents = |memory: &MemoryBus, start: usize| {
+            (start..start + 0x100)
+                .map(|address| memory.read_byte(address))
+                .collect::<Vec<_>>()
+        };
+        let random = contents(&memory, 0x1000);
+        assert_eq!(random, contents(&memory, 0x2000));
+        assert_ne!(random, contents(&memory, 0x3000));
+        assert!(random.iter().any(|&byte| byte != random[0]));
+    }
+
+    #[test]
+    fn hexdump() {
+        let mut memory = MemoryBus::new();
+        memory.add_ram(0x0000, 0x1000);
+
+        load_hexdump(
+            &mut memory,
+            "0600: A9 01 8D 00 02
+             ; comment
+             $0610: ea",
+        )
+        .unwrap();
+        assert_eq!(
+            (0x0600..0x0605)
+                .map(|a| memory.read_byte(a))
+                .collect::<Vec<_>>(),
+            vec![0xA9, 0x01, 0x8D, 0x00, 0x02]
+        );
+        assert_eq!(memory.read_byte(0x0610), 0xEA);
+
+        assert!(matches!(
+            load_hexdump(&mut memory, "0600 A9"),
+            Err(HexdumpError::InvalidLine(1, _))
+        ));
+        assert!(matches!(
+            load_hexdump(&mut memory, "0700: A9\n0701: 1FF"),
+            Err(HexdumpError::InvalidLine(2, _))
+        ));
+        // Nothing was written for the rejected text
+        assert_eq!(memory.read_byte(0x0700), 0x00);
+        assert!(matches!(
+            load_hexdump(&mut memory, "2000: 00"),
+            Err(HexdumpError::Write(MemoryBusError::OffsetOutOfBounds(
+                0x2000
+            )))
+        ));
+    }
+}
@@ -1,4 +1,7 @@
-use crate::instruction::Instruction;
+use crate::instruction::{AddressingType, Instruction};
+#[cfg(feature = "no_std")]
+use hashbrown::HashMap;
+#[cfg(not(feature = "no_std"))]
 use std::collections::HashMap;
 
 #[derive(Debug)]
@@ -46,6 +49,7 @@ lazy_static! {
 
         m.insert(Instruction::BitZeroPage, ArgumentType::Byte);
         m.insert(Instruction::BitAbsolute, ArgumentType::Addr);
+        m.insert(Instruction::BitImmediate, ArgumentType::Byte);
 
         m.insert(Instruction::Brk, ArgumentType::Void);
 
@@ -196,6 +200,924 @@ lazy_static! {
         m.insert(Instruction::Txs, ArgumentType::Void);
         m.insert(Instruction::Tya, ArgumentType::Void);
 
+        #[cfg(feature = "illegal_opcodes")]
+        {
+            m.insert(Instruction::Dop80, ArgumentType::Byte);
+            m.insert(Instruction::Dop82, ArgumentType::Byte);
+            m.insert(Instruction::DopC2, ArgumentType::Byte);
+            m.insert(Instruction::DopE2, ArgumentType::Byte);
+            m.insert(Instruction::Dop04, ArgumentType::Byte);
+            m.insert(Instruction::Dop44, ArgumentType::Byte);
+            m.insert(Instruction::Dop64, ArgumentType::Byte);
+            m.insert(Instruction::Dop14, ArgumentType::Byte);
+            m.insert(Instruction::Dop34, ArgumentType::Byte);
+            m.insert(Instruction::Dop54, ArgumentType::Byte);
+            m.insert(Instruction::Dop74, ArgumentType::Byte);
+            m.insert(Instruction::DopD4, ArgumentType::Byte);
+            m.insert(Instruction::DopF4, ArgumentType::Byte);
+            m.insert(Instruction::Top0C, ArgumentType::Addr);
+            m.insert(Instruction::Top1C, ArgumentType::Addr);
+            m.insert(Instruction::Top3C, ArgumentType::Addr);
+            m.insert(Instruction::Top5C, ArgumentType::Addr);
+            m.insert(Instruction::Top7C, ArgumentType::Addr);
+            m.insert(Instruction::TopDC, ArgumentType::Addr);
+            m.insert(Instruction::TopFC, ArgumentType::Addr);
+            m.insert(Instruction::SloZeroPage, ArgumentType::Byte);
+            m.insert(Instruction::SloXIndexedZero, ArgumentType::Byte);
+            m.insert(Instruction::SloAbsolute, ArgumentType::Addr);
+            m.insert(Instruction::SloXIndexedAbsolute, ArgumentType::Addr);
+            m.insert(Instruction::SloYIndexedAbsolute, ArgumentType::Addr);
+            m.insert(Instruction::SloXIndexedZeroIndirect, ArgumentType::Byte);
+            m.insert(Instruction::SloZeroIndirectIndexed, ArgumentType::Byte);
+            m.insert(Instruction::RlaZeroPage, ArgumentType::Byte);
+            m.insert(Instruction::RlaXIndexedZero, ArgumentType::Byte);
+            m.insert(Instruction::RlaAbsolute, ArgumentType::Addr);
+            m.insert(Instruction::RlaXIndexedAbsolute, ArgumentType::Addr);
+            m.insert(Instruction::RlaYIndexedAbsolute, ArgumentType::Addr);
+            m.insert(Instruction::RlaXIndexedZeroIndirect, ArgumentType::Byte);
+            m.insert(Instruction::RlaZeroIndirectIndexed, ArgumentType::Byte);
+            m.insert(Instruction::SreZeroPage, ArgumentType::Byte);
+            m.insert(Instruction::SreXIndexedZero, ArgumentType::Byte);
+            m.insert(Instruction::SreAbsolute, ArgumentType::Addr);
+            m.insert(Instruction::SreXIndexedAbsolute, ArgumentType::Addr);
+            m.insert(Instruction::SreYIndexedAbsolute, ArgumentType::Addr);
+            m.insert(Instruction::SreXIndexedZeroIndirect, ArgumentType::Byte);
+            m.insert(Instruction::SreZeroIndirectIndexed, ArgumentType::Byte);
+            m.insert(Instruction::RraZeroPage, ArgumentType::Byte);
+            m.insert(Instruction::RraXIndexedZero, ArgumentType::Byte);
+            m.insert(Instruction::RraAbsolute, ArgumentType::Addr);
+            m.insert(Instruction::RraXIndexedAbsolute, ArgumentType::Addr);
+            m.insert(Instruction::RraYIndexedAbsolute, ArgumentType::Addr);
+            m.insert(Instruction::RraXIndexedZeroIndirect, ArgumentType::Byte);
+            m.insert(Instruction::RraZeroIndirectIndexed, ArgumentType::Byte);
+        }
+
+        m
+    };
+}
+
+lazy_static! {
+    pub static ref INSTRUCTIONS_ADDRESSING_MODE: HashMap<Instruction, AddressingType> = {
+        let mut m = HashMap::new();
+        m.insert(
+            Instruction::AdcXIndexedZeroIndirect,
+            AddressingType::XIndexedZeroIndirect,
+        );
+        m.insert(Instruction::AdcZeroPage, AddressingType::ZeroPage);
+        m.insert(Instruction::AdcImmediate, AddressingType::Immediate);
+        m.insert(Instruction::AdcAbsolute, AddressingType::Absolute);
+        m.insert(
+            Instruction::AdcZeroIndirectIndexed,
+            AddressingType::ZeroIndirectIndexed,
+        );
+        m.insert(Instruction::AdcXIndexedZero, AddressingType::XIndexedZero);
+        m.insert(
+            Instruction::AdcYIndexedAbsolute,
+            AddressingType::YIndexedAbsolute,
+        );
+        m.insert(
+            Instruction::AdcXIndexedAbsolute,
+            AddressingType::XIndexedAbsolute,
+        );
+
+        m.insert(
+            Instruction::AndXIndexedZeroIndirect,
+            AddressingType::XIndexedZeroIndirect,
+        );
+        m.insert(Instruction::AndZeroPage, AddressingType::ZeroPage);
+        m.insert(Instruction::AndImmediate, AddressingType::Immediate);
+        m.insert(Instruction::AndAbsolute, AddressingType::Absolute);
+        m.insert(
+            Instruction::AndZeroIndirectIndexed,
+            AddressingType::ZeroIndirectIndexed,
+        );
+        m.insert(Instruction::AndXIndexedZero, AddressingType::XIndexedZero);
+        m.insert(
+            Instruction::AndYIndexedAbsolute,
+            AddressingType::YIndexedAbsolute,
+        );
+        m.insert(
+            Instruction::AndXIndexedAbsolute,
+            AddressingType::XIndexedAbsolute,
+        );
+
+        m.insert(Instruction::AslAbsolute, AddressingType::Absolute);
+        m.insert(Instruction::AslZeroPage, AddressingType::ZeroPage);
+        m.insert(Instruction::AslXIndexedZero, AddressingType::XIndexedZero);
+        m.insert(
+            Instruction::AslXIndexedAbsolute,
+            AddressingType::XIndexedAbsolute,
+        );
+
+        m.insert(Instruction::Bcc, AddressingType::Immediate);
+
+        m.insert(Instruction::Bcs, AddressingType::Immediate);
+
+        m.insert(Instruction::Beq, AddressingType::Immediate);
+
+        m.insert(Instruction::Bne, AddressingType::Immediate);
+
+        m.insert(Instruction::Bmi, AddressingType::Immediate);
+
+        m.insert(Instruction::Bpl, AddressingType::Immediate);
+
+        m.insert(Instruction::Bvc, AddressingType::Immediate);
+
+        m.insert(Instruction::Bvs, AddressingType::Immediate);
+
+        m.insert(Instruction::BitZeroPage, AddressingType::ZeroPage);
+        m.insert(Instruction::BitAbsolute, AddressingType::Absolute);
+        m.insert(Instruction::BitImmediate, AddressingType::Immediate);
+
+        m.insert(
+            Instruction::CmpXIndexedZeroIndirect,
+            AddressingType::XIndexedZeroIndirect,
+        );
+        m.insert(Instruction::CmpZeroPage, AddressingType::ZeroPage);
+        m.insert(Instruction::CmpImmediate, AddressingType::Immediate);
+        m.insert(Instruction::CmpAbsolute, AddressingType::Absolute);
+        m.insert(
+            Instruction::CmpZeroIndirectIndexed,
+            AddressingType::ZeroIndirectIndexed,
+        );
+        m.insert(Instruction::CmpXIndexedZero, AddressingType::XIndexedZero);
+        m.insert(
+            Instruction::CmpYIndexedAbsolute,
+            AddressingType::YIndexedAbsolute,
+        );
+        m.insert(
+            Instruction::CmpXIndexedAbsolute,
+            AddressingType::XIndexedAbsolute,
+        );
+
+        m.insert(Instruction::CpxZeroPage, AddressingType::ZeroPage);
+        m.insert(Instruction::CpxImmediate, AddressingType::Immediate);
+        m.insert(Instruction::CpxAbsolute, AddressingType::Absolute);
+
+        m.insert(Instruction::CpyZeroPage, AddressingType::ZeroPage);
+        m.insert(Instruction::CpyImmediate, AddressingType::Immediate);
+        m.insert(Instruction::CpyAbsolute, AddressingType::Absolute);
+
+        m.insert(Instruction::DecAbsolute, AddressingType::Absolute);
+        m.insert(Instruction::DecZeroPage, AddressingType::ZeroPage);
+        m.insert(Instruction::DecXIndexedZero, AddressingType::XIndexedZero);
+        m.insert(
+            Instruction::DecXIndexedAbsolute,
+            AddressingType::XIndexedAbsolute,
+        );
+
+        m.insert(
+            Instruction::EorXIndexedZeroIndirect,
+            AddressingType::XIndexedZeroIndirect,
+        );
+        m.insert(Instruction::EorZeroPage, AddressingType::ZeroPage);
+        m.insert(Instruction::EorImmediate, AddressingType::Immediate);
+        m.insert(Instruction::EorAbsolute, AddressingType::Absolute);
+        m.insert(
+            Instruction::EorZeroIndirectIndexed,
+            AddressingType::ZeroIndirectIndexed,
+        );
+        m.insert(Instruction::EorXIndexedZero, AddressingType::XIndexedZero);
+        m.insert(
+            Instruction::EorYIndexedAbsolute,
+            AddressingType::YIndexedAbsolute,
+        );
+        m.insert(
+            Instruction::EorXIndexedAbsolute,
+            AddressingType::XIndexedAbsolute,
+        );
+
+        m.insert(Instruction::IncAbsolute, AddressingType::Absolute);
+        m.insert(Instruction::IncZeroPage, AddressingType::ZeroPage);
+        m.insert(Instruction::IncXIndexedZero, AddressingType::XIndexedZero);
+        m.insert(
+            Instruction::IncXIndexedAbsolute,
+            AddressingType::XIndexedAbsolute,
+        );
+
+        m.insert(
+            Instruction::LdaXIndexedZeroIndirect,
+            AddressingType::XIndexedZeroIndirect,
+        );
+        m.insert(Instruction::LdaZeroPage, AddressingType::ZeroPage);
+        m.insert(Instruction::LdaImmediate, AddressingType::Immediate);
+        m.insert(Instruction::LdaAbsolute, AddressingType::Absolute);
+        m.insert(
+            Instruction::LdaZeroIndirectIndexed,
+            AddressingType::ZeroIndirectIndexed,
+        );
+        m.insert(Instruction::LdaXIndexedZero, AddressingType::XIndexedZero);
+        m.insert(
+            Instruction::LdaYIndexedAbsolute,
+            AddressingType::YIndexedAbsolute,
+        );
+        m.insert(
+            Instruction::LdaXIndexedAbsolute,
+            AddressingType::XIndexedAbsolute,
+        );
+
+        m.insert(Instruction::LdxZeroPage, AddressingType::ZeroPage);
+        m.insert(Instruction::LdxImmediate, AddressingType::Immediate);
+        m.insert(Instruction::LdxAbsolute, AddressingType::Absolute);
+        m.insert(
+            Instruction::LdxYIndexedAbsolute,
+            AddressingType::YIndexedAbsolute,
+        );
+        m.insert(Instruction::LdxYIndexedZero, AddressingType::YIndexedZero);
+
+        m.insert(Instruction::LdyZeroPage, AddressingType::ZeroPage);
+        m.insert(Instruction::LdyImmediate, AddressingType::Immediate);
+        m.insert(Instruction::LdyAbsolute, AddressingType::Absolute);
+        m.insert(
+            Instruction::LdyXIndexedAbsolute,
+            AddressingType::XIndexedAbsolute,
+        );
+        m.insert(Instruction::LdyXIndexedZero, AddressingType::XIndexedZero);
+
+        m.insert(Instruction::LsrAbsolute, AddressingType::Absolute);
+        m.insert(Instruction::LsrZeroPage, AddressingType::ZeroPage);
+        m.insert(
+            Instruction::LsrXIndexedAbsolute,
+            AddressingType::XIndexedAbsolute,
+        );
+        m.insert(Instruction::LsrXIndexedZero, AddressingType::XIndexedZero);
+
+        m.insert(
+            Instruction::OraXIndexedZeroIndirect,
+            AddressingType::XIndexedZeroIndirect,
+        );
+        m.insert(Instruction::OraZeroPage, AddressingType::ZeroPage);
+        m.insert(Instruction::OraImmediate, AddressingType::Immediate);
+        m.insert(Instruction::OraAbsolute, AddressingType::Absolute);
+        m.insert(
+            Instruction::OraZeroIndirectIndexed,
+            AddressingType::ZeroIndirectIndexed,
+        );
+        m.insert(Instruction::OraXIndexedZero, AddressingType::XIndexedZero);
+        m.insert(
+            Instruction::OraYIndexedAbsolute,
+            AddressingType::YIndexedAbsolute,
+        );
+        m.insert(
+            Instruction::OraXIndexedAbsolute,
+            AddressingType::XIndexedAbsolute,
+        );
+
+        m.insert(Instruction::RolAbsolute, AddressingType::Absolute);
+        m.insert(Instruction::RolZeroPage, AddressingType::ZeroPage);
+        m.insert(Instruction::RolXIndexedZero, AddressingType::XIndexedZero);
+        m.insert(
+            Instruction::RolXIndexedAbsolute,
+            AddressingType::XIndexedAbsolute,
+        );
+
+        m.insert(Instruction::RorAbsolute, AddressingType::Absolute);
+        m.insert(Instruction::RorZeroPage, AddressingType::ZeroPage);
+        m.insert(Instruction::RorXIndexedZero, AddressingType::XIndexedZero);
+        m.insert(
+            Instruction::RorXIndexedAbsolute,
+            AddressingType::XIndexedAbsolute,
+        );
+
+        m.insert(
+            Instruction::SbcXIndexedZeroIndirect,
+            AddressingType::XIndexedZeroIndirect,
+        );
+        m.insert(Instruction::SbcZeroPage, AddressingType::ZeroPage);
+        m.insert(Instruction::SbcImmediate, AddressingType::Immediate);
+        m.insert(Instruction::SbcAbsolute, AddressingType::Absolute);
+        m.insert(
+            Instruction::SbcZeroIndirectIndexed,
+            AddressingType::ZeroIndirectIndexed,
+        );
+        m.insert(Instruction::SbcXIndexedZero, AddressingType::XIndexedZero);
+        m.insert(
+            Instruction::SbcYIndexedAbsolute,
+            AddressingType::YIndexedAbsolute,
+        );
+        m.insert(
+            Instruction::SbcXIndexedAbsolute,
+            AddressingType::XIndexedAbsolute,
+        );
+
+        m.insert(
+            Instruction::StaXIndexedZeroIndirect,
+            AddressingType::XIndexedZeroIndirect,
+        );
+        m.insert(Instruction::StaZeroPage, AddressingType::ZeroPage);
+        m.insert(Instruction::StaAbsolute, AddressingType::Absolute);
+        m.insert(
+            Instruction::StaZeroIndirectIndexed,
+            AddressingType::ZeroIndirectIndexed,
+        );
+        m.insert(Instruction::StaXIndexedZero, AddressingType::XIndexedZero);
+        m.insert(
+            Instruction::StaYIndexedAbsolute,
+            AddressingType::YIndexedAbsolute,
+        );
+        m.insert(
+            Instruction::StaXIndexedAbsolute,
+            AddressingType::XIndexedAbsolute,
+        );
+
+        m.insert(Instruction::StxZeroPage, AddressingType::ZeroPage);
+        m.insert(Instruction::StxAbsolute, AddressingType::Absolute);
+        m.insert(Instruction::StxYIndexedZero, AddressingType::YIndexedZero);
+
+        m.insert(Instruction::StyZeroPage, AddressingType::ZeroPage);
+        m.insert(Instruction::StyAbsolute, AddressingType::Absolute);
+        m.insert(Instruction::StyXIndexedZero, AddressingType::XIndexedZero);
+
+        #[cfg(feature = "illegal_opcodes")]
+        {
+            m.insert(Instruction::Dop80, AddressingType::Immediate);
+            m.insert(Instruction::Dop82, AddressingType::Immediate);
+            m.insert(Instruction::DopC2, AddressingType::Immediate);
+            m.insert(Instruction::DopE2, AddressingType::Immediate);
+            m.insert(Instruction::Dop04, AddressingType::ZeroPage);
+            m.insert(Instruction::Dop44, AddressingType::ZeroPage);
+            m.insert(Instruction::Dop64, AddressingType::ZeroPage);
+            m.insert(Instruction::Dop14, AddressingType::XIndexedZero);
+            m.insert(Instruction::Dop34, AddressingType::XIndexedZero);
+            m.insert(Instruction::Dop54, AddressingType::XIndexedZero);
+            m.insert(Instruction::Dop74, AddressingType::XIndexedZero);
+            m.insert(Instruction::DopD4, AddressingType::XIndexedZero);
+            m.insert(Instruction::DopF4, AddressingType::XIndexedZero);
+            m.insert(Instruction::Top0C, AddressingType::Absolute);
+            m.insert(Instruction::Top1C, AddressingType::XIndexedAbsolute);
+            m.insert(Instruction::Top3C, AddressingType::XIndexedAbsolute);
+            m.insert(Instruction::Top5C, AddressingType::XIndexedAbsolute);
+            m.insert(Instruction::Top7C, AddressingType::XIndexedAbsolute);
+            m.insert(Instruction::TopDC, AddressingType::XIndexedAbsolute);
+            m.insert(Instruction::TopFC, AddressingType::XIndexedAbsolute);
+            m.insert(Instruction::SloZeroPage, AddressingType::ZeroPage);
+            m.insert(Instruction::SloXIndexedZero, AddressingType::XIndexedZero);
+            m.insert(Instruction::SloAbsolute, AddressingType::Absolute);
+            m.insert(
+                Instruction::SloXIndexedAbsolute,
+                AddressingType::XIndexedAbsolute,
+            );
+            m.insert(
+                Instruction::SloYIndexedAbsolute,
+                AddressingType::YIndexedAbsolute,
+            );
+            m.insert(
+                Instruction::SloXIndexedZeroIndirect,
+                AddressingType::XIndexedZeroIndirect,
+            );
+            m.insert(
+                Instruction::SloZeroIndirectIndexed,
+                AddressingType::ZeroIndirectIndexed,
+            );
+            m.insert(Instruction::RlaZeroPage, AddressingType::ZeroPage);
+            m.insert(Instruction::RlaXIndexedZero, AddressingType::XIndexedZero);
+            m.insert(Instruction::RlaAbsolute, AddressingType::Absolute);
+            m.insert(
+                Instruction::RlaXIndexedAbsolute,
+                AddressingType::XIndexedAbsolute,
+            );
+            m.insert(
+                Instruction::RlaYIndexedAbsolute,
+                AddressingType::YIndexedAbsolute,
+            );
+            m.insert(
+                Instruction::RlaXIndexedZeroIndirect,
+                AddressingType::XIndexedZeroIndirect,
+            );
+            m.insert(
+                Instruction::RlaZeroIndirectIndexed,
+                AddressingType::ZeroIndirectIndexed,
+            );
+            m.insert(Instruction::SreZeroPage, AddressingType::ZeroPage);
+            m.insert(Instruction::SreXIndexedZero, AddressingType::XIndexedZero);
+            m.insert(Instruction::SreAbsolute, AddressingType::Absolute);
+            m.insert(
+                Instruction::SreXIndexedAbsolute,
+                AddressingType::XIndexedAbsolute,
+            );
+            m.insert(
+                Instruction::SreYIndexedAbsolute,
+                AddressingType::YIndexedAbsolute,
+            );
+            m.insert(
+                Instruction::SreXIndexedZeroIndirect,
+                AddressingType::XIndexedZeroIndirect,
+            );
+            m.insert(
+                Instruction::SreZeroIndirectIndexed,
+                AddressingType::ZeroIndirectIndexed,
+            );
+            m.insert(Instruction::RraZeroPage, AddressingType::ZeroPage);
+            m.insert(Instruction::RraXIndexedZero, AddressingType::XIndexedZero);
+            m.insert(Instruction::RraAbsolute, AddressingType::Absolute);
+            m.insert(
+                Instruction::RraXIndexedAbsolute,
+                AddressingType::XIndexedAbsolute,
+            );
+            m.insert(
+                Instruction::RraYIndexedAbsolute,
+                AddressingType::YIndexedAbsolute,
+            );
+            m.insert(
+                Instruction::RraXIndexedZeroIndirect,
+                AddressingType::XIndexedZeroIndirect,
+            );
+            m.insert(
+                Instruction::RraZeroIndirectIndexed,
+                AddressingType::ZeroIndirectIndexed,
+            );
+        }
+
+        m
+    };
+}
+
+lazy_static! {
+    pub static ref INSTRUCTIONS_MNEMONIC: HashMap<Instruction, &'static str> = {
+        let mut m = HashMap::new();
+        m.insert(Instruction::AdcXIndexedZeroIndirect, "ADC");
+        m.insert(Instruction::AdcZeroPage, "ADC");
+        m.insert(Instruction::AdcImmediate, "ADC");
+        m.insert(Instruction::AdcAbsolute, "ADC");
+        m.insert(Instruction::AdcZeroIndirectIndexed, "ADC");
+        m.insert(Instruction::AdcXIndexedZero, "ADC");
+        m.insert(Instruction::AdcYIndexedAbsolute, "ADC");
+        m.insert(Instruction::AdcXIndexedAbsolute, "ADC");
+
+        m.insert(Instruction::AndXIndexedZeroIndirect, "AND");
+        m.insert(Instruction::AndZeroPage, "AND");
+        m.insert(Instruction::AndImmediate, "AND");
+        m.insert(Instruction::AndAbsolute, "AND");
+        m.insert(Instruction::AndZeroIndirectIndexed, "AND");
+        m.insert(Instruction::AndXIndexedZero, "AND");
+        m.insert(Instruction::AndYIndexedAbsolute, "AND");
+        m.insert(Instruction::AndXIndexedAbsolute, "AND");
+
+        m.insert(Instruction::AslAbsolute, "ASL");
+        m.insert(Instruction::AslZeroPage, "ASL");
+        m.insert(Instruction::AslAccumulator, "ASL");
+        m.insert(Instruction::AslXIndexedZero, "ASL");
+        m.insert(Instruction::AslXIndexedAbsolute, "ASL");
+
+        m.insert(Instruction::Bcc, "BCC");
+        m.insert(Instruction::Bcs, "BCS");
+        m.insert(Instruction::Beq, "BEQ");
+        m.insert(Instruction::Bne, "BNE");
+        m.insert(Instruction::Bmi, "BMI");
+        m.insert(Instruction::Bpl, "BPL");
+        m.insert(Instruction::Bvc, "BVC");
+        m.insert(Instruction::Bvs, "BVS");
+
+        m.insert(Instruction::BitZeroPage, "BIT");
+        m.insert(Instruction::BitAbsolute, "BIT");
+        m.insert(Instruction::BitImmediate, "BIT");
+
+        m.insert(Instruction::Brk, "BRK");
+
+        m.insert(Instruction::Clc, "CLC");
+        m.insert(Instruction::Cld, "CLD");
+        m.insert(Instruction::Cli, "CLI");
+        m.insert(Instruction::Clv, "CLV");
+
+        m.insert(Instruction::CmpXIndexedZeroIndirect, "CMP");
+        m.insert(Instruction::CmpZeroPage, "CMP");
+        m.insert(Instruction::CmpImmediate, "CMP");
+        m.insert(Instruction::CmpAbsolute, "CMP");
+        m.insert(Instruction::CmpZeroIndirectIndexed, "CMP");
+        m.insert(Instruction::CmpXIndexedZero, "CMP");
+        m.insert(Instruction::CmpYIndexedAbsolute, "CMP");
+        m.insert(Instruction::CmpXIndexedAbsolute, "CMP");
+
+        m.insert(Instruction::CpxZeroPage, "CPX");
+        m.insert(Instruction::CpxImmediate, "CPX");
+        m.insert(Instruction::CpxAbsolute, "CPX");
+
+        m.insert(Instruction::CpyZeroPage, "CPY");
+        m.insert(Instruction::CpyImmediate, "CPY");
+        m.insert(Instruction::CpyAbsolute, "CPY");
+
+        m.insert(Instruction::DecZeroPage, "DEC");
+        m.insert(Instruction::DecAbsolute, "DEC");
+        m.insert(Instruction::DecXIndexedZero, "DEC");
+        m.insert(Instruction::DecXIndexedAbsolute, "DEC");
+
+        m.insert(Instruction::Dex, "DEX");
+        m.insert(Instruction::Dey, "DEY");
+
+        m.insert(Instruction::EorXIndexedZeroIndirect, "EOR");
+        m.insert(Instruction::EorZeroPage, "EOR");
+        m.insert(Instruction::EorImmediate, "EOR");
+        m.insert(Instruction::EorAbsolute, "EOR");
+        m.insert(Instruction::EorZeroIndirectIndexed, "EOR");
+        m.insert(Instruction::EorXIndexedZero, "EOR");
+        m.insert(Instruction::EorYIndexedAbsolute, "EOR");
+        m.insert(Instruction::EorXIndexedAbsolute, "EOR");
+
+        m.insert(Instruction::IncZeroPage, "INC");
+        m.insert(Instruction::IncAbsolute, "INC");
+        m.insert(Instruction::IncXIndexedZero, "INC");
+        m.insert(Instruction::IncXIndexedAbsolute, "INC");
+
+        m.insert(Instruction::Inx, "INX");
+        m.insert(Instruction::Iny, "INY");
+
+        m.insert(Instruction::Jmp, "JMP");
+        m.insert(Instruction::JmpIndirect, "JMP");
+
+        m.insert(Instruction::Jsr, "JSR");
+
+        m.insert(Instruction::Nop, "NOP");
+
+        m.insert(Instruction::LdaXIndexedZeroIndirect, "LDA");
+        m.insert(Instruction::LdaZeroPage, "LDA");
+        m.insert(Instruction::LdaImmediate, "LDA");
+        m.insert(Instruction::LdaAbsolute, "LDA");
+        m.insert(Instruction::LdaZeroIndirectIndexed, "LDA");
+        m.insert(Instruction::LdaXIndexedZero, "LDA");
+        m.insert(Instruction::LdaYIndexedAbsolute, "LDA");
+        m.insert(Instruction::LdaXIndexedAbsolute, "LDA");
+
+        m.insert(Instruction::LdxZeroPage, "LDX");
+        m.insert(Instruction::LdxImmediate, "LDX");
+        m.insert(Instruction::LdxAbsolute, "LDX");
+        m.insert(Instruction::LdxYIndexedAbsolute, "LDX");
+        m.insert(Instruction::LdxYIndexedZero, "LDX");
+
+        m.insert(Instruction::LdyZeroPage, "LDY");
+        m.insert(Instruction::LdyImmediate, "LDY");
+        m.insert(Instruction::LdyAbsolute, "LDY");
+        m.insert(Instruction::LdyXIndexedAbsolute, "LDY");
+        m.insert(Instruction::LdyXIndexedZero, "LDY");
+
+        m.insert(Instruction::LsrAbsolute, "LSR");
+        m.insert(Instruction::LsrZeroPage, "LSR");
+        m.insert(Instruction::LsrAccumulator, "LSR");
+        m.insert(Instruction::LsrXIndexedZero, "LSR");
+        m.insert(Instruction::LsrXIndexedAbsolute, "LSR");
+
+        m.insert(Instruction::OraXIndexedZeroIndirect, "ORA");
+        m.insert(Instruction::OraZeroPage, "ORA");
+        m.insert(Instruction::OraImmediate, "ORA");
+        m.insert(Instruction::OraAbsolute, "ORA");
+        m.insert(Instruction::OraZeroIndirectIndexed, "ORA");
+        m.insert(Instruction::OraXIndexedZero, "ORA");
+        m.insert(Instruction::OraYIndexedAbsolute, "ORA");
+        m.insert(Instruction::OraXIndexedAbsolute, "ORA");
+
+        m.insert(Instruction::Pha, "PHA");
+        m.insert(Instruction::Php, "PHP");
+        m.insert(Instruction::Pla, "PLA");
+        m.insert(Instruction::Plp, "PLP");
+
+        m.insert(Instruction::RolAbsolute, "ROL");
+        m.insert(Instruction::RolZeroPage, "ROL");
+        m.insert(Instruction::RolAccumulator, "ROL");
+        m.insert(Instruction::RolXIndexedZero, "ROL");
+        m.insert(Instruction::RolXIndexedAbsolute, "ROL");
+
+        m.insert(Instruction::RorAbsolute, "ROR");
+        m.insert(Instruction::RorZeroPage, "ROR");
+        m.insert(Instruction::RorAccumulator, "ROR");
+        m.insert(Instruction::RorXIndexedZero, "ROR");
+        m.insert(Instruction::RorXIndexedAbsolute, "ROR");
+
+        m.insert(Instruction::Rti, "RTI");
+
+        m.insert(Instruction::Rts, "RTS");
+
+        m.insert(Instruction::SbcXIndexedZeroIndirect, "SBC");
+        m.insert(Instruction::SbcZeroPage, "SBC");
+        m.insert(Instruction::SbcImmediate, "SBC");
+        m.insert(Instruction::SbcAbsolute, "SBC");
+        m.insert(Instruction::SbcZeroIndirectIndexed, "SBC");
+        m.insert(Instruction::SbcXIndexedZero, "SBC");
+        m.insert(Instruction::SbcYIndexedAbsolute, "SBC");
+        m.insert(Instruction::SbcXIndexedAbsolute, "SBC");
+
+        m.insert(Instruction::Sec, "SEC");
+        m.insert(Instruction::Sed, "SED");
+        m.insert(Instruction::Sei, "SEI");
+
+        m.insert(Instruction::StaXIndexedZeroIndirect, "STA");
+        m.insert(Instruction::StaZeroPage, "STA");
+        m.insert(Instruction::StaAbsolute, "STA");
+        m.insert(Instruction::StaZeroIndirectIndexed, "STA");
+        m.insert(Instruction::StaXIndexedZero, "STA");
+        m.insert(Instruction::StaYIndexedAbsolute, "STA");
+        m.insert(Instruction::StaXIndexedAbsolute, "STA");
+
+        m.insert(Instruction::StxZeroPage, "STX");
+        m.insert(Instruction::StxAbsolute, "STX");
+        m.insert(Instruction::StxYIndexedZero, "STX");
+
+        m.insert(Instruction::StyZeroPage, "STY");
+        m.insert(Instruction::StyAbsolute, "STY");
+        m.insert(Instruction::StyXIndexedZero, "STY");
+
+        m.insert(Instruction::Tax, "TAX");
+        m.insert(Instruction::Tay, "TAY");
+        m.insert(Instruction::Tsx, "TSX");
+        m.insert(Instruction::Txa, "TXA");
+        m.insert(Instruction::Txs, "TXS");
+        m.insert(Instruction::Tya, "TYA");
+
+        #[cfg(feature = "illegal_opcodes")]
+        {
+            m.insert(Instruction::Dop80, "DOP");
+            m.insert(Instruction::Dop82, "DOP");
+            m.insert(Instruction::DopC2, "DOP");
+            m.insert(Instruction::DopE2, "DOP");
+            m.insert(Instruction::Dop04, "DOP");
+            m.insert(Instruction::Dop44, "DOP");
+            m.insert(Instruction::Dop64, "DOP");
+            m.insert(Instruction::Dop14, "DOP");
+            m.insert(Instruction::Dop34, "DOP");
+            m.insert(Instruction::Dop54, "DOP");
+            m.insert(Instruction::Dop74, "DOP");
+            m.insert(Instruction::DopD4, "DOP");
+            m.insert(Instruction::DopF4, "DOP");
+            m.insert(Instruction::Top0C, "TOP");
+            m.insert(Instruction::Top1C, "TOP");
+            m.insert(Instruction::Top3C, "TOP");
+            m.insert(Instruction::Top5C, "TOP");
+            m.insert(Instruction::Top7C, "TOP");
+            m.insert(Instruction::TopDC, "TOP");
+            m.insert(Instruction::TopFC, "TOP");
+            m.insert(Instruction::SloZeroPage, "SLO");
+            m.insert(Instruction::SloXIndexedZero, "SLO");
+            m.insert(Instruction::SloAbsolute, "SLO");
+            m.insert(Instruction::SloXIndexedAbsolute, "SLO");
+            m.insert(Instruction::SloYIndexedAbsolute, "SLO");
+            m.insert(Instruction::SloXIndexedZeroIndirect, "SLO");
+            m.insert(Instruction::SloZeroIndirectIndexed, "SLO");
+            m.insert(Instruction::RlaZeroPage, "RLA");
+            m.insert(Instruction::RlaXIndexedZero, "RLA");
+            m.insert(Instruction::RlaAbsolute, "RLA");
+            m.insert(Instruction::RlaXIndexedAbsolute, "RLA");
+            m.insert(Instruction::RlaYIndexedAbsolute, "RLA");
+            m.insert(Instruction::RlaXIndexedZeroIndirect, "RLA");
+            m.insert(Instruction::RlaZeroIndirectIndexed, "RLA");
+            m.insert(Instruction::SreZeroPage, "SRE");
+            m.insert(Instruction::SreXIndexedZero, "SRE");
+            m.insert(Instruction::SreAbsolute, "SRE");
+            m.insert(Instruction::SreXIndexedAbsolute, "SRE");
+            m.insert(Instruction::SreYIndexedAbsolute, "SRE");
+            m.insert(Instruction::SreXIndexedZeroIndirect, "SRE");
+            m.insert(Instruction::SreZeroIndirectIndexed, "SRE");
+            m.insert(Instruction::RraZeroPage, "RRA");
+            m.insert(Instruction::RraXIndexedZero, "RRA");
+            m.insert(Instruction::RraAbsolute, "RRA");
+            m.insert(Instruction::RraXIndexedAbsolute, "RRA");
+            m.insert(Instruction::RraYIndexedAbsolute, "RRA");
+            m.insert(Instruction::RraXIndexedZeroIndirect, "RRA");
+            m.insert(Instruction::RraZeroIndirectIndexed, "RRA");
+        }
+
+        m
+    };
+}
+
+lazy_static! {
+    pub static ref INSTRUCTIONS_CYCLES: HashMap<Instruction, u8> = {
+        let mut m = HashMap::new();
+        m.insert(Instruction::AdcXIndexedZeroIndirect, 6);
+        m.insert(Instruction::AdcZeroPage, 3);
+        m.insert(Instruction::AdcImmediate, 2);
+        m.insert(Instruction::AdcAbsolute, 4);
+        m.insert(Instruction::AdcZeroIndirectIndexed, 5);
+        m.insert(Instruction::AdcXIndexedZero, 4);
+        m.insert(Instruction::AdcYIndexedAbsolute, 4);
+        m.insert(Instruction::AdcXIndexedAbsolute, 4);
+
+        m.insert(Instruction::AndXIndexedZeroIndirect, 6);
+        m.insert(Instruction::AndZeroPage, 3);
+        m.insert(Instruction::AndImmediate, 2);
+        m.insert(Instruction::AndAbsolute, 4);
+        m.insert(Instruction::AndZeroIndirectIndexed, 5);
+        m.insert(Instruction::AndXIndexedZero, 4);
+        m.insert(Instruction::AndYIndexedAbsolute, 4);
+        m.insert(Instruction::AndXIndexedAbsolute, 4);
+
+        m.insert(Instruction::AslAbsolute, 6);
+        m.insert(Instruction::AslZeroPage, 5);
+        m.insert(Instruction::AslAccumulator, 2);
+        m.insert(Instruction::AslXIndexedZero, 6);
+        m.insert(Instruction::AslXIndexedAbsolute, 7);
+
+        m.insert(Instruction::Bcc, 2);
+        m.insert(Instruction::Bcs, 2);
+        m.insert(Instruction::Beq, 2);
+        m.insert(Instruction::Bne, 2);
+        m.insert(Instruction::Bmi, 2);
+        m.insert(Instruction::Bpl, 2);
+        m.insert(Instruction::Bvc, 2);
+        m.insert(Instruction::Bvs, 2);
+
+        m.insert(Instruction::BitZeroPage, 3);
+        m.insert(Instruction::BitAbsolute, 4);
+        m.insert(Instruction::BitImmediate, 2);
+
+        m.insert(Instruction::Brk, 7);
+
+        m.insert(Instruction::Clc, 2);
+        m.insert(Instruction::Cld, 2);
+        m.insert(Instruction::Cli, 2);
+        m.insert(Instruction::Clv, 2);
+
+        m.insert(Instruction::CmpXIndexedZeroIndirect, 6);
+        m.insert(Instruction::CmpZeroPage, 3);
+        m.insert(Instruction::CmpImmediate, 2);
+        m.insert(Instruction::CmpAbsolute, 4);
+        m.insert(Instruction::CmpZeroIndirectIndexed, 5);
+        m.insert(Instruction::CmpXIndexedZero, 4);
+        m.insert(Instruction::CmpYIndexedAbsolute, 4);
+        m.insert(Instruction::CmpXIndexedAbsolute, 4);
+
+        m.insert(Instruction::CpxZeroPage, 3);
+        m.insert(Instruction::CpxImmediate, 2);
+        m.insert(Instruction::CpxAbsolute, 4);
+
+        m.insert(Instruction::CpyZeroPage, 3);
+        m.insert(Instruction::CpyImmediate, 2);
+        m.insert(Instruction::CpyAbsolute, 4);
+
+        m.insert(Instruction::DecZeroPage, 5);
+        m.insert(Instruction::DecAbsolute, 6);
+        m.insert(Instruction::DecXIndexedZero, 6);
+        m.insert(Instruction::DecXIndexedAbsolute, 7);
+
+        m.insert(Instruction::Dex, 2);
+        m.insert(Instruction::Dey, 2);
+
+        m.insert(Instruction::EorXIndexedZeroIndirect, 6);
+        m.insert(Instruction::EorZeroPage, 3);
+        m.insert(Instruction::EorImmediate, 2);
+        m.insert(Instruction::EorAbsolute, 4);
+        m.insert(Instruction::EorZeroIndirectIndexed, 5);
+        m.insert(Instruction::EorXIndexedZero, 4);
+        m.insert(Instruction::EorYIndexedAbsolute, 4);
+        m.insert(Instruction::EorXIndexedAbsolute, 4);
+
+        m.insert(Instruction::IncZeroPage, 5);
+        m.insert(Instruction::IncAbsolute, 6);
+        m.insert(Instruction::IncXIndexedZero, 6);
+        m.insert(Instruction::IncXIndexedAbsolute, 7);
+
+        m.insert(Instruction::Inx, 2);
+        m.insert(Instruction::Iny, 2);
+
+        m.insert(Instruction::Jmp, 3);
+        m.insert(Instruction::JmpIndirect, 5);
+
+        m.insert(Instruction::Jsr, 6);
+
+        m.insert(Instruction::Nop, 2);
+
+        m.insert(Instruction::LdaXIndexedZeroIndirect, 6);
+        m.insert(Instruction::LdaZeroPage, 3);
+        m.insert(Instruction::LdaImmediate, 2);
+        m.insert(Instruction::LdaAbsolute, 4);
+        m.insert(Instruction::LdaZeroIndirectIndexed, 5);
+        m.insert(Instruction::LdaXIndexedZero, 4);
+        m.insert(Instruction::LdaYIndexedAbsolute, 4);
+        m.insert(Instruction::LdaXIndexedAbsolute, 4);
+
+        m.insert(Instruction::LdxZeroPage, 3);
+        m.insert(Instruction::LdxImmediate, 2);
+        m.insert(Instruction::LdxAbsolute, 4);
+        m.insert(Instruction::LdxYIndexedAbsolute, 4);
+        m.insert(Instruction::LdxYIndexedZero, 4);
+
+        m.insert(Instruction::LdyZeroPage, 3);
+        m.insert(Instruction::LdyImmediate, 2);
+        m.insert(Instruction::LdyAbsolute, 4);
+        m.insert(Instruction::LdyXIndexedAbsolute, 4);
+        m.insert(Instruction::LdyXIndexedZero, 4);
+
+        m.insert(Instruction::LsrAbsolute, 6);
+        m.insert(Instruction::LsrZeroPage, 5);
+        m.insert(Instruction::LsrAccumulator, 2);
+        m.insert(Instruction::LsrXIndexedZero, 6);
+        m.insert(Instruction::LsrXIndexedAbsolute, 7);
+
+        m.insert(Instruction::OraXIndexedZeroIndirect, 6);
+        m.insert(Instruction::OraZeroPage, 3);
+        m.insert(Instruction::OraImmediate, 2);
+        m.insert(Instruction::OraAbsolute, 4);
+        m.insert(Instruction::OraZeroIndirectIndexed, 5);
+        m.insert(Instruction::OraXIndexedZero, 4);
+        m.insert(Instruction::OraYIndexedAbsolute, 4);
+        m.insert(Instruction::OraXIndexedAbsolute, 4);
+
+        m.insert(Instruction::Pha, 3);
+        m.insert(Instruction::Php, 3);
+        m.insert(Instruction::Pla, 4);
+        m.insert(Instruction::Plp, 4);
+
+        m.insert(Instruction::RolAbsolute, 6);
+        m.insert(Instruction::RolZeroPage, 5);
+        m.insert(Instruction::RolAccumulator, 2);
+        m.insert(Instruction::RolXIndexedZero, 6);
+        m.insert(Instruction::RolXIndexedAbsolute, 7);
+
+        m.insert(Instruction::RorAbsolute, 6);
+        m.insert(Instruction::RorZeroPage, 5);
+        m.insert(Instruction::RorAccumulator, 2);
+        m.insert(Instruction::RorXIndexedZero, 6);
+        m.insert(Instruction::RorXIndexedAbsolute, 7);
+
+        m.insert(Instruction::Rti, 6);
+
+        m.insert(Instruction::Rts, 6);
+
+        m.insert(Instruction::SbcXIndexedZeroIndirect, 6);
+        m.insert(Instruction::SbcZeroPage, 3);
+        m.insert(Instruction::SbcImmediate, 2);
+        m.insert(Instruction::SbcAbsolute, 4);
+        m.insert(Instruction::SbcZeroIndirectIndexed, 5);
+        m.insert(Instruction::SbcXIndexedZero, 4);
+        m.insert(Instruction::SbcYIndexedAbsolute, 4);
+        m.insert(Instruction::SbcXIndexedAbsolute, 4);
+
+        m.insert(Instruction::Sec, 2);
+        m.insert(Instruction::Sed, 2);
+        m.insert(Instruction::Sei, 2);
+
+        m.insert(Instruction::StaXIndexedZeroIndirect, 6);
+        m.insert(Instruction::StaZeroPage, 3);
+        m.insert(Instruction::StaAbsolute, 4);
+        m.insert(Instruction::StaZeroIndirectIndexed, 6);
+        m.insert(Instruction::StaXIndexedZero, 4);
+        m.insert(Instruction::StaYIndexedAbsolute, 5);
+        m.insert(Instruction::StaXIndexedAbsolute, 5);
+
+        m.insert(Instruction::StxZeroPage, 3);
+        m.insert(Instruction::StxAbsolute, 4);
+        m.insert(Instruction::StxYIndexedZero, 4);
+
+        m.insert(Instruction::StyZeroPage, 3);
+        m.insert(Instruction::StyAbsolute, 4);
+        m.insert(Instruction::StyXIndexedZero, 4);
+
+        m.insert(Instruction::Tax, 2);
+        m.insert(Instruction::Tay, 2);
+        m.insert(Instruction::Tsx, 2);
+        m.insert(Instruction::Txa, 2);
+        m.insert(Instruction::Txs, 2);
+        m.insert(Instruction::Tya, 2);
+
+        #[cfg(feature = "illegal_opcodes")]
+        {
+            m.insert(Instruction::Dop80, 2);
+            m.insert(Instruction::Dop82, 2);
+            m.insert(Instruction::DopC2, 2);
+            m.insert(Instruction::DopE2, 2);
+            m.insert(Instruction::Dop04, 3);
+            m.insert(Instruction::Dop44, 3);
+            m.insert(Instruction::Dop64, 3);
+            m.insert(Instruction::Dop14, 4);
+            m.insert(Instruction::Dop34, 4);
+            m.insert(Instruction::Dop54, 4);
+            m.insert(Instruction::Dop74, 4);
+            m.insert(Instruction::DopD4, 4);
+            m.insert(Instruction::DopF4, 4);
+            m.insert(Instruction::Top0C, 4);
+            m.insert(Instruction::Top1C, 4);
+            m.insert(Instruction::Top3C, 4);
+            m.insert(Instruction::Top5C, 4);
+            m.insert(Instruction::Top7C, 4);
+            m.insert(Instruction::TopDC, 4);
+            m.insert(Instruction::TopFC, 4);
+            m.insert(Instruction::SloZeroPage, 5);
+            m.insert(Instruction::SloXIndexedZero, 6);
+            m.insert(Instruction::SloAbsolute, 6);
+            m.insert(Instruction::SloXIndexedAbsolute, 7);
+            m.insert(Instruction::SloYIndexedAbsolute, 7);
+            m.insert(Instruction::SloXIndexedZeroIndirect, 8);
+            m.insert(Instruction::SloZeroIndirectIndexed, 8);
+            m.insert(Instruction::RlaZeroPage, 5);
+            m.insert(Instruction::RlaXIndexedZero, 6);
+            m.insert(Instruction::RlaAbsolute, 6);
+            m.insert(Instruction::RlaXIndexedAbsolute, 7);
+            m.insert(Instruction::RlaYIndexedAbsolute, 7);
+            m.insert(Instruction::RlaXIndexedZeroIndirect, 8);
+            m.insert(Instruction::RlaZeroIndirectIndexed, 8);
+            m.insert(Instruction::SreZeroPage, 5);
+            m.insert(Instruction::SreXIndexedZero, 6);
+            m.insert(Instruction::SreAbsolute, 6);
+            m.insert(Instruction::SreXIndexedAbsolute, 7);
+            m.insert(Instruction::SreYIndexedAbsolute, 7);
+            m.insert(Instruction::SreXIndexedZeroIndirect, 8);
+            m.insert(Instruction::SreZeroIndirectIndexed, 8);
+            m.insert(Instruction::RraZeroPage, 5);
+            m.insert(Instruction::RraXIndexedZero, 6);
+            m.insert(Instruction::RraAbsolute, 6);
+            m.insert(Instruction::RraXIndexedAbsolute, 7);
+            m.insert(Instruction::RraYIndexedAbsolute, 7);
+            m.insert(Instruction::RraXIndexedZeroIndirect, 8);
+            m.insert(Instruction::RraZeroIndirectIndexed, 8);
+        }
+
         m
     };
 }
@@ -0,0 +1,34 @@
+//! Little-endian helpers shared by the CPU, assembler and loaders
+
+/// Combines the low and high byte of a 6502 word
+pub fn dword_from_bytes(low_byte: u8, high_byte: u8) -> u16 {
+    u16::from(high_byte) << 8 | u16::from(low_byte)
+}
+
+/// Splits a word into `(low, high)`, the order it is stored in memory
+pub fn bytes_from_dword(value: u16) -> (u8, u8) {
+    (value as u8, (value >> 8) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dword_from_bytes_combines_little_endian() {
+        assert_eq!(dword_from_bytes(0x34, 0x12), 0x1234);
+        assert_eq!(dword_from_bytes(0xFF, 0x00), 0x00FF);
+        assert_eq!(dword_from_bytes(0x00, 0xFF), 0xFF00);
+    }
+
+    #[test]
+    fn bytes_from_dword_splits_little_endian() {
+        assert_eq!(bytes_from_dword(0x1234), (0x34, 0x12));
+        assert_eq!(bytes_from_dword(0xFF00), (0x00, 0xFF));
+
+        for value in [0x0000, 0x00FF, 0xABCD, 0xFFFF] {
+            let (low, high) = bytes_from_dword(value);
+            assert_eq!(dword_from_bytes(low, high), value);
+        }
+    }
+}
@@ -0,0 +1,42 @@
+//! Runs Klaus Dormann's 6502 functional test suite.
+//!
+//! Build `6502_functional_test.bin` from https://github.com/Klaus2m5/6502_65C02_functional_tests
+//! (or take the prebuilt image from its `bin_files` directory), drop it into `tests/roms/`
+//! and run `cargo test --features functional_test`. Set `FUNCTIONAL_TEST_BIN` to load
+//! the image from another location.
+
+use mos_6502::emulator_from_program;
+
+const LOAD_ADDRESS: u16 = 0x0000;
+const START_ADDRESS: u16 = 0x0400;
+const SUCCESS_ADDRESS: u16 = 0x3469;
+const MAX_INSTRUCTIONS: u64 = 100_000_000;
+
+#[test]
+fn klaus_dormann_functional_test() {
+    let path = std::env::var("FUNCTIONAL_TEST_BIN").unwrap_or_else(|_| {
+        concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/roms/6502_functional_test.bin"
+        )
+        .to_string()
+    });
+    let image = std::fs::read(&path)
+        .unwrap_or_else(|err| panic!("Failed to read functional test image {path}: {err}"));
+
+    let mut cpu = emulator_from_program(&image, LOAD_ADDRESS, START_ADDRESS).unwrap();
+    cpu.s = 0xFF;
+
+    // The suite signals both success and failure by jumping to itself
+    for _ in 0..MAX_INSTRUCTIONS {
+        let pc = cpu.pc;
+        cpu.step();
+
+        if cpu.pc == pc {
+            assert_eq!(pc, SUCCESS_ADDRESS, "Trapped at {pc:#06X}\n{cpu:?}");
+            return;
+        }
+    }
+
+    panic!("No trap reached after {MAX_INSTRUCTIONS} instructions\n{cpu:?}");
+}
@@ -0,0 +1,36 @@
+//! Drives the core the way a bare-metal host would: RAM is a fixed array behind
+//! the `Bus` trait, no `MemoryBus` closures. Building this test with
+//! `--features no_std` checks that the library compiles against core and alloc.
+
+use mos_6502::{
+    cpu::{Cpu, StopReason},
+    memory_bus::Bus,
+};
+
+struct ArrayRam([u8; 0x10000]);
+
+impl Bus for ArrayRam {
+    fn read(&self, addr: u16) -> u8 {
+        self.0[addr as usize]
+    }
+
+    fn write(&mut self, addr: u16, val: u8) {
+        self.0[addr as usize] = val;
+    }
+}
+
+#[test]
+fn runs_on_array_ram() {
+    let mut ram = ArrayRam([0; 0x10000]);
+    // LDX #$03; loop: TXA; STA $10,X; DEX; BNE loop; BRK
+    ram.0[0x0600..0x0609].copy_from_slice(&[0xA2, 0x03, 0x8A, 0x95, 0x10, 0xCA, 0xD0, 0xFA, 0x00]);
+
+    let mut cpu = Cpu::new(ram);
+    cpu.set_pc(0x0600);
+
+    assert_eq!(cpu.run(1_000), StopReason::Brk);
+    assert_eq!(cpu.x, 0x00);
+    assert_eq!(cpu.read_memory(0x0013), 0x03);
+    assert_eq!(cpu.read_memory(0x0012), 0x02);
+    assert_eq!(cpu.read_memory(0x0011), 0x01);
+}
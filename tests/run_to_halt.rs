@@ -0,0 +1,56 @@
+use mos_6502::{
+    assembler::assemble,
+    cpu::{Cpu, StopReason},
+    emulator_from_program,
+};
+
+fn cpu_with_program(source: &str) -> Cpu {
+    let program = assemble(source, 0x0600).unwrap();
+
+    let mut cpu = emulator_from_program(&program, 0x0600, 0x0600).unwrap();
+    cpu.s = 0xFF;
+
+    cpu
+}
+
+#[test]
+fn runs_until_brk() {
+    // Sums 5 + 4 + 3 + 2 + 1 into $10
+    let mut cpu = cpu_with_program(
+        "LDX #$05
+        LDA #$00
+        CLC
+        STX $11
+        ADC $11
+        DEX
+        BNE $0605
+        STA $10
+        BRK",
+    );
+
+    assert_eq!(cpu.run(1_000), StopReason::Brk);
+    assert_eq!(cpu.read_memory(0x10), 15);
+    assert_eq!(cpu.x, 0);
+    assert_eq!(cpu.pc, 0x060E);
+}
+
+#[test]
+fn stops_on_infinite_loop() {
+    let mut cpu = cpu_with_program("LDA #$01\nJMP $0602");
+
+    assert_eq!(cpu.run(1_000), StopReason::InfiniteLoop(0x0602));
+    assert_eq!(cpu.a, 0x01);
+}
+
+#[test]
+fn stops_on_illegal_opcode_and_cycle_limit() {
+    let mut cpu = cpu_with_program("NOP\nNOP");
+    cpu.write_memory(0x0602, 0x02); // KIL
+
+    assert_eq!(cpu.run(1_000), StopReason::IllegalOpcode(0x02));
+    assert_eq!(cpu.pc, 0x0602);
+
+    let mut cpu = cpu_with_program("NOP\nNOP\nNOP\nBRK");
+    assert_eq!(cpu.run(4), StopReason::CycleLimit);
+    assert_eq!(cpu.pc, 0x0602);
+}